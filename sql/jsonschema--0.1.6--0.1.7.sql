@@ -0,0 +1,1117 @@
+-- jsonschema_is_valid(schema::text)
+CREATE FUNCTION jsonschema_is_valid(
+	"schema" TEXT /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'text_schema_is_valid_wrapper';
+
+-- jsonschema_is_valid(id::text, VARIADIC schema::text)
+CREATE FUNCTION jsonschema_is_valid(
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC TEXT[] /* pgrx::datum::array::VariadicArray<&str> */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'text_schema_id_is_valid_wrapper';
+
+-- jsonschema_validates(data::text, schema::text)
+CREATE FUNCTION jsonschema_validates(
+	"data" TEXT, /* &str */
+	"schema" TEXT /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'text_schema_validates_text_wrapper';
+
+-- jsonschema_validates(data::text, id::text, VARIADIC schema::text)
+CREATE FUNCTION jsonschema_validates(
+	"data" TEXT, /* &str */
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC TEXT[] /* pgrx::datum::array::VariadicArray<&str> */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'text_schema_id_validates_text_wrapper';
+
+-- jsonschema_validates(data::bytea, format::text, schema::json)
+CREATE FUNCTION jsonschema_validates(
+	"data" bytea, /* &[u8] */
+	"format" TEXT, /* &str */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'bytea_schema_validates_json_wrapper';
+
+-- jsonschema_validates(data::bytea, format::text, schema::jsonb)
+CREATE FUNCTION jsonschema_validates(
+	"data" bytea, /* &[u8] */
+	"format" TEXT, /* &str */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'bytea_schema_validates_jsonb_wrapper';
+
+-- jsonschema_validates(data::bytea, format::text, id::text, VARIADIC schema::json)
+CREATE FUNCTION jsonschema_validates(
+	"data" bytea, /* &[u8] */
+	"format" TEXT, /* &str */
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC json[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::Json> */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'bytea_schema_id_validates_json_wrapper';
+
+-- jsonschema_validates(data::bytea, format::text, id::text, VARIADIC schema::jsonb)
+CREATE FUNCTION jsonschema_validates(
+	"data" bytea, /* &[u8] */
+	"format" TEXT, /* &str */
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC jsonb[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::JsonB> */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'bytea_schema_id_validates_jsonb_wrapper';
+
+-- jsonschema_is_valid_yaml(schema::text)
+CREATE FUNCTION jsonschema_is_valid_yaml(
+	"schema" TEXT /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'yaml_schema_is_valid_wrapper';
+
+-- jsonschema_is_valid_yaml(id::text, VARIADIC schema::text)
+CREATE FUNCTION jsonschema_is_valid_yaml(
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC TEXT[] /* pgrx::datum::array::VariadicArray<&str> */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'yaml_schema_id_is_valid_wrapper';
+
+-- jsonschema_validates_yaml(data::text, schema::text)
+CREATE FUNCTION jsonschema_validates_yaml(
+	"data" TEXT, /* &str */
+	"schema" TEXT /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'yaml_schema_validates_yaml_wrapper';
+
+-- jsonschema_validates_yaml(data::text, schema::json)
+CREATE FUNCTION jsonschema_validates_yaml(
+	"data" TEXT, /* &str */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'yaml_schema_validates_json_wrapper';
+
+-- jsonschema_validates_yaml(data::text, schema::jsonb)
+CREATE FUNCTION jsonschema_validates_yaml(
+	"data" TEXT, /* &str */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'yaml_schema_validates_jsonb_wrapper';
+
+-- jsonschema_validates_yaml(data::json, schema::text)
+CREATE FUNCTION jsonschema_validates_yaml(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schema" TEXT /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_validates_yaml_wrapper';
+
+-- jsonschema_validates_yaml(data::jsonb, schema::text)
+CREATE FUNCTION jsonschema_validates_yaml(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" TEXT /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_validates_yaml_wrapper';
+
+-- jsonschema_validates_yaml(data::text, id::text, VARIADIC schema::text)
+CREATE FUNCTION jsonschema_validates_yaml(
+	"data" TEXT, /* &str */
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC TEXT[] /* pgrx::datum::array::VariadicArray<&str> */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'yaml_schema_id_validates_yaml_wrapper';
+
+-- jsonschema_validates_yaml(data::text, id::text, VARIADIC schema::json)
+CREATE FUNCTION jsonschema_validates_yaml(
+	"data" TEXT, /* &str */
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC json[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::Json> */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'yaml_schema_id_validates_json_wrapper';
+
+-- jsonschema_validates_yaml(data::text, id::text, VARIADIC schema::jsonb)
+CREATE FUNCTION jsonschema_validates_yaml(
+	"data" TEXT, /* &str */
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC jsonb[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::JsonB> */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'yaml_schema_id_validates_jsonb_wrapper';
+
+-- jsonschema_validates_yaml(data::json, id::text, VARIADIC schema::text)
+CREATE FUNCTION jsonschema_validates_yaml(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC TEXT[] /* pgrx::datum::array::VariadicArray<&str> */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_id_validates_yaml_wrapper';
+
+-- jsonschema_validates_yaml(data::jsonb, id::text, VARIADIC schema::text)
+CREATE FUNCTION jsonschema_validates_yaml(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC TEXT[] /* pgrx::datum::array::VariadicArray<&str> */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_id_validates_yaml_wrapper';
+
+-- jsonschema_set is an array of JSON Schemas or an object mapping "$id"s to
+-- JSON Schemas.
+CREATE DOMAIN jsonschema_set AS jsonb
+CHECK (jsonb_typeof(VALUE) IN ('array', 'object'));
+
+CREATE FUNCTION jsonschema_is_valid_set(
+	"id" TEXT, /* &str */
+	"schemas" jsonschema_set /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'set_schema_id_is_valid_wrapper';
+
+CREATE FUNCTION jsonschema_validates_set(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"id" TEXT, /* &str */
+	"schemas" jsonschema_set /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_id_validates_set_wrapper';
+
+CREATE FUNCTION jsonschema_validates_set(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"id" TEXT, /* &str */
+	"schemas" jsonschema_set /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_id_validates_set_wrapper';
+
+-- jsonschema_set_append appends schema to the schemas array. Used by
+-- jsonschema_agg.
+CREATE FUNCTION jsonschema_set_append(
+	"schemas" jsonschema_set,
+	"schema" jsonb
+) RETURNS jsonschema_set
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE sql
+AS $$ SELECT ($1 || jsonb_build_array($2))::jsonschema_set $$;
+
+-- jsonschema_agg aggregates schemas into a jsonschema_set array, skipping
+-- NULLs.
+CREATE AGGREGATE jsonschema_agg(jsonb) (
+	SFUNC = jsonschema_set_append,
+	STYPE = jsonschema_set,
+	INITCOND = '[]',
+	PARALLEL = SAFE
+);
+
+CREATE FUNCTION "jsonschema_validate_trigger"()
+	RETURNS TRIGGER
+	LANGUAGE c
+	AS 'MODULE_PATHNAME', 'jsonschema_validate_trigger_wrapper';
+
+-- jsonschema_quarantine records rows that failed validation by
+-- jsonschema_validate_trigger() in quarantine mode, along with their errors.
+CREATE TABLE jsonschema_quarantine (
+	id             BIGINT      GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+	quarantined_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp(),
+	relation       REGCLASS    NOT NULL,
+	trigger_name   NAME        NOT NULL,
+	operation      TEXT        NOT NULL,
+	row_data       JSONB       NOT NULL,
+	errors         JSONB       NOT NULL
+);
+
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_quarantine', '');
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_quarantine_id_seq', '');
+
+-- jsonschema_registry stores JSON Schemas by their "$id"s. The typmod column,
+-- a hash of the "$id", identifies the schema in jsonschema_doc type modifiers.
+CREATE TABLE jsonschema_registry (
+	id     TEXT    PRIMARY KEY,
+	typmod INTEGER NOT NULL UNIQUE,
+	schema JSONB   NOT NULL
+);
+
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_registry', '');
+
+-- jsonschema_register(schema::jsonb)
+CREATE FUNCTION jsonschema_register(
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS TEXT /* alloc::string::String */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonschema_register_wrapper';
+
+-- jsonschema_unregister(id::text)
+CREATE FUNCTION jsonschema_unregister(
+	"id" TEXT /* &str */
+) RETURNS bool /* bool */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonschema_unregister_wrapper';
+
+-- jsonschema_doc is a jsonb value that must validate against the registered
+-- schema named by its type modifier.
+CREATE TYPE jsonschema_doc;
+
+CREATE FUNCTION jsonschema_doc_in(cstring, oid, integer) RETURNS jsonschema_doc
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'jsonschema_doc_in';
+
+CREATE FUNCTION jsonschema_doc_out(jsonschema_doc) RETURNS cstring
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE internal
+AS 'jsonb_out';
+
+CREATE FUNCTION jsonschema_doc_recv(internal, oid, integer) RETURNS jsonschema_doc
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'jsonschema_doc_recv';
+
+CREATE FUNCTION jsonschema_doc_send(jsonschema_doc) RETURNS bytea
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE internal
+AS 'jsonb_send';
+
+CREATE FUNCTION jsonschema_doc_typmod_in(cstring[]) RETURNS integer
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'jsonschema_doc_typmod_in';
+
+CREATE FUNCTION jsonschema_doc_typmod_out(integer) RETURNS cstring
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'jsonschema_doc_typmod_out';
+
+CREATE TYPE jsonschema_doc (
+	INPUT = jsonschema_doc_in,
+	OUTPUT = jsonschema_doc_out,
+	RECEIVE = jsonschema_doc_recv,
+	SEND = jsonschema_doc_send,
+	TYPMOD_IN = jsonschema_doc_typmod_in,
+	TYPMOD_OUT = jsonschema_doc_typmod_out,
+	LIKE = jsonb
+);
+
+-- jsonschema_doc validates a jsonschema_doc value against the schema named
+-- by a type modifier. Used as the length coercion cast.
+CREATE FUNCTION jsonschema_doc(jsonschema_doc, integer, boolean) RETURNS jsonschema_doc
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'jsonschema_doc_coerce';
+
+CREATE CAST (jsonschema_doc AS jsonschema_doc)
+	WITH FUNCTION jsonschema_doc(jsonschema_doc, integer, boolean) AS IMPLICIT;
+CREATE CAST (jsonb AS jsonschema_doc) WITHOUT FUNCTION AS ASSIGNMENT;
+CREATE CAST (jsonschema_doc AS jsonb) WITHOUT FUNCTION AS IMPLICIT;
+CREATE CAST (json AS jsonschema_doc) WITH INOUT AS ASSIGNMENT;
+
+-- jsonschema_valid returns true if data validates against schema. Implements
+-- the @~ operator.
+CREATE FUNCTION jsonschema_valid(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_valid_json_wrapper';
+
+CREATE FUNCTION jsonschema_valid(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_valid_jsonb_wrapper';
+
+CREATE FUNCTION jsonschema_valid(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_valid_jsonb_wrapper';
+
+CREATE FUNCTION jsonschema_valid(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_valid_json_wrapper';
+
+-- jsonschema_invalid returns true if data does not validate against schema.
+-- Implements the !@~ operator.
+CREATE FUNCTION jsonschema_invalid(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_invalid_json_wrapper';
+
+CREATE FUNCTION jsonschema_invalid(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_invalid_jsonb_wrapper';
+
+CREATE FUNCTION jsonschema_invalid(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_invalid_jsonb_wrapper';
+
+CREATE FUNCTION jsonschema_invalid(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_invalid_json_wrapper';
+
+-- jsonschema_accepts returns true if data validates against schema.
+-- Implements the ~@ operator.
+CREATE FUNCTION jsonschema_accepts(
+	"schema" json, /* pgrx::datum::json::Json */
+	"data" json /* pgrx::datum::json::JsonString */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_accepts_json_wrapper';
+
+CREATE FUNCTION jsonschema_accepts(
+	"schema" jsonb, /* pgrx::datum::json::JsonB */
+	"data" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_accepts_jsonb_wrapper';
+
+CREATE FUNCTION jsonschema_accepts(
+	"schema" jsonb, /* pgrx::datum::json::JsonB */
+	"data" json /* pgrx::datum::json::JsonString */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_accepts_json_wrapper';
+
+CREATE FUNCTION jsonschema_accepts(
+	"schema" json, /* pgrx::datum::json::Json */
+	"data" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_accepts_jsonb_wrapper';
+
+-- jsonschema_rejects returns true if data does not validate against schema.
+-- Implements the !~@ operator.
+CREATE FUNCTION jsonschema_rejects(
+	"schema" json, /* pgrx::datum::json::Json */
+	"data" json /* pgrx::datum::json::JsonString */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_rejects_json_wrapper';
+
+CREATE FUNCTION jsonschema_rejects(
+	"schema" jsonb, /* pgrx::datum::json::JsonB */
+	"data" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_rejects_jsonb_wrapper';
+
+CREATE FUNCTION jsonschema_rejects(
+	"schema" jsonb, /* pgrx::datum::json::JsonB */
+	"data" json /* pgrx::datum::json::JsonString */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_rejects_json_wrapper';
+
+CREATE FUNCTION jsonschema_rejects(
+	"schema" json, /* pgrx::datum::json::Json */
+	"data" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_rejects_jsonb_wrapper';
+
+-- data @~ schema: data validates against schema.
+CREATE OPERATOR @~ (
+	LEFTARG = json, RIGHTARG = json, FUNCTION = jsonschema_valid,
+	COMMUTATOR = ~@, NEGATOR = !@~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR @~ (
+	LEFTARG = jsonb, RIGHTARG = jsonb, FUNCTION = jsonschema_valid,
+	COMMUTATOR = ~@, NEGATOR = !@~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR @~ (
+	LEFTARG = json, RIGHTARG = jsonb, FUNCTION = jsonschema_valid,
+	COMMUTATOR = ~@, NEGATOR = !@~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR @~ (
+	LEFTARG = jsonb, RIGHTARG = json, FUNCTION = jsonschema_valid,
+	COMMUTATOR = ~@, NEGATOR = !@~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+-- data !@~ schema: data does not validate against schema.
+CREATE OPERATOR !@~ (
+	LEFTARG = json, RIGHTARG = json, FUNCTION = jsonschema_invalid,
+	COMMUTATOR = !~@, NEGATOR = @~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR !@~ (
+	LEFTARG = jsonb, RIGHTARG = jsonb, FUNCTION = jsonschema_invalid,
+	COMMUTATOR = !~@, NEGATOR = @~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR !@~ (
+	LEFTARG = json, RIGHTARG = jsonb, FUNCTION = jsonschema_invalid,
+	COMMUTATOR = !~@, NEGATOR = @~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR !@~ (
+	LEFTARG = jsonb, RIGHTARG = json, FUNCTION = jsonschema_invalid,
+	COMMUTATOR = !~@, NEGATOR = @~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+-- schema ~@ data: schema accepts data.
+CREATE OPERATOR ~@ (
+	LEFTARG = json, RIGHTARG = json, FUNCTION = jsonschema_accepts,
+	COMMUTATOR = @~, NEGATOR = !~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR ~@ (
+	LEFTARG = jsonb, RIGHTARG = jsonb, FUNCTION = jsonschema_accepts,
+	COMMUTATOR = @~, NEGATOR = !~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR ~@ (
+	LEFTARG = jsonb, RIGHTARG = json, FUNCTION = jsonschema_accepts,
+	COMMUTATOR = @~, NEGATOR = !~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR ~@ (
+	LEFTARG = json, RIGHTARG = jsonb, FUNCTION = jsonschema_accepts,
+	COMMUTATOR = @~, NEGATOR = !~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+-- schema !~@ data: schema rejects data.
+CREATE OPERATOR !~@ (
+	LEFTARG = json, RIGHTARG = json, FUNCTION = jsonschema_rejects,
+	COMMUTATOR = !@~, NEGATOR = ~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR !~@ (
+	LEFTARG = jsonb, RIGHTARG = jsonb, FUNCTION = jsonschema_rejects,
+	COMMUTATOR = !@~, NEGATOR = ~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR !~@ (
+	LEFTARG = jsonb, RIGHTARG = json, FUNCTION = jsonschema_rejects,
+	COMMUTATOR = !@~, NEGATOR = ~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR !~@ (
+	LEFTARG = json, RIGHTARG = jsonb, FUNCTION = jsonschema_rejects,
+	COMMUTATOR = !@~, NEGATOR = ~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+-- jsonschema_validates_support(req::internal)
+CREATE FUNCTION jsonschema_validates_support(
+	"req" internal /* pgrx::datum::internal::Internal */
+) RETURNS internal /* pgrx::datum::internal::Internal */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonschema_validates_support_wrapper';
+
+-- jsonschema_import_rejects records lines that failed to parse, validate,
+-- or insert in jsonschema_import(), along with their errors.
+CREATE TABLE jsonschema_import_rejects (
+	id          BIGINT      GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+	rejected_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp(),
+	relation    REGCLASS    NOT NULL,
+	column_name NAME        NOT NULL,
+	line        BIGINT      NOT NULL,
+	data        TEXT        NOT NULL,
+	errors      JSONB       NOT NULL
+);
+
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_import_rejects', '');
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_import_rejects_id_seq', '');
+
+-- jsonschema_import imports the lines of a JSON Lines source that validate
+-- against a schema into a table column, one subtransaction per line, and
+-- records the rest in jsonschema_import_rejects.
+CREATE FUNCTION jsonschema_import(
+	"source" TEXT, /* &str */
+	"target" regclass, /* pgrx::pg_sys::Oid */
+	"target_column" name, /* &core::ffi::CStr */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS TABLE (
+	"imported" bigint, /* i64 */
+	"rejected" bigint /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_import_wrapper';
+
+CREATE FUNCTION jsonschema_import(
+	"source" TEXT, /* &str */
+	"target" regclass, /* pgrx::pg_sys::Oid */
+	"target_column" name, /* &core::ffi::CStr */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS TABLE (
+	"imported" bigint, /* i64 */
+	"rejected" bigint /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_import_wrapper';
+
+CREATE FUNCTION jsonschema_import(
+	"source" TEXT, /* &str */
+	"target" regclass, /* pgrx::pg_sys::Oid */
+	"target_column" name, /* &core::ffi::CStr */
+	"id" TEXT, /* &str */
+	VARIADIC "schemas" json[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::Json> */
+) RETURNS TABLE (
+	"imported" bigint, /* i64 */
+	"rejected" bigint /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_id_import_wrapper';
+
+CREATE FUNCTION jsonschema_import(
+	"source" TEXT, /* &str */
+	"target" regclass, /* pgrx::pg_sys::Oid */
+	"target_column" name, /* &core::ffi::CStr */
+	"id" TEXT, /* &str */
+	VARIADIC "schemas" jsonb[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::JsonB> */
+) RETURNS TABLE (
+	"imported" bigint, /* i64 */
+	"rejected" bigint /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_id_import_wrapper';
+
+-- jsonschema_matching(data::json,  VARIADIC schema::json)
+CREATE FUNCTION jsonschema_matching(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schemas" VARIADIC json[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::Json> */
+) RETURNS SETOF TEXT /* pgrx::iter::SetOfIterator<alloc::string::String> */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_matching_json_wrapper';
+
+-- jsonschema_matching(data::jsonb, VARIADIC schema::jsonb)
+CREATE FUNCTION jsonschema_matching(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schemas" VARIADIC jsonb[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::JsonB> */
+) RETURNS SETOF TEXT /* pgrx::iter::SetOfIterator<alloc::string::String> */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_matching_jsonb_wrapper';
+
+-- jsonschema_matching(data::json,  VARIADIC schema::jsonb)
+CREATE FUNCTION jsonschema_matching(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schemas" VARIADIC jsonb[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::JsonB> */
+) RETURNS SETOF TEXT /* pgrx::iter::SetOfIterator<alloc::string::String> */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_matching_jsonb_wrapper';
+
+-- jsonschema_matching(data::jsonb, VARIADIC schema::json)
+CREATE FUNCTION jsonschema_matching(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schemas" VARIADIC json[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::Json> */
+) RETURNS SETOF TEXT /* pgrx::iter::SetOfIterator<alloc::string::String> */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_matching_json_wrapper';
+
+-- jsonschema_first_match(data::json,  VARIADIC schema::json)
+CREATE FUNCTION jsonschema_first_match(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schemas" VARIADIC json[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::Json> */
+) RETURNS TEXT /* core::option::Option<alloc::string::String> */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_first_match_json_wrapper';
+
+-- jsonschema_first_match(data::jsonb, VARIADIC schema::jsonb)
+CREATE FUNCTION jsonschema_first_match(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schemas" VARIADIC jsonb[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::JsonB> */
+) RETURNS TEXT /* core::option::Option<alloc::string::String> */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_first_match_jsonb_wrapper';
+
+-- jsonschema_first_match(data::json,  VARIADIC schema::jsonb)
+CREATE FUNCTION jsonschema_first_match(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schemas" VARIADIC jsonb[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::JsonB> */
+) RETURNS TEXT /* core::option::Option<alloc::string::String> */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_first_match_jsonb_wrapper';
+
+-- jsonschema_first_match(data::jsonb, VARIADIC schema::json)
+CREATE FUNCTION jsonschema_first_match(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schemas" VARIADIC json[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::Json> */
+) RETURNS TEXT /* core::option::Option<alloc::string::String> */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_first_match_json_wrapper';
+
+-- jsonschema_validates_self(data::json, property::text)
+CREATE FUNCTION jsonschema_validates_self(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"property" TEXT DEFAULT '$schema' /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_validates_self_wrapper';
+
+-- jsonschema_validates_self(data::jsonb, property::text)
+CREATE FUNCTION jsonschema_validates_self(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"property" TEXT DEFAULT '$schema' /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_validates_self_wrapper';
+
+-- jsonschema_subjects stores the compatibility level of each subject.
+CREATE TABLE jsonschema_subjects (
+	subject       TEXT PRIMARY KEY,
+	compatibility TEXT NOT NULL DEFAULT 'BACKWARD' CHECK (compatibility IN (
+		'NONE', 'BACKWARD', 'BACKWARD_TRANSITIVE', 'FORWARD',
+		'FORWARD_TRANSITIVE', 'FULL', 'FULL_TRANSITIVE'
+	))
+);
+
+-- jsonschema_subject_versions stores the schema versions of each subject.
+-- Versions are immutable.
+CREATE TABLE jsonschema_subject_versions (
+	subject       TEXT        NOT NULL REFERENCES jsonschema_subjects,
+	version       INTEGER     NOT NULL CHECK (version > 0),
+	schema        JSONB       NOT NULL,
+	registered_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp(),
+	PRIMARY KEY (subject, version)
+);
+
+CREATE FUNCTION jsonschema_subject_versions_immutable() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+	RAISE EXCEPTION 'cannot % version % of subject "%": schema versions are immutable',
+		lower(TG_OP), OLD.version, OLD.subject
+		USING ERRCODE = 'restrict_violation';
+END;
+$$;
+
+CREATE TRIGGER jsonschema_subject_versions_immutable
+BEFORE UPDATE OR DELETE ON jsonschema_subject_versions
+FOR EACH ROW EXECUTE FUNCTION jsonschema_subject_versions_immutable();
+
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_subjects', '');
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_subject_versions', '');
+
+-- jsonschema_set_compatibility(subject::text, compatibility::text)
+CREATE FUNCTION jsonschema_set_compatibility(
+	"subject" TEXT, /* &str */
+	"compatibility" TEXT /* &str */
+) RETURNS TEXT /* alloc::string::String */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonschema_set_compatibility_wrapper';
+
+-- jsonschema_register_version(subject::text, schema::jsonb)
+CREATE FUNCTION jsonschema_register_version(
+	"subject" TEXT, /* &str */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS INT /* i32 */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonschema_register_version_wrapper';
+
+-- jsonschema_validates_subject(data::json, subject::text)
+CREATE FUNCTION jsonschema_validates_subject(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"subject" TEXT /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_validates_subject_wrapper';
+
+-- jsonschema_validates_subject(data::jsonb, subject::text)
+CREATE FUNCTION jsonschema_validates_subject(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"subject" TEXT /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_validates_subject_wrapper';
+
+-- jsonschema_validates_subject(data::json, subject::text, version::integer)
+CREATE FUNCTION jsonschema_validates_subject(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"subject" TEXT, /* &str */
+	"version" INT /* i32 */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_validates_subject_version_wrapper';
+
+-- jsonschema_validates_subject(data::jsonb, subject::text, version::integer)
+CREATE FUNCTION jsonschema_validates_subject(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"subject" TEXT, /* &str */
+	"version" INT /* i32 */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_validates_subject_version_wrapper';
+
+-- jsonschema_pin(id::text)
+CREATE FUNCTION jsonschema_pin(
+	"id" TEXT /* &str */
+) RETURNS TEXT /* alloc::string::String */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonschema_pin_id_wrapper';
+
+-- jsonschema_pin(subject::text, version::integer)
+CREATE FUNCTION jsonschema_pin(
+	"subject" TEXT, /* &str */
+	"version" INT /* i32 */
+) RETURNS TEXT /* alloc::string::String */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonschema_pin_version_wrapper';
+
+-- jsonschema_validates_pinned(data::json, pinned::text)
+CREATE FUNCTION jsonschema_validates_pinned(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"pinned" TEXT /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_validates_pinned_wrapper';
+
+-- jsonschema_validates_pinned(data::jsonb, pinned::text)
+CREATE FUNCTION jsonschema_validates_pinned(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"pinned" TEXT /* &str */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_validates_pinned_wrapper';
+
+-- jsonschema_is_valid(schema::json, draft::text)
+CREATE FUNCTION jsonschema_is_valid(
+	"schema" json, /* pgrx::datum::json::Json */
+	"draft" TEXT /* &str */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_is_valid_draft_wrapper';
+
+-- jsonschema_is_valid(schema::jsonb, draft::text)
+CREATE FUNCTION jsonschema_is_valid(
+	"schema" jsonb, /* pgrx::datum::json::JsonB */
+	"draft" TEXT /* &str */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_is_valid_draft_wrapper';
+
+-- jsonschema_validates(data::json, schema::json, draft::text)
+CREATE FUNCTION jsonschema_validates(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schema" json, /* pgrx::datum::json::Json */
+	"draft" TEXT /* &str */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_validates_json_draft_wrapper';
+
+-- jsonschema_validates(data::jsonb, schema::jsonb, draft::text)
+CREATE FUNCTION jsonschema_validates(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" jsonb, /* pgrx::datum::json::JsonB */
+	"draft" TEXT /* &str */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_validates_jsonb_draft_wrapper';
+
+-- jsonschema_validates(data::json, schema::jsonb, draft::text)
+CREATE FUNCTION jsonschema_validates(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schema" jsonb, /* pgrx::datum::json::JsonB */
+	"draft" TEXT /* &str */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_validates_jsonb_draft_wrapper';
+
+-- jsonschema_validates(data::jsonb, schema::json, draft::text)
+CREATE FUNCTION jsonschema_validates(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" json, /* pgrx::datum::json::Json */
+	"draft" TEXT /* &str */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_validates_json_draft_wrapper';
+
+-- Indexes and generated columns require IMMUTABLE functions, so refuse to
+-- update while any use the functions that become STABLE below.
+DO $$
+DECLARE
+	objs TEXT;
+BEGIN
+	SELECT pg_catalog.string_agg(DISTINCT pg_catalog.pg_describe_object(classid, objid, objsubid), ', ')
+	  INTO objs
+	  FROM pg_catalog.pg_depend
+	 WHERE refclassid = 'pg_catalog.pg_proc'::regclass
+	   AND classid IN ('pg_catalog.pg_class'::regclass, 'pg_catalog.pg_attrdef'::regclass)
+	   AND refobjid IN (
+		'jsonschema_is_valid(json)'::regprocedure,
+		'jsonschema_is_valid(jsonb)'::regprocedure,
+		'jsonschema_is_valid(text, VARIADIC json[])'::regprocedure,
+		'jsonschema_is_valid(text, VARIADIC jsonb[])'::regprocedure,
+		'jsonschema_validates(json, json)'::regprocedure,
+		'jsonschema_validates(jsonb, jsonb)'::regprocedure,
+		'jsonschema_validates(json, jsonb)'::regprocedure,
+		'jsonschema_validates(jsonb, json)'::regprocedure,
+		'jsonschema_validates(json, text, VARIADIC json[])'::regprocedure,
+		'jsonschema_validates(jsonb, text, VARIADIC jsonb[])'::regprocedure,
+		'jsonschema_validates(json, text, VARIADIC jsonb[])'::regprocedure,
+		'jsonschema_validates(jsonb, text, VARIADIC json[])'::regprocedure,
+		'json_matches_schema(json, json)'::regprocedure,
+		'jsonb_matches_schema(json, jsonb)'::regprocedure
+	   );
+	IF objs IS NOT NULL THEN
+		RAISE EXCEPTION 'cannot make jsonschema functions STABLE while indexes or generated columns use them'
+			USING HINT = pg_catalog.format('Drop %s, update the extension, and recreate them with the draft-pinned jsonschema_validates(data, schema, draft).', objs);
+	END IF;
+END;
+$$;
+
+-- The results of the functions below depend on the jsonschema.default_draft
+-- and jsonschema.reject_duplicate_keys configurations, so they're STABLE
+-- rather than IMMUTABLE. The draft-pinned variants above are IMMUTABLE.
+ALTER FUNCTION jsonschema_is_valid(json) STABLE;
+ALTER FUNCTION jsonschema_is_valid(jsonb) STABLE;
+ALTER FUNCTION jsonschema_is_valid(text, VARIADIC json[]) STABLE;
+ALTER FUNCTION jsonschema_is_valid(text, VARIADIC jsonb[]) STABLE;
+ALTER FUNCTION jsonschema_validates(json, json) STABLE;
+ALTER FUNCTION jsonschema_validates(jsonb, jsonb) STABLE;
+ALTER FUNCTION jsonschema_validates(json, jsonb) STABLE;
+ALTER FUNCTION jsonschema_validates(jsonb, json) STABLE;
+ALTER FUNCTION jsonschema_validates(json, text, VARIADIC json[]) STABLE;
+ALTER FUNCTION jsonschema_validates(jsonb, text, VARIADIC jsonb[]) STABLE;
+ALTER FUNCTION jsonschema_validates(json, text, VARIADIC jsonb[]) STABLE;
+ALTER FUNCTION jsonschema_validates(jsonb, text, VARIADIC json[]) STABLE;
+ALTER FUNCTION json_matches_schema(json, json) STABLE;
+ALTER FUNCTION jsonb_matches_schema(json, jsonb) STABLE;
+
+-- jsonschema_draft enumerates the supported JSON Schema drafts.
+CREATE TYPE jsonschema_draft AS ENUM ('V4', 'V6', 'V7', 'V2019', 'V2020');
+
+-- jsonschema_draft_of(schema::json)
+CREATE FUNCTION jsonschema_draft_of(
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS jsonschema_draft /* jsonschema::Draft */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_draft_of_wrapper';
+
+-- jsonschema_draft_of(schema::jsonb)
+CREATE FUNCTION jsonschema_draft_of(
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS jsonschema_draft /* jsonschema::Draft */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_draft_of_wrapper';
+
+-- jsonschema_meta_schema(draft::jsonschema_draft)
+CREATE FUNCTION jsonschema_meta_schema(
+	"draft" jsonschema_draft /* jsonschema::Draft */
+) RETURNS jsonb /* pgrx::datum::json::JsonB */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonschema_meta_schema_wrapper';
+
+-- jsonschema_dialects stores the meta-schemas of custom "$schema" dialects
+-- by their "$id"s, along with the draft each extends.
+CREATE TABLE jsonschema_dialects (
+	id     TEXT             PRIMARY KEY,
+	draft  jsonschema_draft NOT NULL,
+	schema JSONB            NOT NULL
+);
+
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_dialects', '');
+
+-- jsonschema_register_dialect(schema::jsonb)
+CREATE FUNCTION jsonschema_register_dialect(
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS TEXT /* alloc::string::String */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonschema_register_dialect_wrapper';
+
+-- jsonschema_unregister_dialect(id::text)
+CREATE FUNCTION jsonschema_unregister_dialect(
+	"id" TEXT /* &str */
+) RETURNS bool /* bool */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonschema_unregister_dialect_wrapper';
+
+-- jsonschema_keywords maps custom keywords to the SQL functions that
+-- implement them.
+CREATE TABLE jsonschema_keywords (
+	keyword  TEXT         PRIMARY KEY CHECK (keyword <> ''),
+	function REGPROCEDURE NOT NULL
+);
+
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_keywords', '');
+
+-- jsonschema_register_keyword(keyword::text, function::text)
+CREATE FUNCTION jsonschema_register_keyword(
+	"keyword" TEXT, /* &str */
+	"function" TEXT /* &str */
+) RETURNS void
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonschema_register_keyword_wrapper';
+
+-- jsonschema_unregister_keyword(keyword::text)
+CREATE FUNCTION jsonschema_unregister_keyword(
+	"keyword" TEXT /* &str */
+) RETURNS bool /* bool */
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonschema_unregister_keyword_wrapper';
+
+-- jsonschema_validates(data::json, schema::json, data_refs::bool)
+CREATE FUNCTION jsonschema_validates(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schema" json, /* pgrx::datum::json::Json */
+	"data_refs" bool /* bool */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_validates_json_data_refs_wrapper';
+
+-- jsonschema_validates(data::jsonb, schema::jsonb, data_refs::bool)
+CREATE FUNCTION jsonschema_validates(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" jsonb, /* pgrx::datum::json::JsonB */
+	"data_refs" bool /* bool */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_validates_jsonb_data_refs_wrapper';
+
+-- Estimate the selectivity and cost of the validating functions.
+ALTER FUNCTION jsonschema_validates(json, json) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, jsonb) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(json, jsonb) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, json) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(json, text, json[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, text, jsonb[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(json, text, jsonb[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, text, json[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_set(json, text, jsonschema_set) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_set(jsonb, text, jsonschema_set) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(json, json, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, jsonb, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(json, jsonb, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, json, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(json, json, bool) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, jsonb, bool) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(text, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(text, text, text[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(bytea, text, json) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(bytea, text, jsonb) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(bytea, text, text, json[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(bytea, text, text, jsonb[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(text, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(text, json) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(text, jsonb) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(json, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(jsonb, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(text, text, text[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(text, text, json[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(text, text, jsonb[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(json, text, text[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(jsonb, text, text[]) SUPPORT jsonschema_validates_support;
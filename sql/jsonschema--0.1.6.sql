@@ -0,0 +1,130 @@
+-- The v0.1.6 installation script, reconstructed from its release to test
+-- upgrades from it to later versions.
+
+-- jsonschema_is_valid(schema::json)
+CREATE FUNCTION jsonschema_is_valid(
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_is_valid_wrapper';
+
+-- jsonschema_is_valid(schema::jsonb)
+CREATE FUNCTION jsonschema_is_valid(
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_is_valid_wrapper';
+
+-- jsonschema_is_valid(id::text, VARIADIC schema::json)
+CREATE FUNCTION jsonschema_is_valid(
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC json[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::Json> */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_id_is_valid_wrapper';
+
+-- jsonschema_is_valid(id::text, VARIADIC schema::jsonb)
+CREATE FUNCTION jsonschema_is_valid(
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC jsonb[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::JsonB> */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_id_is_valid_wrapper';
+
+-- jsonschema_validates(data::json, schema::json)
+CREATE FUNCTION jsonschema_validates(
+	"data" json, /* pgrx::datum::json::Json */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_validates_json_wrapper';
+
+-- jsonschema_validates(data::jsonb, schema::jsonb)
+CREATE FUNCTION jsonschema_validates(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_validates_jsonb_wrapper';
+
+-- jsonschema_validates(data::json, schema::jsonb)
+CREATE FUNCTION jsonschema_validates(
+	"data" json, /* pgrx::datum::json::Json */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_validates_jsonb_wrapper';
+
+-- jsonschema_validates(data::jsonb, schema::json)
+CREATE FUNCTION jsonschema_validates(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_validates_json_wrapper';
+
+-- jsonschema_validates(data::json, id::text, VARIADIC schema::json)
+CREATE FUNCTION jsonschema_validates(
+	"data" json, /* pgrx::datum::json::Json */
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC json[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::Json> */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_id_validates_json_wrapper';
+
+-- jsonschema_validates(data::jsonb, id::text, VARIADIC schema::jsonb)
+CREATE FUNCTION jsonschema_validates(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC jsonb[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::JsonB> */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_id_validates_jsonb_wrapper';
+
+-- jsonschema_validates(data::json, id::text, VARIADIC schema::jsonb)
+CREATE FUNCTION jsonschema_validates(
+	"data" json, /* pgrx::datum::json::Json */
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC jsonb[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::JsonB> */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_id_validates_jsonb_wrapper';
+
+-- jsonschema_validates(data::jsonb, id::text, VARIADIC schema::json)
+CREATE FUNCTION jsonschema_validates(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"id" TEXT, /* &str */
+	"schemas" VARIADIC json[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::Json> */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_id_validates_json_wrapper';
+
+-- json_matches_schema(schema::json, instance::json)
+CREATE FUNCTION json_matches_schema(
+	"schema" json, /* pgrx::datum::json::Json */
+	"instance" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_matches_schema_wrapper';
+
+-- jsonb_matches_schema(schema::json, instance::jsonb)
+CREATE FUNCTION jsonb_matches_schema(
+	"schema" json, /* pgrx::datum::json::Json */
+	"instance" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_matches_schema_wrapper';
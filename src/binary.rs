@@ -0,0 +1,349 @@
+//! Decoding of binary-encoded documents into `serde_json::Value`s.
+//!
+//! Binary formats can represent values that JSON cannot. Following the
+//! CBOR-to-JSON conversion recommendations of [RFC 8949 Section 6.1], such
+//! values map to JSON like so:
+//!
+//! *   Byte strings become base64url-encoded strings without padding, unless
+//!     wrapped in a CBOR tag 22 (base64) or 23 (base16) expected-conversion
+//!     tag, in which case they use that encoding.
+//! *   CBOR bignums (tags 2 and 3) become numbers.
+//! *   Other CBOR tags and MessagePack extension types are ignored in favor of
+//!     their content; MessagePack extension data becomes a base64url string.
+//! *   Non-finite floats (NaN and infinities) become `null`.
+//! *   Integers outside the 64-bit range become (lossy) floats.
+//! *   Map keys that are integers, floats, or booleans become their JSON text
+//!     representations; byte string keys become base64url strings. Any other
+//!     key type is an error.
+//!
+//! [RFC 8949 Section 6.1]: https://www.rfc-editor.org/rfc/rfc8949.html#name-converting-from-cbor-to-jso
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use serde_json::{Map, Number, Value};
+
+/// Binary document formats supported by decode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Format {
+    Cbor,
+    MessagePack,
+}
+
+impl Format {
+    /// from_name returns the Format for `name`, which may be "cbor",
+    /// "msgpack", or "messagepack", case-insensitively.
+    pub(crate) fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "cbor" => Ok(Format::Cbor),
+            "msgpack" | "messagepack" => Ok(Format::MessagePack),
+            _ => Err(format!(
+                r#"unsupported binary format "{name}": must be "cbor" or "msgpack""#
+            )),
+        }
+    }
+}
+
+/// decode decodes `data` as `format` into a `serde_json::Value`. Returns an
+/// error if `data` is malformed, contains trailing bytes, or contains a value
+/// that cannot be mapped to JSON.
+pub(crate) fn decode(format: Format, data: &[u8]) -> Result<Value, String> {
+    match format {
+        Format::Cbor => decode_cbor(data).map_err(|e| format!("cannot decode CBOR: {e}")),
+        Format::MessagePack => {
+            decode_msgpack(data).map_err(|e| format!("cannot decode MessagePack: {e}"))
+        }
+    }
+}
+
+fn decode_cbor(data: &[u8]) -> Result<Value, String> {
+    use ciborium::de::Error;
+    let mut reader = data;
+    let value: ciborium::Value = ciborium::from_reader(&mut reader).map_err(|e| match e {
+        Error::Io(e) => e.to_string(),
+        Error::Syntax(offset) => format!("syntax error at offset {offset}"),
+        Error::Semantic(Some(offset), msg) => format!("{msg} at offset {offset}"),
+        Error::Semantic(None, msg) => msg,
+        Error::RecursionLimitExceeded => "recursion limit exceeded".to_string(),
+    })?;
+    if !reader.is_empty() {
+        return Err(format!("{} trailing bytes", reader.len()));
+    }
+    cbor_to_json(value)
+}
+
+fn cbor_to_json(value: ciborium::Value) -> Result<Value, String> {
+    use ciborium::Value as Cbor;
+    Ok(match value {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(b) => Value::Bool(b),
+        Cbor::Integer(i) => int_to_json(i.into()),
+        Cbor::Float(f) => float_to_json(f),
+        Cbor::Text(s) => Value::String(s),
+        Cbor::Bytes(b) => Value::String(URL_SAFE_NO_PAD.encode(b)),
+        Cbor::Tag(tag, content) => match (tag, *content) {
+            (2, Cbor::Bytes(b)) => bignum_to_json(&b, false),
+            (3, Cbor::Bytes(b)) => bignum_to_json(&b, true),
+            (22, Cbor::Bytes(b)) => Value::String(STANDARD.encode(b)),
+            (23, Cbor::Bytes(b)) => Value::String(b.iter().map(|b| format!("{b:02X}")).collect()),
+            (_, content) => cbor_to_json(content)?,
+        },
+        Cbor::Array(a) => Value::Array(a.into_iter().map(cbor_to_json).collect::<Result<_, _>>()?),
+        Cbor::Map(m) => {
+            let mut obj = Map::with_capacity(m.len());
+            for (k, v) in m {
+                let key = match k {
+                    Cbor::Text(s) => s,
+                    Cbor::Bytes(b) => URL_SAFE_NO_PAD.encode(b),
+                    k @ (Cbor::Integer(_) | Cbor::Float(_) | Cbor::Bool(_)) => {
+                        cbor_to_json(k)?.to_string()
+                    }
+                    k => return Err(format!("unsupported map key {k:?}")),
+                };
+                obj.insert(key, cbor_to_json(v)?);
+            }
+            Value::Object(obj)
+        }
+        v => return Err(format!("unsupported value {v:?}")),
+    })
+}
+
+fn decode_msgpack(data: &[u8]) -> Result<Value, String> {
+    let mut reader = data;
+    let value = rmpv::decode::read_value(&mut reader).map_err(|e| e.to_string())?;
+    if !reader.is_empty() {
+        return Err(format!("{} trailing bytes", reader.len()));
+    }
+    msgpack_to_json(value)
+}
+
+fn msgpack_to_json(value: rmpv::Value) -> Result<Value, String> {
+    use rmpv::Value as Mp;
+    Ok(match value {
+        Mp::Nil => Value::Null,
+        Mp::Boolean(b) => Value::Bool(b),
+        // MessagePack integers always fit in an i64 or a u64.
+        Mp::Integer(i) => i
+            .as_i64()
+            .map_or_else(|| Value::from(i.as_u64()), Value::from),
+        Mp::F32(f) => float_to_json(f.into()),
+        Mp::F64(f) => float_to_json(f),
+        Mp::String(s) => match s.into_str() {
+            Some(s) => Value::String(s),
+            None => return Err("invalid UTF-8 in string".to_string()),
+        },
+        Mp::Binary(b) => Value::String(URL_SAFE_NO_PAD.encode(b)),
+        Mp::Ext(_, b) => Value::String(URL_SAFE_NO_PAD.encode(b)),
+        Mp::Array(a) => Value::Array(
+            a.into_iter()
+                .map(msgpack_to_json)
+                .collect::<Result<_, _>>()?,
+        ),
+        Mp::Map(m) => {
+            let mut obj = Map::with_capacity(m.len());
+            for (k, v) in m {
+                let key = match k {
+                    Mp::String(s) => match s.into_str() {
+                        Some(s) => s,
+                        None => return Err("invalid UTF-8 in map key".to_string()),
+                    },
+                    Mp::Binary(b) => URL_SAFE_NO_PAD.encode(b),
+                    k @ (Mp::Integer(_) | Mp::F32(_) | Mp::F64(_) | Mp::Boolean(_)) => {
+                        msgpack_to_json(k)?.to_string()
+                    }
+                    k => return Err(format!("unsupported map key {k}")),
+                };
+                obj.insert(key, msgpack_to_json(v)?);
+            }
+            Value::Object(obj)
+        }
+    })
+}
+
+// Converts a CBOR integer, which may fall anywhere in -2^64..2^64, to a JSON
+// number, falling back on a float outside the 64-bit integer range.
+fn int_to_json(i: i128) -> Value {
+    if let Ok(i) = i64::try_from(i) {
+        Value::from(i)
+    } else if let Ok(u) = u64::try_from(i) {
+        Value::from(u)
+    } else {
+        float_to_json(i as f64)
+    }
+}
+
+// Converts a float to a JSON number, or to null if it's NaN or infinite.
+fn float_to_json(f: f64) -> Value {
+    Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+// Converts the big-endian magnitude of a CBOR bignum to a JSON number. A
+// negative bignum represents -1 - n.
+fn bignum_to_json(bytes: &[u8], negative: bool) -> Value {
+    let bytes = bytes.iter().skip_while(|b| **b == 0).collect::<Vec<_>>();
+    if bytes.len() <= 8 {
+        let n = bytes.iter().fold(0u64, |n, b| (n << 8) | u64::from(**b));
+        int_to_json(if negative {
+            -1 - i128::from(n)
+        } else {
+            i128::from(n)
+        })
+    } else {
+        let n = bytes.iter().fold(0f64, |n, b| n * 256.0 + f64::from(**b));
+        float_to_json(if negative { -1.0 - n } else { n })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn cbor(value: &ciborium::Value) -> Vec<u8> {
+        let mut buf = Vec::new();
+        ciborium::into_writer(value, &mut buf).unwrap();
+        buf
+    }
+
+    fn msgpack(value: &rmpv::Value) -> Vec<u8> {
+        let mut buf = Vec::new();
+        rmpv::encode::write_value(&mut buf, value).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(Format::from_name("cbor"), Ok(Format::Cbor));
+        assert_eq!(Format::from_name("CBOR"), Ok(Format::Cbor));
+        assert_eq!(Format::from_name("msgpack"), Ok(Format::MessagePack));
+        assert_eq!(Format::from_name("MessagePack"), Ok(Format::MessagePack));
+        assert_eq!(
+            Format::from_name("bson"),
+            Err(r#"unsupported binary format "bson": must be "cbor" or "msgpack""#.to_string()),
+        );
+    }
+
+    #[test]
+    fn test_decode_cbor() {
+        use ciborium::Value as Cbor;
+
+        let doc = Cbor::Map(vec![
+            (Cbor::Text("name".into()), Cbor::Text("Amos".into())),
+            (Cbor::Text("age".into()), Cbor::Integer(42.into())),
+            (Cbor::Text("score".into()), Cbor::Float(98.6)),
+            (Cbor::Text("nan".into()), Cbor::Float(f64::NAN)),
+            (Cbor::Text("ok".into()), Cbor::Bool(true)),
+            (Cbor::Text("none".into()), Cbor::Null),
+            (Cbor::Text("raw".into()), Cbor::Bytes(vec![0xfb, 0xff])),
+            (
+                Cbor::Text("b64".into()),
+                Cbor::Tag(22, Box::new(Cbor::Bytes(vec![0xfb, 0xff]))),
+            ),
+            (
+                Cbor::Text("hex".into()),
+                Cbor::Tag(23, Box::new(Cbor::Bytes(vec![0xfb, 0xff]))),
+            ),
+            (
+                Cbor::Text("big".into()),
+                Cbor::Tag(2, Box::new(Cbor::Bytes(vec![0x01, 0x00]))),
+            ),
+            (
+                Cbor::Text("when".into()),
+                Cbor::Tag(0, Box::new(Cbor::Text("2025-01-01T00:00:00Z".into()))),
+            ),
+            (
+                Cbor::Integer(1.into()),
+                Cbor::Array(vec![Cbor::Integer((-1).into())]),
+            ),
+        ]);
+
+        assert_eq!(
+            decode(Format::Cbor, &cbor(&doc)),
+            Ok(json!({
+                "name": "Amos",
+                "age": 42,
+                "score": 98.6,
+                "nan": null,
+                "ok": true,
+                "none": null,
+                "raw": "-_8",
+                "b64": "+/8=",
+                "hex": "FBFF",
+                "big": 256,
+                "when": "2025-01-01T00:00:00Z",
+                "1": [-1],
+            })),
+        );
+
+        // Unsupported key.
+        let doc = Cbor::Map(vec![(Cbor::Array(vec![]), Cbor::Null)]);
+        assert_eq!(
+            decode(Format::Cbor, &cbor(&doc)),
+            Err("cannot decode CBOR: unsupported map key Array([])".to_string()),
+        );
+
+        // Trailing bytes.
+        let mut buf = cbor(&Cbor::Bool(true));
+        buf.push(0xf5);
+        assert_eq!(
+            decode(Format::Cbor, &buf),
+            Err("cannot decode CBOR: 1 trailing bytes".to_string()),
+        );
+
+        // Malformed.
+        assert!(decode(Format::Cbor, &[0xa1]).is_err());
+        assert!(decode(Format::Cbor, &[]).is_err());
+    }
+
+    #[test]
+    fn test_decode_msgpack() {
+        use rmpv::Value as Mp;
+
+        let doc = Mp::Map(vec![
+            (Mp::from("name"), Mp::from("Amos")),
+            (Mp::from("age"), Mp::from(42)),
+            (Mp::from("big"), Mp::from(u64::MAX)),
+            (Mp::from("score"), Mp::F64(98.6)),
+            (Mp::from("inf"), Mp::F32(f32::INFINITY)),
+            (Mp::from("ok"), Mp::Boolean(false)),
+            (Mp::from("none"), Mp::Nil),
+            (Mp::from("raw"), Mp::Binary(vec![0xfb, 0xff])),
+            (Mp::from("ext"), Mp::Ext(5, vec![0xfb, 0xff])),
+            (Mp::from(true), Mp::Array(vec![Mp::from(-1)])),
+        ]);
+
+        assert_eq!(
+            decode(Format::MessagePack, &msgpack(&doc)),
+            Ok(json!({
+                "name": "Amos",
+                "age": 42,
+                "big": u64::MAX,
+                "score": 98.6,
+                "inf": null,
+                "ok": false,
+                "none": null,
+                "raw": "-_8",
+                "ext": "-_8",
+                "true": [-1],
+            })),
+        );
+
+        // Unsupported key.
+        let doc = Mp::Map(vec![(Mp::Nil, Mp::Nil)]);
+        assert_eq!(
+            decode(Format::MessagePack, &msgpack(&doc)),
+            Err("cannot decode MessagePack: unsupported map key nil".to_string()),
+        );
+
+        // Trailing bytes.
+        let mut buf = msgpack(&Mp::Nil);
+        buf.push(0xc0);
+        assert_eq!(
+            decode(Format::MessagePack, &buf),
+            Err("cannot decode MessagePack: 1 trailing bytes".to_string()),
+        );
+
+        // Malformed.
+        assert!(decode(Format::MessagePack, &[0x81]).is_err());
+        assert!(decode(Format::MessagePack, &[]).is_err());
+    }
+}
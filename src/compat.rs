@@ -0,0 +1,615 @@
+//! Schema compatibility checks for versioned subjects.
+//!
+//! `superset(a, b)` reports whether schema `a` accepts every instance that
+//! schema `b` accepts. Deciding that in general is intractable, so the check
+//! is structural and conservative: it compares the assertion keywords of `a`
+//! with those of `b`, and reports a keyword of `a` as incompatible whenever
+//! it cannot show that `b` is at least as restrictive. It understands:
+//!
+//! *   `type`, including `integer` as a subset of `number`
+//! *   `required`, `properties`, and `additionalProperties`
+//! *   `items` with a single schema
+//! *   `enum` and `const`
+//! *   Numeric, length, and size bounds such as `minimum` and `maxItems`
+//! *   `allOf` and `anyOf` in `a`
+//!
+//! Annotations such as `title` and `description` are ignored, and any other
+//! keyword of `a` must appear with the same value in `b`.
+
+use serde_json::{Map, Value};
+
+/// Compatibility policies for the versions of a subject, as in the Confluent
+/// Schema Registry.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Compatibility {
+    None,
+    Backward,
+    BackwardTransitive,
+    Forward,
+    ForwardTransitive,
+    Full,
+    FullTransitive,
+}
+
+impl Compatibility {
+    /// from_name returns the Compatibility for `name`, such as "BACKWARD" or
+    /// "FULL_TRANSITIVE", case-insensitively.
+    pub(crate) fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_ascii_uppercase().as_str() {
+            "NONE" => Ok(Compatibility::None),
+            "BACKWARD" => Ok(Compatibility::Backward),
+            "BACKWARD_TRANSITIVE" => Ok(Compatibility::BackwardTransitive),
+            "FORWARD" => Ok(Compatibility::Forward),
+            "FORWARD_TRANSITIVE" => Ok(Compatibility::ForwardTransitive),
+            "FULL" => Ok(Compatibility::Full),
+            "FULL_TRANSITIVE" => Ok(Compatibility::FullTransitive),
+            _ => Err(format!(
+                r#"invalid compatibility level "{name}": must be NONE, BACKWARD, FORWARD, or FULL, optionally with a _TRANSITIVE suffix"#
+            )),
+        }
+    }
+
+    /// name returns the name of the Compatibility.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Compatibility::None => "NONE",
+            Compatibility::Backward => "BACKWARD",
+            Compatibility::BackwardTransitive => "BACKWARD_TRANSITIVE",
+            Compatibility::Forward => "FORWARD",
+            Compatibility::ForwardTransitive => "FORWARD_TRANSITIVE",
+            Compatibility::Full => "FULL",
+            Compatibility::FullTransitive => "FULL_TRANSITIVE",
+        }
+    }
+
+    /// backward returns true if a new version must accept all data that
+    /// earlier versions accept.
+    pub(crate) fn backward(self) -> bool {
+        matches!(
+            self,
+            Compatibility::Backward
+                | Compatibility::BackwardTransitive
+                | Compatibility::Full
+                | Compatibility::FullTransitive
+        )
+    }
+
+    /// forward returns true if earlier versions must accept all data that a
+    /// new version accepts.
+    pub(crate) fn forward(self) -> bool {
+        matches!(
+            self,
+            Compatibility::Forward
+                | Compatibility::ForwardTransitive
+                | Compatibility::Full
+                | Compatibility::FullTransitive
+        )
+    }
+
+    /// transitive returns true if a new version must be compatible with all
+    /// earlier versions, rather than only the latest.
+    pub(crate) fn transitive(self) -> bool {
+        matches!(
+            self,
+            Compatibility::BackwardTransitive
+                | Compatibility::ForwardTransitive
+                | Compatibility::FullTransitive
+        )
+    }
+}
+
+// Keywords that do not affect validation.
+const ANNOTATIONS: &[&str] = &[
+    "$id",
+    "$schema",
+    "$comment",
+    "$anchor",
+    "title",
+    "description",
+    "default",
+    "examples",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+];
+
+// Keywords that set a lower bound on a value.
+const MINIMUMS: &[&str] = &[
+    "minimum",
+    "exclusiveMinimum",
+    "minLength",
+    "minItems",
+    "minProperties",
+    "minContains",
+];
+
+// Keywords that set an upper bound on a value.
+const MAXIMUMS: &[&str] = &[
+    "maximum",
+    "exclusiveMaximum",
+    "maxLength",
+    "maxItems",
+    "maxProperties",
+    "maxContains",
+];
+
+const TRUE: Value = Value::Bool(true);
+
+/// superset returns `Ok(())` if schema `a` accepts every instance that schema
+/// `b` accepts. Otherwise it returns the JSON Pointer to the keyword of `a`
+/// that may reject instances that `b` accepts.
+pub(crate) fn superset(a: &Value, b: &Value) -> Result<(), String> {
+    check(a, b, "")
+}
+
+fn check(a: &Value, b: &Value, at: &str) -> Result<(), String> {
+    if a == b {
+        return Ok(());
+    }
+    let a = match a {
+        Value::Bool(true) => return Ok(()),
+        Value::Object(a) => a,
+        _ => return Err(at.to_string()),
+    };
+    let empty = Map::new();
+    let b_map = match b {
+        Value::Bool(false) => return Ok(()),
+        Value::Bool(true) => &empty,
+        Value::Object(b) => b,
+        _ => return Err(at.to_string()),
+    };
+
+    for (key, value) in a {
+        if ANNOTATIONS.contains(&key.as_str()) {
+            continue;
+        }
+        let at = format!("{at}/{}", escape(key));
+        let other = b_map.get(key);
+        let ok = match key.as_str() {
+            "type" => types(value, b_map),
+            "required" => required(value, other),
+            "enum" => values(b_map).is_some_and(|vals| {
+                vals.iter()
+                    .all(|v| value.as_array().is_some_and(|e| e.contains(*v)))
+            }),
+            "const" => values(b_map).is_some_and(|vals| vals.iter().all(|v| *v == value)),
+            "properties" => {
+                properties(value, b_map, &at)?;
+                true
+            }
+            "additionalProperties" => {
+                additional_properties(value, a, b_map, &at)?;
+                true
+            }
+            "items" if value.is_object() || value.is_boolean() => {
+                if a.get("prefixItems") != b_map.get("prefixItems")
+                    || other.is_some_and(Value::is_array)
+                {
+                    false
+                } else {
+                    check(value, other.unwrap_or(&TRUE), &at)?;
+                    true
+                }
+            }
+            "allOf" => {
+                for (i, s) in value.as_array().into_iter().flatten().enumerate() {
+                    check(s, b, &format!("{at}/{i}"))?;
+                }
+                true
+            }
+            "anyOf" => value
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|s| check(s, b, &at).is_ok()),
+            k if MINIMUMS.contains(&k) => bound(value, other, |a, b| b >= a),
+            k if MAXIMUMS.contains(&k) => bound(value, other, |a, b| b <= a),
+            _ => other == Some(value),
+        };
+        if !ok {
+            return Err(at);
+        }
+    }
+    Ok(())
+}
+
+/// types returns true if the types allowed by the `type` keyword value `a`
+/// include all of the types of the values that `b` accepts.
+fn types(a: &Value, b: &Map<String, Value>) -> bool {
+    let allowed = type_names(a);
+    let includes =
+        |t: &str| allowed.contains(&t) || (t == "integer" && allowed.contains(&"number"));
+    if let Some(t) = b.get("type") {
+        return type_names(t).into_iter().all(includes);
+    }
+    values(b).is_some_and(|vals| {
+        vals.iter().all(|v| match v {
+            Value::Number(n) if n.is_i64() || n.is_u64() => includes("integer"),
+            v => includes(type_of(v)),
+        })
+    })
+}
+
+fn type_names(t: &Value) -> Vec<&str> {
+    match t {
+        Value::String(s) => vec![s.as_str()],
+        Value::Array(list) => list.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn type_of(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// values returns the values allowed by the `const` or `enum` keyword of
+/// `b`, if it has either.
+fn values(b: &Map<String, Value>) -> Option<Vec<&Value>> {
+    if let Some(c) = b.get("const") {
+        return Some(vec![c]);
+    }
+    b.get("enum")
+        .and_then(Value::as_array)
+        .map(|e| e.iter().collect())
+}
+
+/// required returns true if the properties required by `a` are all required
+/// by `b`.
+fn required(a: &Value, b: Option<&Value>) -> bool {
+    let b = b.and_then(Value::as_array);
+    a.as_array()
+        .into_iter()
+        .flatten()
+        .all(|r| b.is_some_and(|b| b.contains(r)))
+}
+
+/// bound returns true if the numeric bound `b` satisfies `cmp` with the
+/// numeric bound `a`.
+fn bound(a: &Value, b: Option<&Value>, cmp: impl Fn(f64, f64) -> bool) -> bool {
+    match (a.as_f64(), b.and_then(Value::as_f64)) {
+        (Some(a), Some(b)) => cmp(a, b),
+        _ => Some(a) == b,
+    }
+}
+
+/// additional_for returns the schema that `b` applies to object properties
+/// it does not list in `properties`. Returns the `true` schema if `b` has
+/// `patternProperties` unlike those of `a`, since a property matching one of
+/// them is not subject to `additionalProperties`.
+fn additional_for<'a>(a: &Map<String, Value>, b: &'a Map<String, Value>) -> &'a Value {
+    if b.contains_key("patternProperties")
+        && a.get("patternProperties") != b.get("patternProperties")
+    {
+        return &TRUE;
+    }
+    b.get("additionalProperties").unwrap_or(&TRUE)
+}
+
+/// properties checks that each schema in the `properties` keyword value `a`
+/// accepts all values that `b` accepts for the same property.
+fn properties(a: &Value, b: &Map<String, Value>, at: &str) -> Result<(), String> {
+    let Some(props) = a.as_object() else {
+        return Err(at.to_string());
+    };
+    let b_props = b.get("properties").and_then(Value::as_object);
+    for (name, schema) in props {
+        let other = match b_props.and_then(|p| p.get(name)) {
+            Some(s) => s,
+            None if b.contains_key("patternProperties") => &TRUE,
+            None => b.get("additionalProperties").unwrap_or(&TRUE),
+        };
+        check(schema, other, &format!("{at}/{}", escape(name)))?;
+    }
+    Ok(())
+}
+
+/// additional_properties checks that the `additionalProperties` keyword value
+/// `ap` of `a` accepts all values that `b` accepts for the properties that
+/// `a` does not list.
+fn additional_properties(
+    ap: &Value,
+    a: &Map<String, Value>,
+    b: &Map<String, Value>,
+    at: &str,
+) -> Result<(), String> {
+    let a_props = a.get("properties").and_then(Value::as_object);
+    let b_props = b.get("properties").and_then(Value::as_object);
+    for (name, schema) in b_props.into_iter().flatten() {
+        if !a_props.is_some_and(|p| p.contains_key(name)) {
+            check(ap, schema, at)?;
+        }
+    }
+    check(ap, additional_for(a, b), at)
+}
+
+/// escape escapes `token` for use in a JSON Pointer.
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_compatibility() {
+        for (name, level, backward, forward, transitive) in [
+            ("NONE", Compatibility::None, false, false, false),
+            ("backward", Compatibility::Backward, true, false, false),
+            (
+                "Backward_Transitive",
+                Compatibility::BackwardTransitive,
+                true,
+                false,
+                true,
+            ),
+            ("FORWARD", Compatibility::Forward, false, true, false),
+            (
+                "FORWARD_TRANSITIVE",
+                Compatibility::ForwardTransitive,
+                false,
+                true,
+                true,
+            ),
+            ("FULL", Compatibility::Full, true, true, false),
+            (
+                "FULL_TRANSITIVE",
+                Compatibility::FullTransitive,
+                true,
+                true,
+                true,
+            ),
+        ] {
+            assert_eq!(Ok(level), Compatibility::from_name(name), "{name}");
+            assert_eq!(name.to_ascii_uppercase(), level.name(), "{name}");
+            assert_eq!(backward, level.backward(), "{name}");
+            assert_eq!(forward, level.forward(), "{name}");
+            assert_eq!(transitive, level.transitive(), "{name}");
+        }
+        assert!(Compatibility::from_name("SIDEWAYS").is_err());
+    }
+
+    #[test]
+    fn test_superset() {
+        for (name, a, b, res) in [
+            (
+                "same",
+                json!({"type": "string"}),
+                json!({"type": "string"}),
+                Ok(()),
+            ),
+            ("true", json!(true), json!({"type": "string"}), Ok(())),
+            ("empty", json!({}), json!({"type": "string"}), Ok(())),
+            ("false", json!({"type": "string"}), json!(false), Ok(())),
+            ("false a", json!(false), json!(true), Err("")),
+            (
+                "annotations",
+                json!({"title": "x"}),
+                json!({"type": "string"}),
+                Ok(()),
+            ),
+            ("type", json!({"type": "string"}), json!({}), Err("/type")),
+            (
+                "type list",
+                json!({"type": ["string", "null"]}),
+                json!({"type": "null"}),
+                Ok(()),
+            ),
+            (
+                "type narrower",
+                json!({"type": "string"}),
+                json!({"type": ["string", "null"]}),
+                Err("/type"),
+            ),
+            (
+                "integer",
+                json!({"type": "number"}),
+                json!({"type": "integer"}),
+                Ok(()),
+            ),
+            (
+                "number",
+                json!({"type": "integer"}),
+                json!({"type": "number"}),
+                Err("/type"),
+            ),
+            (
+                "type enum",
+                json!({"type": "integer"}),
+                json!({"enum": [1, 2]}),
+                Ok(()),
+            ),
+            (
+                "type const",
+                json!({"type": "string"}),
+                json!({"const": 1}),
+                Err("/type"),
+            ),
+            (
+                "required",
+                json!({"required": ["x"]}),
+                json!({"required": ["x", "y"]}),
+                Ok(()),
+            ),
+            (
+                "required added",
+                json!({"required": ["x", "y"]}),
+                json!({"required": ["x"]}),
+                Err("/required"),
+            ),
+            (
+                "enum",
+                json!({"enum": [1, 2, 3]}),
+                json!({"enum": [1, 2]}),
+                Ok(()),
+            ),
+            (
+                "enum removed",
+                json!({"enum": [1]}),
+                json!({"enum": [1, 2]}),
+                Err("/enum"),
+            ),
+            (
+                "enum const",
+                json!({"enum": [1, 2]}),
+                json!({"const": 2}),
+                Ok(()),
+            ),
+            ("const", json!({"const": 2}), json!({"enum": [2]}), Ok(())),
+            (
+                "const changed",
+                json!({"const": 2}),
+                json!({"const": 3}),
+                Err("/const"),
+            ),
+            (
+                "minimum",
+                json!({"minimum": 1}),
+                json!({"minimum": 2}),
+                Ok(()),
+            ),
+            (
+                "minimum raised",
+                json!({"minimum": 2}),
+                json!({"minimum": 1}),
+                Err("/minimum"),
+            ),
+            (
+                "minimum added",
+                json!({"minimum": 2}),
+                json!({}),
+                Err("/minimum"),
+            ),
+            (
+                "maxLength",
+                json!({"maxLength": 10}),
+                json!({"maxLength": 5}),
+                Ok(()),
+            ),
+            (
+                "maxLength lowered",
+                json!({"maxLength": 5}),
+                json!({"maxLength": 10}),
+                Err("/maxLength"),
+            ),
+            (
+                "property",
+                json!({"properties": {"x": {"type": "number"}}}),
+                json!({"properties": {"x": {"type": "integer"}}}),
+                Ok(()),
+            ),
+            (
+                "property narrowed",
+                json!({"properties": {"x": {"type": "integer"}}}),
+                json!({"properties": {"x": {"type": "number"}}}),
+                Err("/properties/x/type"),
+            ),
+            (
+                "property added",
+                json!({"properties": {"x": {"type": "integer"}, "a/b": {"type": "string"}}}),
+                json!({"properties": {"x": {"type": "integer"}}}),
+                Err("/properties/a~1b/type"),
+            ),
+            (
+                "property closed",
+                json!({"properties": {"y": {"type": "string"}}}),
+                json!({"properties": {"x": {}}, "additionalProperties": false}),
+                Ok(()),
+            ),
+            (
+                "additionalProperties",
+                json!({"properties": {"x": {}, "y": {}}, "additionalProperties": false}),
+                json!({"properties": {"x": {}}, "additionalProperties": false}),
+                Ok(()),
+            ),
+            (
+                "additionalProperties removed property",
+                json!({"properties": {"x": {}}, "additionalProperties": false}),
+                json!({"properties": {"x": {}, "y": {}}, "additionalProperties": false}),
+                Err("/additionalProperties"),
+            ),
+            (
+                "additionalProperties added",
+                json!({"properties": {"x": {}}, "additionalProperties": false}),
+                json!({"properties": {"x": {}}}),
+                Err("/additionalProperties"),
+            ),
+            (
+                "additionalProperties patterns",
+                json!({"additionalProperties": {"type": "string"}}),
+                json!({"patternProperties": {"^x": {}}, "additionalProperties": {"type": "string"}}),
+                Err("/additionalProperties/type"),
+            ),
+            (
+                "items",
+                json!({"items": {"type": "number"}}),
+                json!({"items": {"type": "integer"}}),
+                Ok(()),
+            ),
+            (
+                "items narrowed",
+                json!({"items": {"type": "integer"}}),
+                json!({"items": {"type": "number"}}),
+                Err("/items/type"),
+            ),
+            (
+                "items prefixItems",
+                json!({"items": {"type": "number"}}),
+                json!({"prefixItems": [{}], "items": {"type": "number"}}),
+                Err("/items"),
+            ),
+            (
+                "allOf",
+                json!({"allOf": [{"type": "object"}, {"required": ["x"]}]}),
+                json!({"type": "object", "required": ["x"]}),
+                Ok(()),
+            ),
+            (
+                "allOf narrowed",
+                json!({"allOf": [{"type": "object"}, {"required": ["y"]}]}),
+                json!({"type": "object", "required": ["x"]}),
+                Err("/allOf/1/required"),
+            ),
+            (
+                "anyOf",
+                json!({"anyOf": [{"type": "string"}, {"type": "number"}]}),
+                json!({"type": "integer"}),
+                Ok(()),
+            ),
+            (
+                "anyOf narrowed",
+                json!({"anyOf": [{"type": "string"}, {"type": "number"}]}),
+                json!({"type": "null"}),
+                Err("/anyOf"),
+            ),
+            (
+                "other",
+                json!({"pattern": "^a"}),
+                json!({"pattern": "^a"}),
+                Ok(()),
+            ),
+            (
+                "other changed",
+                json!({"pattern": "^a"}),
+                json!({"pattern": "^ab"}),
+                Err("/pattern"),
+            ),
+            (
+                "$ref",
+                json!({"$ref": "#/$defs/x"}),
+                json!({}),
+                Err("/$ref"),
+            ),
+        ] {
+            assert_eq!(res.map_err(String::from), superset(&a, &b), "{name}");
+        }
+    }
+}
@@ -0,0 +1,567 @@
+//! `$data` references.
+//!
+//! The [`$data` proposal], as supported by AJV, allows the values of
+//! comparison keywords to be relative JSON Pointers into the instance, as in
+//! `"minimum": {"$data": "1/minPrice"}`. When enabled, by the
+//! `jsonschema.data_references` configuration or the `data_refs` argument,
+//! `compile()` calls `strip()` to remove such keywords from the schemas it
+//! compiles with `boon`, which would otherwise reject them as invalid, and
+//! the keyword pass calls `check()` to resolve their pointers against the
+//! document and apply them to the values they point to. Keywords whose
+//! pointers resolve to nothing are ignored.
+//!
+//!   [`$data` proposal]: https://github.com/json-schema-org/json-schema-spec/issues/51
+
+use std::borrow::Cow;
+use std::cell::Cell;
+
+use serde_json::{Map, Value};
+
+use crate::{Restore, DATA_REFERENCES};
+
+/// The keywords whose values may be `$data` references.
+const KEYWORDS: &[&str] = &[
+    "const",
+    "enum",
+    "exclusiveMaximum",
+    "exclusiveMinimum",
+    "maxItems",
+    "maxLength",
+    "maxProperties",
+    "maximum",
+    "minItems",
+    "minLength",
+    "minProperties",
+    "minimum",
+    "multipleOf",
+    "required",
+    "uniqueItems",
+];
+
+thread_local! {
+    // Overrides jsonschema.data_references for the current call.
+    static ENABLED: Cell<Option<bool>> = const { Cell::new(None) };
+}
+
+/// enabled returns true if `$data` references are enabled for the current
+/// call or by jsonschema.data_references.
+pub(crate) fn enabled() -> bool {
+    ENABLED.get().unwrap_or_else(|| DATA_REFERENCES.get())
+}
+
+/// with_enabled calls `f` with `$data` references enabled or disabled,
+/// regardless of jsonschema.data_references.
+pub(crate) fn with_enabled<R>(enabled: bool, f: impl FnOnce() -> R) -> R {
+    let prev = ENABLED.replace(Some(enabled));
+    let _restore = Restore::new(|| ENABLED.set(prev));
+    f()
+}
+
+/// strip returns `schemas` without the keywords of their subschemas whose
+/// values are `$data` references if `$data` references are enabled, and
+/// unchanged if not.
+pub(crate) fn strip(schemas: &[Value]) -> Cow<'_, [Value]> {
+    if !enabled() {
+        return Cow::Borrowed(schemas);
+    }
+    Cow::Owned(
+        schemas
+            .iter()
+            .map(|s| {
+                let mut s = s.clone();
+                strip_schema(&mut s);
+                s
+            })
+            .collect(),
+    )
+}
+
+/// strip_schema removes the keywords of `schema` and its subschemas whose
+/// values are `$data` references.
+fn strip_schema(schema: &mut Value) {
+    let Value::Object(obj) = schema else {
+        return;
+    };
+    obj.retain(|kw, v| !(KEYWORDS.contains(&kw.as_str()) && data_ref(v).is_some()));
+
+    for (kw, v) in obj.iter_mut() {
+        match kw.as_str() {
+            // Keywords whose values are maps of subschemas.
+            "properties" | "patternProperties" | "$defs" | "definitions" | "dependentSchemas"
+            | "dependencies" => {
+                if let Value::Object(o) = v {
+                    o.values_mut().for_each(strip_schema);
+                }
+            }
+            // Keywords whose values are not schemas.
+            "const" | "enum" | "default" | "examples" | "required" => {}
+            // Keywords whose values are schemas or arrays of schemas.
+            _ => match v {
+                Value::Array(a) => a.iter_mut().for_each(strip_schema),
+                _ => strip_schema(v),
+            },
+        }
+    }
+}
+
+/// data_ref returns the pointer of `value` if it's a `$data` reference, an
+/// object with only a `$data` string.
+fn data_ref(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Object(o) if o.len() == 1 => o.get("$data").filter(|v| v.is_string()),
+        _ => None,
+    }
+}
+
+/// found returns true if `obj` has a keyword whose value is a `$data`
+/// reference.
+pub(crate) fn found(obj: &Map<String, Value>) -> bool {
+    KEYWORDS
+        .iter()
+        .any(|kw| obj.get(*kw).and_then(data_ref).is_some())
+}
+
+/// check applies each keyword of `obj` whose value is a `$data` reference to
+/// `instance`, at the location `path` in `root`, with the value its pointer
+/// resolves to, calling `fail` with the keyword and an error message for
+/// each that fails.
+pub(crate) fn check(
+    obj: &Map<String, Value>,
+    root: &Value,
+    path: &[Token],
+    instance: &Value,
+    mut fail: impl FnMut(&str, String),
+) {
+    for kw in KEYWORDS {
+        let Some(Value::String(ptr)) = obj.get(*kw).and_then(data_ref) else {
+            continue;
+        };
+        if let Some(want) = resolve(root, path, ptr) {
+            if let Err(e) = apply(kw, &want, instance) {
+                fail(kw, e);
+            }
+        }
+    }
+}
+
+/// pointer returns the JSON Pointer for the location `path`.
+pub(crate) fn pointer(path: &[Token]) -> String {
+    path.iter()
+        .map(|t| match t {
+            Token::Prop(p) => format!("/{}", p.replace('~', "~0").replace('/', "~1")),
+            Token::Item(i) => format!("/{i}"),
+        })
+        .collect()
+}
+
+/// path returns the location of the value at the JSON Pointer `ptr` in
+/// `root`, or `None` if it points to nothing.
+pub(crate) fn path(root: &Value, ptr: &str) -> Option<Vec<Token>> {
+    let mut v = root;
+    let mut path = Vec::new();
+    for t in ptr.split('/').skip(1) {
+        let t = t.replace("~1", "/").replace("~0", "~");
+        let token = match v {
+            Value::Array(a) => Token::Item(t.parse().ok().filter(|i| *i < a.len())?),
+            Value::Object(o) if o.contains_key(&t) => Token::Prop(t),
+            _ => return None,
+        };
+        v = match &token {
+            Token::Prop(p) => &v[p.as_str()],
+            Token::Item(i) => &v[*i],
+        };
+        path.push(token);
+    }
+    Some(path)
+}
+
+/// Token is a token of the location of a value in a document.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    Prop(String),
+    Item(usize),
+}
+
+/// resolve resolves `ptr`, a relative JSON Pointer from the location `path`
+/// in `root` or an absolute JSON Pointer, returning `None` if it points to
+/// nothing.
+pub(crate) fn resolve(root: &Value, path: &[Token], ptr: &str) -> Option<Value> {
+    if ptr.is_empty() || ptr.starts_with('/') {
+        return root.pointer(ptr).cloned();
+    }
+    let digits = ptr.find(|c: char| !c.is_ascii_digit()).unwrap_or(ptr.len());
+    let (up, rest) = ptr.split_at(digits);
+    if up.is_empty() || (up.len() > 1 && up.starts_with('0')) {
+        return None;
+    }
+    let up: usize = up.parse().ok()?;
+    let path = &path[..path.len().checked_sub(up)?];
+    if rest == "#" {
+        // The name or index of the location.
+        return match path.last()? {
+            Token::Prop(p) => Some(Value::String(p.to_string())),
+            Token::Item(i) => Some(Value::from(*i)),
+        };
+    }
+    if !rest.is_empty() && !rest.starts_with('/') {
+        return None;
+    }
+    let mut v = root;
+    for t in path {
+        v = match t {
+            Token::Prop(p) => v.get(p)?,
+            Token::Item(i) => v.get(i)?,
+        };
+    }
+    v.pointer(rest).cloned()
+}
+
+/// apply applies the keyword `kw` with the value `want` to `instance`.
+fn apply(kw: &str, want: &Value, instance: &Value) -> Result<(), String> {
+    match kw {
+        "const" if !equal(want, instance) => return Err(format!("value must be {want}")),
+        "enum" => {
+            let Value::Array(want) = want else {
+                return Err(format!("$data for {kw} must be an array, but got {want}"));
+            };
+            if !want.iter().any(|w| equal(w, instance)) {
+                return Err(format!(
+                    "value must be one of {}",
+                    Value::Array(want.clone())
+                ));
+            }
+        }
+        "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum" | "multipleOf" => {
+            let Some(want) = want.as_f64() else {
+                return Err(format!("$data for {kw} must be a number, but got {want}"));
+            };
+            let Some(got) = instance.as_f64() else {
+                return Ok(());
+            };
+            let (ok, op) = match kw {
+                "minimum" => (got >= want, ">="),
+                "maximum" => (got <= want, "<="),
+                "exclusiveMinimum" => (got > want, ">"),
+                "exclusiveMaximum" => (got < want, "<"),
+                _ => (want > 0.0 && (got / want).fract() == 0.0, "multipleOf"),
+            };
+            if !ok {
+                return Err(format!("must be {op} {want}, but got {got}"));
+            }
+        }
+        "minLength" | "maxLength" | "minItems" | "maxItems" | "minProperties" | "maxProperties" => {
+            let Some(want) = want.as_u64() else {
+                return Err(format!(
+                    "$data for {kw} must be a non-negative integer, but got {want}"
+                ));
+            };
+            let got = match (kw, instance) {
+                ("minLength" | "maxLength", Value::String(s)) => s.chars().count(),
+                ("minItems" | "maxItems", Value::Array(a)) => a.len(),
+                ("minProperties" | "maxProperties", Value::Object(o)) => o.len(),
+                _ => return Ok(()),
+            } as u64;
+            let (ok, op) = if kw.starts_with("min") {
+                (got >= want, ">=")
+            } else {
+                (got <= want, "<=")
+            };
+            if !ok {
+                let what = &kw[3..].to_ascii_lowercase();
+                return Err(format!("{what} must be {op} {want}, but got {got}"));
+            }
+        }
+        "required" => {
+            let Value::Array(want) = want else {
+                return Err(format!("$data for {kw} must be an array, but got {want}"));
+            };
+            let Value::Object(obj) = instance else {
+                return Ok(());
+            };
+            let missing: Vec<_> = want
+                .iter()
+                .filter_map(Value::as_str)
+                .filter(|p| !obj.contains_key(*p))
+                .collect();
+            if !missing.is_empty() {
+                return Err(format!("missing properties {}", missing.join(", ")));
+            }
+        }
+        "uniqueItems" => {
+            let Value::Bool(want) = want else {
+                return Err(format!("$data for {kw} must be a boolean, but got {want}"));
+            };
+            let Value::Array(a) = instance else {
+                return Ok(());
+            };
+            if *want {
+                for (j, b) in a.iter().enumerate() {
+                    if let Some(i) = a[..j].iter().position(|x| equal(x, b)) {
+                        return Err(format!("items at {i} and {j} are equal"));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// equal returns true if `a` and `b` are equal JSON values, comparing
+/// numbers by value, so that `1` equals `1.0`.
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len() && a.iter().all(|(k, v)| b.get(k).is_some_and(|w| equal(v, w)))
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_strip_schema() {
+        let mut schema = json!({
+            "type": "object",
+            "properties": {
+                "minPrice": {"type": "number"},
+                "maxPrice": {"type": "number", "minimum": {"$data": "1/minPrice"}},
+                "minimum": {"$data": "1/minPrice"},
+                "password": {"type": "string"},
+                "confirm": {"const": {"$data": "1/password"}, "minLength": 8},
+                "tags": {"items": {"enum": {"$data": "2/allowed"}}},
+            },
+            "allOf": [{"required": {"$data": "0/must"}}],
+            "default": {"minimum": {"$data": "1/x"}},
+            "minimum": {"$data": "/a", "b": 1},
+        });
+        strip_schema(&mut schema);
+        assert_eq!(
+            json!({
+                "type": "object",
+                "properties": {
+                    "minPrice": {"type": "number"},
+                    "maxPrice": {"type": "number"},
+                    "minimum": {"$data": "1/minPrice"},
+                    "password": {"type": "string"},
+                    "confirm": {"minLength": 8},
+                    "tags": {"items": {}},
+                },
+                "allOf": [{}],
+                "default": {"minimum": {"$data": "1/x"}},
+                "minimum": {"$data": "/a", "b": 1},
+            }),
+            schema,
+        );
+    }
+
+    #[test]
+    fn test_found() {
+        for (schema, found) in [
+            (json!({"minimum": {"$data": "1/min"}}), true),
+            (json!({"const": {"$data": "/pw"}, "type": "string"}), true),
+            (json!({"minimum": 1}), false),
+            (json!({"minimum": {"$data": "/a", "b": 1}}), false),
+            (json!({"type": {"$data": "1/t"}}), false),
+            (
+                json!({"properties": {"a": {"minimum": {"$data": "1/b"}}}}),
+                false,
+            ),
+        ] {
+            let Value::Object(obj) = &schema else {
+                unreachable!()
+            };
+            assert_eq!(found, super::found(obj), "{schema}");
+        }
+    }
+
+    #[test]
+    fn test_pointer() {
+        assert_eq!("", pointer(&[]));
+        assert_eq!(
+            "/a~1b/0/c~0d",
+            pointer(&[
+                Token::Prop("a/b".to_string()),
+                Token::Item(0),
+                Token::Prop("c~d".to_string()),
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_path() {
+        let root = json!({"a/b": [1, {"c~d": 2}], "0": 3});
+        assert_eq!(Some(vec![]), path(&root, ""));
+        assert_eq!(
+            Some(vec![
+                Token::Prop("a/b".to_string()),
+                Token::Item(1),
+                Token::Prop("c~d".to_string()),
+            ]),
+            path(&root, "/a~1b/1/c~0d"),
+        );
+        assert_eq!(Some(vec![Token::Prop("0".to_string())]), path(&root, "/0"));
+        assert_eq!(None, path(&root, "/a~1b/2"));
+        assert_eq!(None, path(&root, "/x"));
+        assert_eq!(None, path(&root, "/0/x"));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let root = json!({"min": 1, "items": [{"x": 2}, {"x": 3}]});
+        let path = vec![
+            Token::Prop("items".to_string()),
+            Token::Item(1),
+            Token::Prop("x".to_string()),
+        ];
+        for (ptr, want) in [
+            ("0", Some(json!(3))),
+            ("1/x", Some(json!(3))),
+            ("2/0/x", Some(json!(2))),
+            ("3/min", Some(json!(1))),
+            ("0#", Some(json!("x"))),
+            ("1#", Some(json!(1))),
+            ("/min", Some(json!(1))),
+            ("", Some(root.clone())),
+            ("4", None),
+            ("3#", None),
+            ("1/y", None),
+            ("01/x", None),
+            ("1x", None),
+            ("x", None),
+        ] {
+            assert_eq!(want, resolve(&root, &path, ptr), "{ptr}");
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        for (kw, want, instance, res) in [
+            ("minimum", json!(5), json!(5), Ok(())),
+            (
+                "minimum",
+                json!(5),
+                json!(4),
+                Err("must be >= 5, but got 4"),
+            ),
+            (
+                "maximum",
+                json!(5),
+                json!(6),
+                Err("must be <= 5, but got 6"),
+            ),
+            (
+                "exclusiveMinimum",
+                json!(5),
+                json!(5),
+                Err("must be > 5, but got 5"),
+            ),
+            ("exclusiveMaximum", json!(5), json!(4.5), Ok(())),
+            ("multipleOf", json!(2), json!(6), Ok(())),
+            (
+                "multipleOf",
+                json!(2),
+                json!(7),
+                Err("must be multipleOf 2, but got 7"),
+            ),
+            ("minimum", json!(5), json!("x"), Ok(())),
+            (
+                "minimum",
+                json!("5"),
+                json!(4),
+                Err(r#"$data for minimum must be a number, but got "5""#),
+            ),
+            ("const", json!("pw"), json!("pw"), Ok(())),
+            ("const", json!(1), json!(1.0), Ok(())),
+            (
+                "const",
+                json!("pw"),
+                json!("px"),
+                Err(r#"value must be "pw""#),
+            ),
+            ("enum", json!([1, 2]), json!(2), Ok(())),
+            (
+                "enum",
+                json!([1, 2]),
+                json!(3),
+                Err("value must be one of [1,2]"),
+            ),
+            (
+                "minLength",
+                json!(3),
+                json!("ab"),
+                Err("length must be >= 3, but got 2"),
+            ),
+            (
+                "maxItems",
+                json!(1),
+                json!([1, 2]),
+                Err("items must be <= 1, but got 2"),
+            ),
+            (
+                "minProperties",
+                json!(1),
+                json!({}),
+                Err("properties must be >= 1, but got 0"),
+            ),
+            ("maxLength", json!(3), json!(12345), Ok(())),
+            (
+                "required",
+                json!(["a", "b"]),
+                json!({"a": 1}),
+                Err("missing properties b"),
+            ),
+            ("required", json!(["a"]), json!({"a": 1}), Ok(())),
+            (
+                "uniqueItems",
+                json!(true),
+                json!([1, 2, 1]),
+                Err("items at 0 and 2 are equal"),
+            ),
+            ("uniqueItems", json!(false), json!([1, 1]), Ok(())),
+        ] {
+            assert_eq!(
+                res.map_err(str::to_string),
+                apply(kw, &want, &instance),
+                "{kw} {want} {instance}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let schema = json!({
+            "minimum": {"$data": "1/min"},
+            "maximum": {"$data": "1/nope"},
+            "multipleOf": 2,
+        });
+        let Value::Object(obj) = &schema else {
+            unreachable!()
+        };
+        let root = json!({"min": 3, "max": 2});
+        let path = [Token::Prop("max".to_string())];
+        let mut errors = Vec::new();
+        check(obj, &root, &path, &root["max"], |kw, e| {
+            errors.push((kw.to_string(), e))
+        });
+        assert_eq!(
+            vec![("minimum".to_string(), "must be >= 3, but got 2".to_string())],
+            errors,
+        );
+
+        // Keywords whose pointers resolve to nothing are ignored.
+        errors.clear();
+        check(obj, &root, &[], &json!(1), |kw, e| {
+            errors.push((kw.to_string(), e))
+        });
+        assert!(errors.is_empty());
+    }
+}
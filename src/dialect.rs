@@ -0,0 +1,315 @@
+//! Custom `$schema` dialects: meta-schemas that extend a supported draft.
+
+use std::rc::Rc;
+
+use boon::{CompileError, Compiler};
+use pgrx::prelude::*;
+use pgrx::JsonB;
+use serde_json::Value;
+
+use crate::{compile, extension_table, invalidate_table, resource_id, Draft, TableCache};
+
+extension_sql!(
+    r#"
+-- jsonschema_dialects stores the meta-schemas of custom "$schema" dialects
+-- by their "$id"s, along with the draft each extends.
+CREATE TABLE jsonschema_dialects (
+	id     TEXT             PRIMARY KEY,
+	draft  jsonschema_draft NOT NULL,
+	schema JSONB            NOT NULL
+);
+
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_dialects', '');
+"#,
+    name = "jsonschema_dialects",
+    requires = ["jsonschema_draft"],
+);
+
+/// jsonschema_register_dialect registers the dialect `schema` under its `$id`.
+#[pg_extern(volatile, strict)]
+fn jsonschema_register_dialect(schema: JsonB) -> String {
+    let Value::String(id) = &schema.0["$id"] else {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"cannot register a dialect without an "$id""#,
+        );
+    };
+    let id = id.to_string();
+    if Draft::from_url(&id).is_some() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(r#"cannot register dialect "{id}": it is a supported draft"#),
+        );
+    }
+
+    let draft = schema.0["$schema"]
+        .as_str()
+        .filter(|url| !same_url(url, &id))
+        .and_then(|url| Draft::from_url(url).or_else(|| draft_for(url)));
+    let Some(draft) = draft else {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(
+                r#"invalid dialect "{id}": "$schema" must name a supported draft or another registered dialect"#
+            ),
+        );
+    };
+    if let Err(e) = compile(&id, &[schema.0.clone()]) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(r#"invalid dialect "{id}": {e:#}"#),
+        );
+    }
+
+    Spi::run_with_args(
+        &format!(
+            "INSERT INTO {} (id, draft, schema) VALUES ($1, $2, $3)
+             ON CONFLICT (id) DO UPDATE SET draft = EXCLUDED.draft, schema = EXCLUDED.schema",
+            extension_table("jsonschema_dialects"),
+        ),
+        &[id.as_str().into(), draft.into(), schema.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    invalidate_table("jsonschema_dialects");
+    id
+}
+
+/// jsonschema_unregister_dialect removes the dialect registered under `id`.
+#[pg_extern(volatile, strict)]
+fn jsonschema_unregister_dialect(id: &str) -> bool {
+    let deleted = Spi::get_one_with_args::<bool>(
+        &format!(
+            "WITH d AS (DELETE FROM {} WHERE id = $1 RETURNING 1) SELECT count(*) > 0 FROM d",
+            extension_table("jsonschema_dialects"),
+        ),
+        &[id.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"))
+    .unwrap_or_default();
+    invalidate_table("jsonschema_dialects");
+    deleted
+}
+
+/// add_resources adds the dialects not among `schemas` to `compiler`.
+pub(crate) fn add_resources(
+    compiler: &mut Compiler,
+    id: &str,
+    schemas: &[Value],
+) -> Result<(), CompileError> {
+    if !schemas.iter().any(names_dialect) {
+        return Ok(());
+    }
+    let ids: Vec<_> = schemas
+        .iter()
+        .enumerate()
+        .map(|(i, s)| resource_id(id, i, s))
+        .collect();
+    for d in registered().iter() {
+        if !ids.iter().any(|id| same_url(id, &d.id)) {
+            compiler.add_resource(&d.id, relax(&d.schema))?;
+        }
+    }
+    Ok(())
+}
+
+/// draft_for returns the draft extended by the dialect registered under `url`.
+pub(crate) fn draft_for(url: &str) -> Option<Draft> {
+    registered()
+        .iter()
+        .find(|d| same_url(&d.id, url))
+        .map(|d| d.draft)
+}
+
+/// names_dialect returns true if `schema` may name a dialect or reference one.
+fn names_dialect(schema: &Value) -> bool {
+    match schema {
+        Value::Object(obj) => {
+            ["$schema", "$ref", "$dynamicRef"].iter().any(|key| {
+                obj.get(*key).and_then(Value::as_str).is_some_and(|url| {
+                    let base = url.split('#').next().unwrap_or_default();
+                    !base.is_empty() && Draft::from_url(base).is_none()
+                })
+            }) || obj.values().any(names_dialect)
+        }
+        Value::Array(list) => list.iter().any(names_dialect),
+        _ => false,
+    }
+}
+
+/// same_url returns true if `a` and `b` are equal, ignoring an empty fragment.
+fn same_url(a: &str, b: &str) -> bool {
+    a.strip_suffix('#').unwrap_or(a) == b.strip_suffix('#').unwrap_or(b)
+}
+
+/// relax returns `schema` with the vocabularies no draft defines made optional.
+fn relax(schema: &Value) -> Value {
+    let mut schema = schema.clone();
+    if let Some(Value::Object(vocabs)) = schema.get_mut("$vocabulary") {
+        for (url, required) in vocabs.iter_mut() {
+            if !url.starts_with("https://json-schema.org/draft/") {
+                *required = Value::Bool(false);
+            }
+        }
+    }
+    schema
+}
+
+/// Dialect is a registered dialect.
+struct Dialect {
+    id: String,
+    draft: Draft,
+    schema: Value,
+}
+
+thread_local! {
+    // The registered dialects, reused until the table changes.
+    static CACHE: TableCache<Vec<Dialect>> = const { TableCache::new("jsonschema_dialects") };
+}
+
+/// invalidate clears the cached dialects on an invalidation of `relid`.
+pub(crate) fn invalidate(relid: pg_sys::Oid) {
+    CACHE.with(|cache| cache.invalidate(relid));
+}
+
+/// registered returns the registered dialects, cached until the table changes.
+fn registered() -> Rc<Vec<Dialect>> {
+    CACHE.with(|cache| cache.get(load))
+}
+
+/// load loads the registered dialects.
+fn load() -> Vec<Dialect> {
+    Spi::connect(|client| {
+        client
+            .select(
+                &format!(
+                    "SELECT id, draft, schema FROM {} ORDER BY id",
+                    extension_table("jsonschema_dialects")
+                ),
+                None,
+                &[],
+            )?
+            .map(|row| {
+                Ok((
+                    row.get::<String>(1)?,
+                    row.get::<Draft>(2)?,
+                    row.get::<JsonB>(3)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, pgrx::spi::SpiError>>()
+    })
+    .unwrap_or_else(|e| error!("{e}"))
+    .into_iter()
+    .filter_map(|(id, draft, schema)| {
+        Some(Dialect {
+            id: id?,
+            draft: draft?,
+            schema: schema?.0,
+        })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_relax() {
+        let meta = json!({
+            "$id": "https://spec.openapis.org/oas/3.1/dialect/base",
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$vocabulary": {
+                "https://json-schema.org/draft/2020-12/vocab/core": true,
+                "https://json-schema.org/draft/2020-12/vocab/applicator": true,
+                "https://example.com/vocab/sku": true,
+                "https://spec.openapis.org/oas/3.1/vocab/base": false,
+            },
+        });
+        assert_eq!(
+            json!({
+                "$id": "https://spec.openapis.org/oas/3.1/dialect/base",
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "$vocabulary": {
+                    "https://json-schema.org/draft/2020-12/vocab/core": true,
+                    "https://json-schema.org/draft/2020-12/vocab/applicator": true,
+                    "https://example.com/vocab/sku": false,
+                    "https://spec.openapis.org/oas/3.1/vocab/base": false,
+                },
+            }),
+            relax(&meta),
+        );
+        let meta = json!({"$schema": "http://json-schema.org/draft-07/schema#"});
+        assert_eq!(meta, relax(&meta));
+    }
+
+    #[test]
+    fn test_names_dialect() {
+        for (name, schema, names) in [
+            ("none", json!({"type": "object"}), false),
+            (
+                "draft",
+                json!({"$schema": "https://json-schema.org/draft/2020-12/schema"}),
+                false,
+            ),
+            (
+                "draft fragment",
+                json!({"$schema": "http://json-schema.org/draft-07/schema#"}),
+                false,
+            ),
+            (
+                "dialect",
+                json!({"$schema": "https://spec.openapis.org/oas/3.1/dialect/base"}),
+                true,
+            ),
+            (
+                "embedded",
+                json!({"$defs": {"x": {
+                    "$id": "https://example.com/x",
+                    "$schema": "https://example.com/dialect",
+                }}}),
+                true,
+            ),
+            (
+                "ref",
+                json!({"$ref": "https://spec.openapis.org/oas/3.1/meta/base"}),
+                true,
+            ),
+            (
+                "dynamic ref",
+                json!({"allOf": [{"$dynamicRef": "https://example.com/meta#meta"}]}),
+                true,
+            ),
+            (
+                "draft ref",
+                json!({"$ref": "https://json-schema.org/draft/2020-12/schema"}),
+                false,
+            ),
+            ("fragment ref", json!({"$ref": "#/$defs/x"}), false),
+            ("relative path ref", json!({"$ref": "../meta/base"}), true),
+            (
+                "property",
+                json!({"properties": {"$schema": {"type": "string"}}}),
+                false,
+            ),
+        ] {
+            assert_eq!(names, names_dialect(&schema), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_same_url() {
+        assert!(same_url("https://example.com/x", "https://example.com/x"));
+        assert!(same_url("https://example.com/x#", "https://example.com/x"));
+        assert!(same_url("https://example.com/x", "https://example.com/x#"));
+        assert!(!same_url("https://example.com/x", "https://example.com/y"));
+        assert!(!same_url(
+            "https://example.com/x#a",
+            "https://example.com/x"
+        ));
+    }
+}
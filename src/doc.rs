@@ -0,0 +1,364 @@
+//! The `jsonschema_doc` type.
+//!
+//! `jsonschema_doc` is a JSONB value that, given a type modifier naming a
+//! registered schema, as in `jsonschema_doc('https://example.com/user')`, must
+//! validate against that schema. It shares the binary representation of
+//! `jsonb`, so its output and send functions are those of `jsonb`, and it
+//! casts to and from `jsonb` without conversion. Its input and receive
+//! functions validate values against the schema named by the type modifier,
+//! and its length coercion function, which Postgres applies when assigning a
+//! value to a column with a type modifier, validates assigned values.
+//!
+//! The support functions pass the `jsonb` datum through unchanged, so they
+//! are implemented as raw V1 functions rather than with `#[pg_extern]`, which
+//! would round-trip values through `serde_json`.
+
+use std::ffi::{CStr, CString};
+use std::rc::Rc;
+
+use boon::SchemaIndex;
+use pgrx::fcinfo::{pg_getarg, pg_getarg_datum_raw};
+use pgrx::prelude::*;
+use pgrx::JsonB;
+use serde_json::Value;
+
+use crate::{compile, registry, validate_instance, Draft, Schemas, StatementCache, GUC};
+
+extension_sql!(
+    r#"
+-- jsonschema_doc is a jsonb value that must validate against the registered
+-- schema named by its type modifier.
+CREATE TYPE jsonschema_doc;
+
+CREATE FUNCTION jsonschema_doc_in(cstring, oid, integer) RETURNS jsonschema_doc
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'jsonschema_doc_in';
+
+CREATE FUNCTION jsonschema_doc_out(jsonschema_doc) RETURNS cstring
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE internal
+AS 'jsonb_out';
+
+CREATE FUNCTION jsonschema_doc_recv(internal, oid, integer) RETURNS jsonschema_doc
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'jsonschema_doc_recv';
+
+CREATE FUNCTION jsonschema_doc_send(jsonschema_doc) RETURNS bytea
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE internal
+AS 'jsonb_send';
+
+CREATE FUNCTION jsonschema_doc_typmod_in(cstring[]) RETURNS integer
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'jsonschema_doc_typmod_in';
+
+CREATE FUNCTION jsonschema_doc_typmod_out(integer) RETURNS cstring
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'jsonschema_doc_typmod_out';
+
+CREATE TYPE jsonschema_doc (
+	INPUT = jsonschema_doc_in,
+	OUTPUT = jsonschema_doc_out,
+	RECEIVE = jsonschema_doc_recv,
+	SEND = jsonschema_doc_send,
+	TYPMOD_IN = jsonschema_doc_typmod_in,
+	TYPMOD_OUT = jsonschema_doc_typmod_out,
+	LIKE = jsonb
+);
+
+-- jsonschema_doc validates a jsonschema_doc value against the schema named
+-- by a type modifier. Used as the length coercion cast.
+CREATE FUNCTION jsonschema_doc(jsonschema_doc, integer, boolean) RETURNS jsonschema_doc
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'jsonschema_doc_coerce';
+
+CREATE CAST (jsonschema_doc AS jsonschema_doc)
+	WITH FUNCTION jsonschema_doc(jsonschema_doc, integer, boolean) AS IMPLICIT;
+CREATE CAST (jsonb AS jsonschema_doc) WITHOUT FUNCTION AS ASSIGNMENT;
+CREATE CAST (jsonschema_doc AS jsonb) WITHOUT FUNCTION AS IMPLICIT;
+CREATE CAST (json AS jsonschema_doc) WITH INOUT AS ASSIGNMENT;
+"#,
+    name = "jsonschema_doc",
+);
+
+// pg_finfo_v1 declares the info function that tells Postgres that a raw
+// function uses the version 1 calling convention.
+macro_rules! pg_finfo_v1 {
+    ($finfo:ident) => {
+        #[no_mangle]
+        #[doc(hidden)]
+        pub extern "C" fn $finfo() -> &'static pg_sys::Pg_finfo_record {
+            const V1: pg_sys::Pg_finfo_record = pg_sys::Pg_finfo_record { api_version: 1 };
+            &V1
+        }
+    };
+}
+
+pg_finfo_v1!(pg_finfo_jsonschema_doc_in);
+pg_finfo_v1!(pg_finfo_jsonschema_doc_recv);
+pg_finfo_v1!(pg_finfo_jsonschema_doc_typmod_in);
+pg_finfo_v1!(pg_finfo_jsonschema_doc_typmod_out);
+pg_finfo_v1!(pg_finfo_jsonschema_doc_coerce);
+
+/// jsonschema_doc_in parses a `jsonschema_doc` from its text representation
+/// and validates it against the schema named by its type modifier. On
+/// Postgres 16 and later, it reports invalid input as a soft error when the
+/// caller asks for one, as `COPY ... ON_ERROR` and `pg_input_is_valid()` do.
+#[pg_guard]
+#[no_mangle]
+pub unsafe extern "C-unwind" fn jsonschema_doc_in(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    #[cfg(any(feature = "pg16", feature = "pg17"))]
+    if let Some(escontext) = soft::context(fcinfo) {
+        return soft::doc_in(fcinfo, escontext);
+    }
+
+    let doc = pg_sys::DirectFunctionCall1Coll(
+        Some(pg_sys::jsonb_in),
+        pg_sys::InvalidOid,
+        pg_getarg_datum_raw(fcinfo, 0),
+    );
+    check(doc, pg_getarg::<i32>(fcinfo, 2).unwrap_or(-1));
+    doc
+}
+
+/// jsonschema_doc_recv parses a `jsonschema_doc` from its binary
+/// representation and validates it against the schema named by its type
+/// modifier.
+#[pg_guard]
+#[no_mangle]
+pub unsafe extern "C-unwind" fn jsonschema_doc_recv(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let doc = pg_sys::DirectFunctionCall1Coll(
+        Some(pg_sys::jsonb_recv),
+        pg_sys::InvalidOid,
+        pg_getarg_datum_raw(fcinfo, 0),
+    );
+    check(doc, pg_getarg::<i32>(fcinfo, 2).unwrap_or(-1));
+    doc
+}
+
+/// jsonschema_doc_coerce validates a `jsonschema_doc` against the schema
+/// named by a type modifier and returns it unchanged.
+#[pg_guard]
+#[no_mangle]
+pub unsafe extern "C-unwind" fn jsonschema_doc_coerce(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let doc = pg_getarg_datum_raw(fcinfo, 0);
+    check(doc, pg_getarg::<i32>(fcinfo, 1).unwrap_or(-1));
+    doc
+}
+
+/// jsonschema_doc_typmod_in converts a `jsonschema_doc` type modifier, the
+/// `$id` of a registered schema, to its integer representation, a hash of the
+/// `$id`. It does not require the schema to be registered yet, so that
+/// `pg_restore` can create columns before it restores the registry; values
+/// fail to validate until it is.
+#[pg_guard]
+#[no_mangle]
+pub unsafe extern "C-unwind" fn jsonschema_doc_typmod_in(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let mods = pg_getarg::<Array<&CStr>>(fcinfo, 0);
+    let mods: Vec<Option<&CStr>> = mods.map(|a| a.iter().collect()).unwrap_or_default();
+    let [Some(id)] = mods.as_slice() else {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            "jsonschema_doc type modifier must be a single schema $id",
+        );
+    };
+
+    registry::typmod(&id.to_string_lossy()).into()
+}
+
+/// jsonschema_doc_typmod_out converts a `jsonschema_doc` type modifier to
+/// its text representation, the quoted `$id` of its schema in parentheses.
+/// Raises an error if no schema is registered for the type modifier.
+#[pg_guard]
+#[no_mangle]
+pub unsafe extern "C-unwind" fn jsonschema_doc_typmod_out(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let typmod = pg_getarg::<i32>(fcinfo, 0).unwrap_or(-1);
+    let out = if typmod < 0 {
+        String::new()
+    } else {
+        let Some(id) = registry::id_for_typmod(typmod) else {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+                format!("no schema registered for jsonschema_doc type modifier {typmod}"),
+            );
+        };
+        format!("('{}')", id.replace('\'', "''"))
+    };
+    let out = CString::new(out).unwrap_or_default();
+    pg_sys::Datum::from(pg_sys::pstrdup(out.as_ptr()))
+}
+
+/// Compiled holds the compiled schema for a type modifier.
+pub(crate) struct Compiled {
+    pub(crate) id: String,
+    pub(crate) schemas: Schemas,
+    pub(crate) index: SchemaIndex,
+}
+
+thread_local! {
+    // Compiled schemas by type modifier and default draft, reused for the
+    // rest of the statement.
+    static CACHE: StatementCache<(i32, Draft), Compiled> = StatementCache::new();
+}
+
+/// compiled returns the Compiled schema for `typmod`, compiling it with all
+/// of the registered schemas once per statement.
+pub(crate) fn compiled(typmod: i32) -> Rc<Compiled> {
+    CACHE.with(|cache| cache.get((typmod, GUC.get()), || load(typmod)))
+}
+
+/// load compiles the schema for `typmod` with all of the registered schemas.
+fn load(typmod: i32) -> Compiled {
+    let Some(id) = registry::id_for_typmod(typmod) else {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            format!("no schema registered for jsonschema_doc type modifier {typmod}"),
+        );
+    };
+    let (schemas, index) = compile(&id, &registry::registered()).unwrap_or_else(|e| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(r#"invalid schema "{id}": {e:#}"#),
+        )
+    });
+
+    Compiled { id, schemas, index }
+}
+
+/// check validates the `jsonb` datum `doc` against the schema for `typmod`,
+/// raising an error if it's invalid. A negative `typmod` means no schema.
+///
+/// # Safety
+///
+/// `doc` must be a valid `jsonb` datum.
+unsafe fn check(doc: pg_sys::Datum, typmod: i32) {
+    if let Some((message, detail)) = violation(doc, typmod) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_CHECK_VIOLATION,
+            message,
+            detail
+        );
+    }
+}
+
+/// violation validates the `jsonb` datum `doc` against the schema for
+/// `typmod`, returning the error message and detail if it's invalid.
+///
+/// # Safety
+///
+/// `doc` must be a valid `jsonb` datum.
+unsafe fn violation(doc: pg_sys::Datum, typmod: i32) -> Option<(String, String)> {
+    if typmod < 0 {
+        return None;
+    }
+
+    let c = compiled(typmod);
+    let instance = JsonB::from_datum(doc, false).map_or(Value::Null, |j| j.0);
+    validate_instance(&c.schemas, &instance, c.index)
+        .err()
+        .map(|e| {
+            (
+                format!(
+                    "value for type jsonschema_doc('{}') violates jsonschema",
+                    c.id
+                ),
+                e.to_string(),
+            )
+        })
+}
+
+/// Soft error reporting for `jsonschema_doc` input, available on Postgres 16
+/// and later.
+#[cfg(any(feature = "pg16", feature = "pg17"))]
+mod soft {
+    use std::ffi::CString;
+
+    use pgrx::fcinfo::{pg_getarg, pg_getarg_datum_raw};
+    use pgrx::prelude::*;
+
+    use super::violation;
+
+    /// context returns the `ErrorSaveContext` of `fcinfo`, if the caller
+    /// passed one to request soft error reporting.
+    pub(super) unsafe fn context(fcinfo: pg_sys::FunctionCallInfo) -> Option<*mut pg_sys::Node> {
+        let ctx = (*fcinfo).context;
+        (!ctx.is_null() && pgrx::is_a(ctx, pg_sys::NodeTag::T_ErrorSaveContext)).then_some(ctx)
+    }
+
+    /// doc_in implements `jsonschema_doc_in()` for callers that request soft
+    /// errors, saving malformed JSON and schema violations to `escontext`
+    /// rather than raising them.
+    pub(super) unsafe fn doc_in(
+        fcinfo: pg_sys::FunctionCallInfo,
+        escontext: *mut pg_sys::Node,
+    ) -> pg_sys::Datum {
+        let mut doc = pg_sys::Datum::from(0);
+        if !pg_sys::DirectInputFunctionCallSafe(
+            Some(pg_sys::jsonb_in),
+            pg_getarg_datum_raw(fcinfo, 0).cast_mut_ptr(),
+            pg_sys::JSONBOID,
+            -1,
+            escontext,
+            &mut doc,
+        ) {
+            return pg_sys::Datum::from(0);
+        }
+
+        match violation(doc, pg_getarg::<i32>(fcinfo, 2).unwrap_or(-1)) {
+            None => doc,
+            Some((message, detail)) => {
+                save(
+                    escontext,
+                    PgSqlErrorCode::ERRCODE_CHECK_VIOLATION,
+                    &message,
+                    &detail,
+                );
+                pg_sys::Datum::from(0)
+            }
+        }
+    }
+
+    /// save reports an error to `escontext`, as the C `errsave()` macro does.
+    /// Records only that an error occurred unless the context wants details.
+    unsafe fn save(
+        escontext: *mut pg_sys::Node,
+        code: PgSqlErrorCode,
+        message: &str,
+        detail: &str,
+    ) {
+        if !pg_sys::errsave_start(escontext, std::ptr::null()) {
+            return;
+        }
+        let message = CString::new(message).unwrap_or_default();
+        let detail = CString::new(detail).unwrap_or_default();
+        pg_sys::errcode(code as i32);
+        pg_sys::errmsg_internal(c"%s".as_ptr(), message.as_ptr());
+        pg_sys::errdetail_internal(c"%s".as_ptr(), detail.as_ptr());
+        pg_sys::errsave_finish(
+            escontext,
+            c"doc.rs".as_ptr(),
+            line!() as i32,
+            c"jsonschema_doc_in".as_ptr(),
+        );
+    }
+}
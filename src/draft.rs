@@ -0,0 +1,219 @@
+//! The `jsonschema_draft` enum type and the functions that use it.
+
+use std::ffi::{CStr, CString};
+
+use pgrx::pgrx_sql_entity_graph::metadata::{
+    ArgumentError, Returns, ReturnsError, SqlMapping, SqlTranslatable,
+};
+use pgrx::prelude::*;
+use pgrx::{Json, JsonB};
+use serde_json::Value;
+
+use crate::{dialect, extension_table, Draft, GUC};
+
+extension_sql!(
+    r#"
+-- jsonschema_draft enumerates the supported JSON Schema drafts.
+CREATE TYPE jsonschema_draft AS ENUM ('V4', 'V6', 'V7', 'V2019', 'V2020');
+"#,
+    name = "jsonschema_draft",
+);
+
+impl Draft {
+    /// name returns the `jsonschema_draft` label of the draft.
+    fn name(self) -> &'static str {
+        match self {
+            Draft::V4 => "V4",
+            Draft::V6 => "V6",
+            Draft::V7 => "V7",
+            Draft::V2019 => "V2019",
+            Draft::V2020 => "V2020",
+        }
+    }
+
+    /// from_url returns the Draft whose meta-schema URL is `url`.
+    pub(crate) fn from_url(url: &str) -> Option<Self> {
+        let url = url.strip_suffix('#').unwrap_or(url);
+        let url = url
+            .strip_prefix("https:")
+            .or_else(|| url.strip_prefix("http:"))?;
+        match url {
+            "//json-schema.org/draft-04/schema" => Some(Draft::V4),
+            "//json-schema.org/draft-06/schema" => Some(Draft::V6),
+            "//json-schema.org/draft-07/schema" => Some(Draft::V7),
+            "//json-schema.org/draft/2019-09/schema" => Some(Draft::V2019),
+            "//json-schema.org/draft/2020-12/schema" => Some(Draft::V2020),
+            _ => None,
+        }
+    }
+
+    /// meta_schema returns the meta-schema of the draft.
+    fn meta_schema(self) -> &'static str {
+        match self {
+            Draft::V4 => include_str!("metaschemas/draft-04.json"),
+            Draft::V6 => include_str!("metaschemas/draft-06.json"),
+            Draft::V7 => include_str!("metaschemas/draft-07.json"),
+            Draft::V2019 => include_str!("metaschemas/draft-2019-09.json"),
+            Draft::V2020 => include_str!("metaschemas/draft-2020-12.json"),
+        }
+    }
+}
+
+// The jsonschema_draft type is created by the extension_sql above rather
+// than by deriving PostgresEnum, which would name it "draft".
+unsafe impl SqlTranslatable for Draft {
+    fn argument_sql() -> Result<SqlMapping, ArgumentError> {
+        Ok(SqlMapping::As("jsonschema_draft".to_string()))
+    }
+
+    fn return_sql() -> Result<Returns, ReturnsError> {
+        Ok(Returns::One(SqlMapping::As("jsonschema_draft".to_string())))
+    }
+}
+
+impl FromDatum for Draft {
+    unsafe fn from_polymorphic_datum(
+        datum: pg_sys::Datum,
+        is_null: bool,
+        _typoid: pg_sys::Oid,
+    ) -> Option<Self> {
+        if is_null {
+            return None;
+        }
+        let label =
+            pg_sys::DirectFunctionCall1Coll(Some(pg_sys::enum_out), pg_sys::InvalidOid, datum);
+        let label = CStr::from_ptr(label.cast_mut_ptr()).to_string_lossy();
+        Some(Draft::from_name(&label).unwrap_or_else(|e| error!("{e}")))
+    }
+}
+
+impl IntoDatum for Draft {
+    fn into_datum(self) -> Option<pg_sys::Datum> {
+        let label = CString::new(self.name()).unwrap_or_default();
+        Some(unsafe {
+            pg_sys::DirectFunctionCall2Coll(
+                Some(pg_sys::enum_in),
+                pg_sys::InvalidOid,
+                pg_sys::Datum::from(label.as_ptr()),
+                Self::type_oid().into(),
+            )
+        })
+    }
+
+    // The extension is relocatable, so qualify the type with its schema,
+    // which may not be in the search path.
+    fn type_oid() -> pg_sys::Oid {
+        pgrx::wrappers::regtypein(&extension_table("jsonschema_draft"))
+    }
+}
+
+// jsonschema_draft_of(schema::json)
+// jsonschema_draft_of(schema::jsonb)
+// jsonschema_meta_schema(draft::jsonschema_draft)
+
+/// json_schema_draft_of returns the draft of `schema`.
+#[pg_extern(
+    stable,
+    strict,
+    parallel_safe,
+    name = "jsonschema_draft_of",
+    requires = ["jsonschema_draft"]
+)]
+fn json_schema_draft_of(schema: Json) -> Draft {
+    draft_of(&schema.0)
+        .unwrap_or_else(|e| ereport!(ERROR, PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE, e))
+}
+
+/// jsonb_schema_draft_of returns the draft of `schema`.
+#[pg_extern(
+    stable,
+    strict,
+    parallel_safe,
+    name = "jsonschema_draft_of",
+    requires = ["jsonschema_draft"]
+)]
+fn jsonb_schema_draft_of(schema: JsonB) -> Draft {
+    draft_of(&schema.0)
+        .unwrap_or_else(|e| ereport!(ERROR, PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE, e))
+}
+
+/// jsonschema_meta_schema returns the meta-schema of `draft`.
+#[pg_extern(immutable, strict, parallel_safe, requires = ["jsonschema_draft"])]
+fn jsonschema_meta_schema(draft: Draft) -> JsonB {
+    JsonB(serde_json::from_str(draft.meta_schema()).unwrap_or_else(|e| error!("{e}")))
+}
+
+/// draft_of returns the draft `schema` names in `$schema` or the default.
+fn draft_of(schema: &Value) -> Result<Draft, String> {
+    match schema.get("$schema") {
+        None => Ok(GUC.get()),
+        Some(Value::String(url)) => Draft::from_url(url)
+            .or_else(|| dialect::draft_for(url))
+            .ok_or_else(|| format!(r#"unsupported draft "{url}""#)),
+        Some(v) => Err(format!(r#"invalid "$schema" {v}: must be a string"#)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_url() {
+        for (url, draft) in [
+            ("http://json-schema.org/draft-04/schema#", Some(Draft::V4)),
+            ("http://json-schema.org/draft-04/schema", Some(Draft::V4)),
+            ("https://json-schema.org/draft-06/schema#", Some(Draft::V6)),
+            ("http://json-schema.org/draft-07/schema#", Some(Draft::V7)),
+            (
+                "https://json-schema.org/draft/2019-09/schema",
+                Some(Draft::V2019),
+            ),
+            (
+                "https://json-schema.org/draft/2020-12/schema",
+                Some(Draft::V2020),
+            ),
+            (
+                "http://json-schema.org/draft/2020-12/schema#",
+                Some(Draft::V2020),
+            ),
+            ("https://json-schema.org/draft/2020-12/schema#/x", None),
+            ("ftp://json-schema.org/draft-07/schema", None),
+            ("https://example.com/schema", None),
+            ("lol", None),
+        ] {
+            assert_eq!(draft, Draft::from_url(url), "{url}");
+        }
+    }
+
+    #[test]
+    fn test_meta_schema() {
+        for draft in [Draft::V4, Draft::V6, Draft::V7, Draft::V2019, Draft::V2020] {
+            let meta: Value = serde_json::from_str(draft.meta_schema()).unwrap();
+            let id = meta.get("$id").or_else(|| meta.get("id")).unwrap();
+            assert_eq!(
+                Some(draft),
+                Draft::from_url(id.as_str().unwrap()),
+                "{draft:?}"
+            );
+            assert_eq!(Ok(draft), Draft::from_name(draft.name()));
+        }
+    }
+
+    #[test]
+    fn test_draft_of() {
+        assert_eq!(
+            Ok(Draft::V7),
+            draft_of(&json!({"$schema": "http://json-schema.org/draft-07/schema#"})),
+        );
+        assert_eq!(
+            Err(r#"unsupported draft "lol""#.to_string()),
+            draft_of(&json!({"$schema": "lol"})),
+        );
+        assert_eq!(
+            Err(r#"invalid "$schema" 42: must be a string"#.to_string()),
+            draft_of(&json!({"$schema": 42})),
+        );
+    }
+}
@@ -0,0 +1,457 @@
+//! The `errorMessage` keyword.
+//!
+//! Modeled on [ajv-errors], `errorMessage` replaces the messages of the
+//! errors raised by the other keywords of its schema object with messages
+//! written for users. Its value is either a string, which replaces the
+//! messages of all of them, or an object of keywords and messages, as in
+//! `"errorMessage": {"minimum": "must be at least 18"}`. Messages may
+//! interpolate values with relative JSON Pointers from the value the schema
+//! applies to, as in `"${0/name} is too young"`.
+//!
+//! `boon` ignores `errorMessage`, so `Invalid` looks up the schema of each
+//! error's keyword location in the schema sources and, if it has an
+//! `errorMessage`, substitutes its message, interpolated for the value at
+//! the error's instance location, in its display and output, leaving the
+//! keyword and instance locations unchanged.
+//!
+//!   [ajv-errors]: https://github.com/ajv-validator/ajv-errors
+
+use std::fmt;
+
+use boon::ValidationError;
+use pgrx::prelude::*;
+use serde_json::{json, Value};
+
+use crate::data_ref::{self, Token};
+use crate::keyword::{without_fragment, Keywords, Unit};
+
+/// The name of the keyword.
+pub(crate) const KEYWORD: &str = "errorMessage";
+
+/// The keywords whose error locations include a token after the keyword, as
+/// in `dependentRequired/foo`.
+const TOKEN_KEYWORDS: &[&str] = &["dependencies", "dependentRequired"];
+
+/// ErrorMessage is the value of an `errorMessage` keyword.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ErrorMessage {
+    /// A message for the errors of every keyword.
+    All(String),
+    /// Messages for the errors of each keyword.
+    Keywords(Vec<(String, String)>),
+}
+
+impl ErrorMessage {
+    /// parse parses the value of an `errorMessage` keyword.
+    pub(crate) fn parse(value: &Value) -> Result<Self, String> {
+        let err = || {
+            format!(
+                r#"invalid "{KEYWORD}" {value}: must be a string or an object of keywords and strings"#
+            )
+        };
+        match value {
+            Value::String(m) => Ok(ErrorMessage::All(m.to_string())),
+            Value::Object(o) => o
+                .iter()
+                .map(|(kw, m)| match m {
+                    Value::String(m) => Ok((kw.to_string(), m.to_string())),
+                    _ => Err(err()),
+                })
+                .collect::<Result<_, _>>()
+                .map(ErrorMessage::Keywords),
+            _ => Err(err()),
+        }
+    }
+
+    /// get returns the message for the errors of `kw`.
+    fn get(&self, kw: &str) -> Option<&str> {
+        match self {
+            ErrorMessage::All(m) => Some(m),
+            ErrorMessage::Keywords(k) => k.iter().find(|(k, _)| k == kw).map(|(_, m)| m.as_str()),
+        }
+    }
+}
+
+/// interpolate replaces each `${ptr}` in `message` with the value the
+/// relative JSON Pointer `ptr` resolves to from the location `path` in
+/// `root`: strings as is, other values as JSON, and nothing if it resolves
+/// to nothing.
+fn interpolate(message: &str, root: &Value, path: &[Token]) -> String {
+    let mut out = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        match data_ref::resolve(root, path, &rest[start + 2..start + len]) {
+            Some(Value::String(s)) => out.push_str(&s),
+            Some(v) => out.push_str(&v.to_string()),
+            None => {}
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Invalid is a validation error and the errors of the keywords `boon`
+/// ignores, displayed with the messages of the `errorMessage` keywords that
+/// apply to them.
+pub(crate) struct Invalid<'s, 'v> {
+    error: Option<ValidationError<'s, 'v>>,
+    // The URL of the schema, for errors only of the ignored keywords.
+    url: &'s str,
+    units: Vec<Unit>,
+    // The keywords of the schemas, to look up errorMessage keywords.
+    keywords: Option<&'s Keywords>,
+    // The document validated.
+    root: &'v Value,
+}
+
+impl<'s, 'v> Invalid<'s, 'v> {
+    /// new returns the Invalid for the validation error `error`, if any, and
+    /// the errors `units` of the keywords of the schema `url` that `boon`
+    /// ignores, found validating `root`.
+    pub(crate) fn new(
+        error: Option<ValidationError<'s, 'v>>,
+        url: &'s str,
+        units: Vec<Unit>,
+        keywords: Option<&'s Keywords>,
+        root: &'v Value,
+    ) -> Self {
+        Invalid {
+            error,
+            url,
+            units,
+            keywords: keywords.filter(|k| k.has_messages()),
+            root,
+        }
+    }
+
+    /// url returns the URL of the schema.
+    fn url(&self) -> &str {
+        self.error.as_ref().map_or(self.url, |e| e.schema_url)
+    }
+
+    /// message returns the message of the `errorMessage` keyword that
+    /// applies to the basic output error `unit`, if any.
+    fn message(&self, unit: &Value) -> Option<String> {
+        lookup(self.keywords?, self.url(), self.root, unit)
+    }
+
+    /// basic_output returns the error in the JSON Schema "basic" output
+    /// format, with the messages of `errorMessage` keywords in place of
+    /// those of the errors they apply to.
+    pub(crate) fn basic_output(&self) -> Value {
+        let mut units = self.units();
+        for unit in &mut units {
+            if let Some(msg) = self.message(unit) {
+                unit["error"] = Value::String(msg);
+            }
+        }
+        json!({
+            "valid": false,
+            "keywordLocation": "",
+            "instanceLocation": "",
+            "errors": units,
+        })
+    }
+
+    /// units returns the errors in the JSON Schema "basic" output format,
+    /// with their own messages.
+    fn units(&self) -> Vec<Value> {
+        let mut units = match &self.error {
+            Some(e) => match serde_json::to_value(e.basic_output()) {
+                Ok(Value::Object(mut output)) => match output.remove("errors") {
+                    Some(Value::Array(units)) => units,
+                    _ => Vec::new(),
+                },
+                Ok(_) => Vec::new(),
+                Err(e) => error!("{e}"),
+            },
+            None => Vec::new(),
+        };
+        units.extend(self.units.iter().map(Unit::output));
+        units
+    }
+}
+
+impl fmt::Display for Invalid<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = match &self.error {
+            Some(e) => e.to_string(),
+            None => format!("jsonschema validation failed with {}", self.url),
+        };
+        for unit in &self.units {
+            text.push_str(&format!(
+                "\n- at {}: {}",
+                quote(&unit.instance_location),
+                unit.error
+            ));
+        }
+        if self.keywords.is_none() {
+            return f.write_str(&text);
+        }
+        let units = self.units();
+        let changes: Vec<_> = units
+            .iter()
+            .filter_map(|unit| {
+                let msg = self.message(unit)?;
+                Some((
+                    unit["instanceLocation"].as_str()?,
+                    unit["error"].as_str()?,
+                    msg,
+                ))
+            })
+            .collect();
+        f.write_str(&substitute(&text, &changes))
+    }
+}
+
+/// quote quotes `s` as `boon` quotes instance locations in its display.
+fn quote(s: &str) -> String {
+    let s = format!("{s:?}").replace(r#"\""#, "\"").replace('\'', r"\'");
+    format!("'{}'", &s[1..s.len() - 1])
+}
+
+/// lookup returns the message of the `errorMessage` keyword of the schema
+/// of the keyword of the basic output error `unit`, interpolated for the
+/// value at its instance location in `root`. Uses the URL of the root
+/// schema, `url`, for errors without an absolute keyword location.
+fn lookup(keywords: &Keywords, url: &str, root: &Value, unit: &Value) -> Option<String> {
+    let instance = unit.get("instanceLocation")?.as_str()?;
+    let location = match unit.get("absoluteKeywordLocation").and_then(Value::as_str) {
+        Some(loc) => loc.to_string(),
+        None => format!(
+            "{}#{}",
+            without_fragment(url),
+            unit.get("keywordLocation")?.as_str()?
+        ),
+    };
+    let (base, ptr) = location.split_once('#')?;
+    let tokens: Vec<_> = ptr.split('/').skip(1).collect();
+    for up in 1..=tokens.len().min(2) {
+        let kw = tokens[tokens.len() - up];
+        if up == 2 && !TOKEN_KEYWORDS.contains(&kw) {
+            break;
+        }
+        let schema: String = tokens[..tokens.len() - up]
+            .iter()
+            .map(|t| format!("/{t}"))
+            .collect();
+        let Some(message) = keywords
+            .schema(&format!("{base}#{schema}"))
+            .and_then(|s| s.get(KEYWORD))
+            .and_then(|m| ErrorMessage::parse(m).ok())
+        else {
+            continue;
+        };
+        let path = data_ref::path(root, instance)?;
+        return message.get(kw).map(|m| interpolate(m, root, &path));
+    }
+    None
+}
+
+/// substitute replaces the messages of errors in `text`, the display of a
+/// validation error, with custom messages. Each change lists the instance
+/// location of an error, its message, and the custom message, in the order
+/// the errors appear.
+fn substitute(text: &str, changes: &[(&str, &str, String)]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    for (at, was, now) in changes {
+        let line = format!("at '{at}': {was}");
+        let found = rest.match_indices(&line).map(|(i, _)| i).find(|i| {
+            let end = &rest[i + line.len()..];
+            end.is_empty() || end.starts_with('\n')
+        });
+        if let Some(i) = found {
+            out.push_str(&rest[..i]);
+            out.push_str(&format!("at '{at}': {now}"));
+            rest = &rest[i + line.len()..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Ok(ErrorMessage::All("invalid".to_string())),
+            ErrorMessage::parse(&json!("invalid")),
+        );
+        assert_eq!(
+            Ok(ErrorMessage::Keywords(vec![(
+                "type".to_string(),
+                "not text".to_string()
+            )])),
+            ErrorMessage::parse(&json!({"type": "not text"})),
+        );
+        for value in [json!(1), json!({"type": 1}), json!(["x"])] {
+            assert_eq!(
+                Err(format!(
+                    r#"invalid "errorMessage" {value}: must be a string or an object of keywords and strings"#
+                )),
+                ErrorMessage::parse(&value),
+            );
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let root = json!({"name": "Kamala", "age": 17, "tags": ["a"]});
+        let path = [Token::Prop("age".to_string())];
+        for (message, want) in [
+            ("too young", "too young"),
+            ("${1/name} is ${0}", "Kamala is 17"),
+            ("${1/tags} at ${0#}", r#"["a"] at age"#),
+            ("${1/nope}!", "!"),
+            ("${0", "${0"),
+        ] {
+            assert_eq!(want, interpolate(message, &root, &path), "{message}");
+        }
+    }
+
+    fn schema() -> Value {
+        json!({
+            "$id": "https://example.com/u",
+            "type": "object",
+            "required": ["name"],
+            "dependentRequired": {"a": ["b"]},
+            "properties": {
+                "age": {"minimum": 18, "errorMessage": "${1/name} is too young"},
+                "name": {"type": "string"},
+                "ref": {"$ref": "#/$defs/pos"},
+            },
+            "$defs": {
+                "pos": {"minimum": 0, "errorMessage": {"minimum": "${0} is negative"}},
+            },
+            "errorMessage": {"required": "name is required", "dependentRequired": "needs both"},
+        })
+    }
+
+    #[test]
+    fn test_lookup() {
+        let (schemas, _) = crate::compile("https://example.com/u", &[schema()]).unwrap();
+        let keywords = schemas.keywords.as_ref().unwrap();
+        let root = json!({"name": "Tim", "age": 17, "ref": -1});
+        let url = "https://example.com/u#";
+        for (unit, want) in [
+            (
+                json!({"keywordLocation": "/required", "absoluteKeywordLocation": "https://example.com/u#/required", "instanceLocation": ""}),
+                Some("name is required"),
+            ),
+            (
+                json!({"keywordLocation": "/required", "instanceLocation": ""}),
+                Some("name is required"),
+            ),
+            (
+                json!({"keywordLocation": "/type", "instanceLocation": ""}),
+                None,
+            ),
+            (
+                json!({"keywordLocation": "/dependentRequired/a", "instanceLocation": ""}),
+                Some("needs both"),
+            ),
+            (
+                json!({"keywordLocation": "/properties/age/minimum", "instanceLocation": "/age"}),
+                Some("Tim is too young"),
+            ),
+            (
+                json!({"keywordLocation": "/properties/age/minimum", "instanceLocation": "/x"}),
+                None,
+            ),
+            (
+                json!({"keywordLocation": "/properties/name/type", "instanceLocation": "/name"}),
+                None,
+            ),
+            (
+                json!({"keywordLocation": "/properties/ref/$ref/minimum", "absoluteKeywordLocation": "https://example.com/u#/$defs/pos/minimum", "instanceLocation": "/ref"}),
+                Some("-1 is negative"),
+            ),
+        ] {
+            assert_eq!(
+                want.map(str::to_string),
+                lookup(keywords, url, &root, &unit),
+                "{unit}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let (schemas, index) = crate::compile("https://example.com/u", &[schema()]).unwrap();
+        let instance = json!({"name": "Tim", "age": 17, "ref": -1, "a": 1});
+        let Err(e) = crate::validate_instance(&schemas, &instance, index) else {
+            panic!("{instance} is valid");
+        };
+        assert_eq!(
+            "jsonschema validation failed with https://example.com/u#\n\
+            - at '/age': Tim is too young\n\
+            - at '/ref': -1 is negative\n\
+            - at '': needs both",
+            e.to_string(),
+        );
+        assert_eq!(
+            json!({
+                "valid": false,
+                "keywordLocation": "",
+                "instanceLocation": "",
+                "errors": [
+                    {
+                        "valid": false,
+                        "keywordLocation": "/properties/age/minimum",
+                        "instanceLocation": "/age",
+                        "error": "Tim is too young",
+                    },
+                    {
+                        "valid": false,
+                        "keywordLocation": "/properties/ref/$ref/minimum",
+                        "absoluteKeywordLocation": "https://example.com/u#/$defs/pos/minimum",
+                        "instanceLocation": "/ref",
+                        "error": "-1 is negative",
+                    },
+                    {
+                        "valid": false,
+                        "keywordLocation": "/dependentRequired/a",
+                        "instanceLocation": "",
+                        "error": "needs both",
+                    },
+                ],
+            }),
+            e.basic_output(),
+        );
+    }
+
+    #[test]
+    fn test_substitute() {
+        let text = "jsonschema validation failed with https://example.com/u#\n\
+            - at '/age': must be >= 18, but got 17\n\
+            - at '/age': must be >= 18, but got 170\n\
+            - at '': missing properties 'name'";
+        assert_eq!(
+            "jsonschema validation failed with https://example.com/u#\n\
+            - at '/age': must be >= 18, but got 17\n\
+            - at '/age': too old\n\
+            - at '': name is required",
+            substitute(
+                text,
+                &[
+                    ("/age", "must be >= 18, but got 170", "too old".to_string()),
+                    (
+                        "",
+                        "missing properties 'name'",
+                        "name is required".to_string(),
+                    ),
+                ],
+            ),
+        );
+        assert_eq!(text, substitute(text, &[("/x", "nope", "yes".to_string())]));
+    }
+}
@@ -0,0 +1,273 @@
+//! JSON Lines import with rejects recorded in `jsonschema_import_rejects`.
+
+use std::ffi::CStr;
+
+use pgrx::pg_sys::panic::CaughtError;
+use pgrx::prelude::*;
+use pgrx::{Json, JsonB, VariadicArray};
+use serde_json::{json, Value};
+
+use crate::{compile, extension_table, parse_data, validate_instance, DEFAULT_URL};
+
+// jsonschema_import_rejects records lines rejected by jsonschema_import().
+// Mark it as a configuration table so that pg_dump dumps its contents.
+extension_sql!(
+    r#"
+-- jsonschema_import_rejects records lines that failed to parse, validate,
+-- or insert in jsonschema_import(), along with their errors.
+CREATE TABLE jsonschema_import_rejects (
+	id          BIGINT      GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+	rejected_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp(),
+	relation    REGCLASS    NOT NULL,
+	column_name NAME        NOT NULL,
+	line        BIGINT      NOT NULL,
+	data        TEXT        NOT NULL,
+	errors      JSONB       NOT NULL
+);
+
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_import_rejects', '');
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_import_rejects_id_seq', '');
+"#,
+    name = "jsonschema_import_rejects",
+);
+
+type Counts = TableIterator<'static, (name!(imported, i64), name!(rejected, i64))>;
+
+/// json_schema_import imports the valid lines of `source` into `target`.
+#[pg_extern(volatile, strict, sql = false)]
+fn json_schema_import(source: &str, target: pg_sys::Oid, column: &CStr, schema: Json) -> Counts {
+    let schemas = [schema.0];
+    let id = schemas[0]["$id"]
+        .as_str()
+        .unwrap_or(DEFAULT_URL)
+        .to_string();
+    import(source, target, column, &id, &schemas)
+}
+
+/// jsonb_schema_import imports the valid lines of `source` into `target`.
+#[pg_extern(volatile, strict, sql = false)]
+fn jsonb_schema_import(source: &str, target: pg_sys::Oid, column: &CStr, schema: JsonB) -> Counts {
+    let schemas = [schema.0];
+    let id = schemas[0]["$id"]
+        .as_str()
+        .unwrap_or(DEFAULT_URL)
+        .to_string();
+    import(source, target, column, &id, &schemas)
+}
+
+/// json_schema_id_import imports the valid lines of `source` into `target`.
+#[pg_extern(volatile, strict, sql = false)]
+fn json_schema_id_import(
+    source: &str,
+    target: pg_sys::Oid,
+    column: &CStr,
+    id: &str,
+    schemas: VariadicArray<Json>,
+) -> Counts {
+    let schemas = schemas.iter_deny_null().map(|x| x.0).collect::<Vec<_>>();
+    import(source, target, column, id, &schemas)
+}
+
+/// jsonb_schema_id_import imports the valid lines of `source` into `target`.
+#[pg_extern(volatile, strict, sql = false)]
+fn jsonb_schema_id_import(
+    source: &str,
+    target: pg_sys::Oid,
+    column: &CStr,
+    id: &str,
+    schemas: VariadicArray<JsonB>,
+) -> Counts {
+    let schemas = schemas.iter_deny_null().map(|x| x.0).collect::<Vec<_>>();
+    import(source, target, column, id, &schemas)
+}
+
+/// import imports `source`, returning the lines imported and rejected.
+fn import(source: &str, target: pg_sys::Oid, column: &CStr, id: &str, schemas: &[Value]) -> Counts {
+    let column = column.to_string_lossy();
+    let (schemas, index) = compile(id, schemas).unwrap_or_else(|e| error!("{e:#}"));
+    let (table, column_type) = target_column(target, &column);
+
+    let insert = format!(
+        "INSERT INTO {table} ({}) VALUES ($1::{column_type})",
+        spi::quote_identifier(&column),
+    );
+    let mut imported = 0;
+    let (mut lines, mut data, mut errors) = (Vec::new(), Vec::new(), Vec::new());
+    for (i, line) in source.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let err = match parse_data(line) {
+            Err(e) => failure(&e),
+            Ok(value) => match validate_instance(&schemas, &value, index) {
+                Err(e) => e.basic_output(),
+                Ok(()) => match insert_line(&insert, line) {
+                    Ok(()) => {
+                        imported += 1;
+                        continue;
+                    }
+                    Err(e) => failure(&e),
+                },
+            },
+        };
+        lines.push(i as i64 + 1);
+        data.push(line.to_string());
+        errors.push(JsonB(err));
+    }
+
+    let counts = (imported, lines.len() as i64);
+    if !lines.is_empty() {
+        Spi::run_with_args(
+            &format!(
+                "INSERT INTO {} (relation, column_name, line, data, errors)
+                 SELECT $1, $2, * FROM unnest($3::bigint[], $4::text[], $5::jsonb[])",
+                extension_table("jsonschema_import_rejects"),
+            ),
+            &[
+                target.into(),
+                column.as_ref().into(),
+                lines.into(),
+                data.into(),
+                errors.into(),
+            ],
+        )
+        .unwrap_or_else(|e| error!("{e}"));
+    }
+
+    TableIterator::once(counts)
+}
+
+/// failure returns the basic output for a line that failed with `error`.
+fn failure(error: &str) -> Value {
+    json!({"valid": false, "errors": [{"instanceLocation": "", "error": error}]})
+}
+
+/// insert_line runs `insert` for `line` in a subtransaction, returning the
+/// error message if it fails. Re-raises cancellations and privilege errors.
+fn insert_line(insert: &str, line: &str) -> Result<(), String> {
+    // Subtransaction handling follows PL/Python's subtransaction wrappers.
+    let (cxt, owner) = unsafe { (pg_sys::CurrentMemoryContext, pg_sys::CurrentResourceOwner) };
+    unsafe {
+        pg_sys::BeginInternalSubTransaction(std::ptr::null());
+        pg_sys::CurrentMemoryContext = cxt;
+    }
+    PgTryBuilder::new(|| {
+        Spi::run_with_args(insert, &[line.into()]).unwrap_or_else(|e| error!("{e}"));
+        unsafe {
+            pg_sys::ReleaseCurrentSubTransaction();
+            pg_sys::CurrentMemoryContext = cxt;
+            pg_sys::CurrentResourceOwner = owner;
+        }
+        Ok(())
+    })
+    .catch_others(|e| {
+        unsafe {
+            pg_sys::CurrentMemoryContext = cxt;
+            pg_sys::FlushErrorState();
+            pg_sys::RollbackAndReleaseCurrentSubTransaction();
+            pg_sys::CurrentMemoryContext = cxt;
+            pg_sys::CurrentResourceOwner = owner;
+        }
+        match e {
+            CaughtError::PostgresError(ref report)
+                if !matches!(
+                    report.sql_error_code(),
+                    PgSqlErrorCode::ERRCODE_QUERY_CANCELED
+                        | PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE
+                ) =>
+            {
+                Err(match report.detail() {
+                    Some(detail) => format!("{}: {detail}", report.message()),
+                    None => report.message().to_string(),
+                })
+            }
+            e => e.rethrow(),
+        }
+    })
+    .execute()
+}
+
+/// target_column returns the name of `target` and the type of its `column`.
+fn target_column(target: pg_sys::Oid, column: &str) -> (String, String) {
+    let (table, column_type) = Spi::get_two_with_args::<String, String>(
+        "SELECT $1::regclass::text,
+                (SELECT format_type(atttypid, atttypmod)
+                   FROM pg_catalog.pg_attribute
+                  WHERE attrelid = $1 AND attname = $2
+                    AND attnum > 0 AND NOT attisdropped)",
+        &[target.into(), column.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    let table = table.unwrap_or_default();
+    let Some(column_type) = column_type else {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            format!(r#"column "{column}" of relation "{table}" does not exist"#),
+        );
+    };
+    (table, column_type)
+}
+
+extension_sql!(
+    r#"
+-- jsonschema_import imports the lines of a JSON Lines source that validate
+-- against a schema into a table column, one subtransaction per line, and
+-- records the rest in jsonschema_import_rejects.
+CREATE FUNCTION jsonschema_import(
+	"source" TEXT, /* &str */
+	"target" regclass, /* pgrx::pg_sys::Oid */
+	"target_column" name, /* &core::ffi::CStr */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS TABLE (
+	"imported" bigint, /* i64 */
+	"rejected" bigint /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_import_wrapper';
+
+CREATE FUNCTION jsonschema_import(
+	"source" TEXT, /* &str */
+	"target" regclass, /* pgrx::pg_sys::Oid */
+	"target_column" name, /* &core::ffi::CStr */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS TABLE (
+	"imported" bigint, /* i64 */
+	"rejected" bigint /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_import_wrapper';
+
+CREATE FUNCTION jsonschema_import(
+	"source" TEXT, /* &str */
+	"target" regclass, /* pgrx::pg_sys::Oid */
+	"target_column" name, /* &core::ffi::CStr */
+	"id" TEXT, /* &str */
+	VARIADIC "schemas" json[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::Json> */
+) RETURNS TABLE (
+	"imported" bigint, /* i64 */
+	"rejected" bigint /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_id_import_wrapper';
+
+CREATE FUNCTION jsonschema_import(
+	"source" TEXT, /* &str */
+	"target" regclass, /* pgrx::pg_sys::Oid */
+	"target_column" name, /* &core::ffi::CStr */
+	"id" TEXT, /* &str */
+	VARIADIC "schemas" jsonb[] /* pgrx::datum::array::VariadicArray<pgrx::datum::json::JsonB> */
+) RETURNS TABLE (
+	"imported" bigint, /* i64 */
+	"rejected" bigint /* i64 */
+)
+STRICT
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_id_import_wrapper';
+"#,
+    name = "jsonschema_import",
+    requires = ["jsonschema_import_rejects"],
+);
@@ -0,0 +1,1052 @@
+//! Custom keywords implemented as SQL functions, checked after `boon`.
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use boon::{Compiler, SchemaIndex};
+use pgrx::prelude::*;
+use pgrx::JsonB;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+
+use crate::data_ref::{self, Token};
+use crate::error_message::{self, ErrorMessage};
+use crate::reference::{self, References, Target};
+use crate::{extension_table, invalidate_table, resource_id, CompileError, TableCache};
+
+extension_sql!(
+    r#"
+-- jsonschema_keywords maps custom keywords to the SQL functions that
+-- implement them.
+CREATE TABLE jsonschema_keywords (
+	keyword  TEXT         PRIMARY KEY CHECK (keyword <> ''),
+	function REGPROCEDURE NOT NULL
+);
+
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_keywords', '');
+"#,
+    name = "jsonschema_keywords",
+);
+
+/// jsonschema_register_keyword registers `function` to implement `keyword`.
+#[pg_extern(volatile, strict)]
+fn jsonschema_register_keyword(keyword: &str, function: &str) {
+    if keyword.is_empty() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            "cannot register an empty keyword",
+        );
+    }
+    if [reference::KEYWORD, error_message::KEYWORD].contains(&keyword) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(r#"cannot register keyword "{keyword}": it is built in"#),
+        );
+    }
+    let valid = Spi::get_one_with_args::<bool>(
+        "SELECT p.proargtypes::pg_catalog.oid[] = ARRAY[
+                    'pg_catalog.jsonb'::pg_catalog.regtype,
+                    'pg_catalog.jsonb'::pg_catalog.regtype
+                ]::pg_catalog.oid[]
+            AND p.prorettype IN (
+                    'pg_catalog.bool'::pg_catalog.regtype,
+                    'pg_catalog.text'::pg_catalog.regtype
+                )
+           FROM pg_catalog.pg_proc p
+          WHERE p.oid = $1::pg_catalog.regprocedure",
+        &[function.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    if valid != Some(true) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(
+                r#"invalid function for keyword "{keyword}": {function} must take (jsonb, jsonb) and return boolean or text"#
+            ),
+        );
+    }
+
+    Spi::run_with_args(
+        &format!(
+            "INSERT INTO {} (keyword, function) VALUES ($1, $2::pg_catalog.regprocedure)
+             ON CONFLICT (keyword) DO UPDATE SET function = EXCLUDED.function",
+            extension_table("jsonschema_keywords"),
+        ),
+        &[keyword.into(), function.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    invalidate_table("jsonschema_keywords");
+}
+
+/// jsonschema_unregister_keyword removes the custom keyword `keyword`.
+#[pg_extern(volatile, strict)]
+fn jsonschema_unregister_keyword(keyword: &str) -> bool {
+    let deleted = Spi::get_one_with_args::<bool>(
+        &format!(
+            "WITH d AS (DELETE FROM {} WHERE keyword = $1 RETURNING 1) SELECT count(*) > 0 FROM d",
+            extension_table("jsonschema_keywords"),
+        ),
+        &[keyword.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"))
+    .unwrap_or_default();
+    invalidate_table("jsonschema_keywords");
+    deleted
+}
+
+/// Keyword is a registered custom keyword.
+#[derive(Debug, Clone, PartialEq)]
+struct Keyword {
+    keyword: String,
+    // The schema-qualified name of the function, as output by regproc.
+    function: String,
+    returns_bool: bool,
+}
+
+/// The keywords whose values are objects of subschemas.
+const MAP_KEYWORDS: &[&str] = &[
+    "properties",
+    "patternProperties",
+    "$defs",
+    "definitions",
+    "dependentSchemas",
+    "dependencies",
+];
+
+/// The keywords whose values are arrays of subschemas.
+const ARRAY_KEYWORDS: &[&str] = &["allOf", "anyOf", "oneOf", "prefixItems", "items"];
+
+/// The keywords whose values are subschemas.
+const SCHEMA_KEYWORDS: &[&str] = &[
+    "additionalProperties",
+    "additionalItems",
+    "items",
+    "contains",
+    "propertyNames",
+    "not",
+    "if",
+    "then",
+    "else",
+    "unevaluatedItems",
+    "unevaluatedProperties",
+    "contentSchema",
+];
+
+/// Keywords checks instances against the keywords `boon` ignores.
+pub(crate) struct Keywords {
+    keywords: Rc<Vec<Keyword>>,
+    // Whether `$data` references were enabled when compiled.
+    data: bool,
+    // Whether any schema uses the keywords to check.
+    walk: bool,
+    // Whether any schema has an errorMessage.
+    messages: bool,
+    sources: Vec<Value>,
+    // The source and pointer of each resource, by URL.
+    resources: HashMap<String, (usize, String)>,
+    // The location of each anchor, by URL and name.
+    anchors: HashMap<String, String>,
+    // The compiled subschemas of branches, by location.
+    branches: HashMap<String, SchemaIndex>,
+    // The compiled patternProperties patterns.
+    patterns: HashMap<String, Regex>,
+    // The location of each compiled root schema.
+    roots: HashMap<SchemaIndex, String>,
+}
+
+impl Keywords {
+    /// new returns the Keywords of `sources`, or `None` if they use none.
+    pub(crate) fn new(
+        c: &mut Compiler,
+        schemas: &mut boon::Schemas,
+        id: &str,
+        sources: &[Value],
+        roots: &[(SchemaIndex, String)],
+    ) -> Result<Option<Self>, CompileError> {
+        let mut k = Keywords {
+            keywords: registered(),
+            data: data_ref::enabled(),
+            walk: false,
+            messages: false,
+            sources: sources.to_vec(),
+            resources: HashMap::new(),
+            anchors: HashMap::new(),
+            branches: HashMap::new(),
+            patterns: HashMap::new(),
+            roots: roots
+                .iter()
+                .map(|(i, url)| (*i, format!("{}#", without_fragment(url))))
+                .collect(),
+        };
+        let mut index = Index::default();
+        for (i, s) in sources.iter().enumerate() {
+            let base = resource_id(id, i, s);
+            k.index(&mut index, i, without_fragment(&base), "", "", s)
+                .map_err(CompileError::Keyword)?;
+        }
+        if !index.found && !index.messages {
+            return Ok(None);
+        }
+        k.walk = index.found;
+        k.messages = index.messages;
+        // Treat branches that are not valid schemas for their drafts, which
+        // boon ignores, as valid.
+        for loc in index.branches {
+            if let Ok(i) = c.compile(&loc, schemas) {
+                k.branches.insert(loc, i);
+            }
+        }
+        Ok(Some(k))
+    }
+
+    /// index records the resources, anchors, and keywords of `schema`.
+    fn index(
+        &mut self,
+        index: &mut Index,
+        src: usize,
+        base: &str,
+        ptr: &str,
+        rel: &str,
+        schema: &Value,
+    ) -> Result<(), String> {
+        let Value::Object(obj) = schema else {
+            return Ok(());
+        };
+
+        // Embedded resources start a new base URL.
+        let (base, rel) = match obj.get("$id") {
+            Some(Value::String(id)) if !id.starts_with('#') => (join(base, id), String::new()),
+            _ => (base.to_string(), rel.to_string()),
+        };
+        self.resources
+            .entry(base.clone())
+            .or_insert_with(|| (src, ptr.to_string()));
+        // Before 2019-09, $id may name an anchor.
+        let anchors = ["$anchor", "$dynamicAnchor"]
+            .iter()
+            .filter_map(|kw| obj.get(*kw).and_then(Value::as_str))
+            .chain(
+                obj.get("$id")
+                    .and_then(Value::as_str)
+                    .and_then(|id| id.strip_prefix('#')),
+            );
+        for a in anchors {
+            self.anchors
+                .insert(format!("{base}#{a}"), format!("{base}#{rel}"));
+        }
+
+        if let Some(v) = obj.get(reference::KEYWORD) {
+            Target::parse(v)?;
+            index.found = true;
+        }
+        if self.data && data_ref::found(obj) {
+            index.found = true;
+        }
+        if let Some(v) = obj.get(error_message::KEYWORD) {
+            ErrorMessage::parse(v)?;
+            index.messages = true;
+        }
+        if self.keywords.iter().any(|k| obj.contains_key(&k.keyword)) {
+            index.found = true;
+        }
+
+        for (kw, v) in obj {
+            let ptr = format!("{ptr}/{}", escape(kw));
+            let rel = format!("{rel}/{}", escape(kw));
+            match (kw.as_str(), v) {
+                (kw, Value::Object(o)) if MAP_KEYWORDS.contains(&kw) => {
+                    for (name, s) in o {
+                        if kw == "patternProperties" {
+                            if let Ok(re) = Regex::new(name) {
+                                self.patterns.insert(name.to_string(), re);
+                            }
+                        }
+                        let name = escape(name);
+                        self.index(
+                            index,
+                            src,
+                            &base,
+                            &format!("{ptr}/{name}"),
+                            &format!("{rel}/{name}"),
+                            s,
+                        )?;
+                    }
+                }
+                (kw, Value::Array(a)) if ARRAY_KEYWORDS.contains(&kw) => {
+                    for (i, s) in a.iter().enumerate() {
+                        if kw == "anyOf" || kw == "oneOf" {
+                            index.branches.push(format!("{base}#{rel}/{i}"));
+                        }
+                        self.index(
+                            index,
+                            src,
+                            &base,
+                            &format!("{ptr}/{i}"),
+                            &format!("{rel}/{i}"),
+                            s,
+                        )?;
+                    }
+                }
+                (kw, _) if SCHEMA_KEYWORDS.contains(&kw) => {
+                    if kw == "if" || kw == "else" {
+                        index.branches.push(format!("{base}#{rel}"));
+                    }
+                    self.index(index, src, &base, &ptr, &rel, v)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// url returns the location of the root schema compiled at `index`.
+    pub(crate) fn url(&self, index: SchemaIndex) -> &str {
+        self.roots.get(&index).map_or("", String::as_str)
+    }
+
+    /// check checks `instance` against the root schema at `index`.
+    pub(crate) fn check(
+        &self,
+        schemas: &boon::Schemas,
+        instance: &Value,
+        index: SchemaIndex,
+    ) -> Vec<Unit> {
+        if !self.walk {
+            return Vec::new();
+        }
+        let Some((at, schema)) = self.resolve(self.url(index), "") else {
+            return Vec::new();
+        };
+        let mut walk = Walk {
+            keywords: self,
+            schemas,
+            root: instance,
+            references: References::new(),
+            seen: HashSet::new(),
+        };
+        let units = walk.check(schema, &at, instance, &mut Vec::new());
+        // Walk again to check the x-pg-references keys, if any, after one
+        // lookup per table column.
+        if !walk.references.lookup() {
+            return units;
+        }
+        walk.check(schema, &at, instance, &mut Vec::new())
+    }
+
+    /// has_messages returns true if any schema has an `errorMessage`.
+    pub(crate) fn has_messages(&self) -> bool {
+        self.messages
+    }
+
+    /// schema returns the schema at the absolute location `location`.
+    pub(crate) fn schema(&self, location: &str) -> Option<&Value> {
+        let (base, fragment) = location.split_once('#').unwrap_or((location, ""));
+        self.resolve(base, &format!("#{fragment}"))
+            .map(|(_, schema)| schema)
+    }
+
+    /// resolve returns the location and schema `reference` refers to.
+    fn resolve(&self, base: &str, reference: &str) -> Option<(At, &Value)> {
+        let (url, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+        let url = if url.is_empty() {
+            without_fragment(base).to_string()
+        } else {
+            join(base, url)
+        };
+        let (url, ptr) = if fragment.is_empty() || fragment.starts_with('/') {
+            (url, fragment.to_string())
+        } else {
+            let (url, ptr) = self
+                .anchors
+                .get(&format!("{url}#{fragment}"))?
+                .split_once('#')?;
+            (url.to_string(), ptr.to_string())
+        };
+        let (src, prefix) = self.resources.get(&url)?;
+        let schema = self.sources[*src].pointer(&format!("{prefix}{ptr}"))?;
+        Some((
+            At {
+                base: url,
+                ptr,
+                keyword: String::new(),
+                in_ref: false,
+            },
+            schema,
+        ))
+    }
+}
+
+/// Index collects what `Keywords::index()` finds in schemas.
+#[derive(Default)]
+struct Index {
+    // Whether the schemas use any of the keywords to check.
+    found: bool,
+    // Whether the schemas have errorMessage keywords.
+    messages: bool,
+    // The locations of the subschemas of branches.
+    branches: Vec<String>,
+}
+
+/// At is the absolute and evaluation location of a schema object.
+#[derive(Debug, Clone, PartialEq)]
+struct At {
+    base: String,
+    ptr: String,
+    keyword: String,
+    // Whether the location is through a reference.
+    in_ref: bool,
+}
+
+impl At {
+    /// child returns the location of the subschema at `tokens`.
+    fn child(&self, tokens: &[&str]) -> At {
+        let suffix: String = tokens.iter().map(|t| format!("/{}", escape(t))).collect();
+        At {
+            base: self.base.clone(),
+            ptr: format!("{}{suffix}", self.ptr),
+            keyword: format!("{}{suffix}", self.keyword),
+            in_ref: self.in_ref,
+        }
+    }
+
+    /// absolute returns the absolute location.
+    fn absolute(&self) -> String {
+        format!("{}#{}", self.base, self.ptr)
+    }
+
+    /// unit returns an output unit for `error` of keyword `kw` at `path`.
+    fn unit(&self, kw: &str, path: &[Token], error: String) -> Unit {
+        let at = self.child(&[kw]);
+        Unit {
+            keyword_location: at.keyword.clone(),
+            absolute_keyword_location: self.in_ref.then(|| at.absolute()),
+            instance_location: data_ref::pointer(path),
+            error,
+        }
+    }
+}
+
+/// Unit is an error found by `Keywords`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Unit {
+    pub(crate) keyword_location: String,
+    pub(crate) absolute_keyword_location: Option<String>,
+    pub(crate) instance_location: String,
+    pub(crate) error: String,
+}
+
+impl Unit {
+    /// output returns the unit in the JSON Schema "basic" output format.
+    pub(crate) fn output(&self) -> Value {
+        let mut unit = json!({
+            "valid": false,
+            "keywordLocation": self.keyword_location,
+            "instanceLocation": self.instance_location,
+            "error": self.error,
+        });
+        if let Some(loc) = &self.absolute_keyword_location {
+            unit["absoluteKeywordLocation"] = Value::String(loc.to_string());
+        }
+        unit
+    }
+}
+
+/// Walk checks an instance against the keywords, following applicators.
+struct Walk<'a> {
+    keywords: &'a Keywords,
+    schemas: &'a boon::Schemas,
+    // The document being checked, to resolve `$data` references against.
+    root: &'a Value,
+    references: References,
+    // The schema and instance locations being checked, to stop at cycles.
+    seen: HashSet<(String, String)>,
+}
+
+impl<'a> Walk<'a> {
+    /// check checks `instance` at `path` against `schema` at `at`.
+    fn check(
+        &mut self,
+        schema: &'a Value,
+        at: &At,
+        instance: &Value,
+        path: &mut Vec<Token>,
+    ) -> Vec<Unit> {
+        let mut units = Vec::new();
+        let Value::Object(obj) = schema else {
+            return units;
+        };
+        // boon reports infinite loops.
+        let seen = (at.absolute(), data_ref::pointer(path));
+        if !self.seen.insert(seen.clone()) {
+            return units;
+        }
+        self.assertions(obj, at, instance, path, &mut units);
+        self.applicators(obj, at, instance, path, &mut units);
+        self.seen.remove(&seen);
+        units
+    }
+
+    /// assertions checks `instance` against the keywords of `obj`.
+    fn assertions(
+        &mut self,
+        obj: &Map<String, Value>,
+        at: &At,
+        instance: &Value,
+        path: &[Token],
+        units: &mut Vec<Unit>,
+    ) {
+        if let Some(t) = obj
+            .get(reference::KEYWORD)
+            .and_then(|v| Target::parse(v).ok())
+        {
+            if let Err(e) = t.check(instance, &mut self.references) {
+                units.push(at.unit(reference::KEYWORD, path, e));
+            }
+        }
+        if self.keywords.data {
+            data_ref::check(obj, self.root, path, instance, |kw, e| {
+                units.push(at.unit(kw, path, e))
+            });
+        }
+        for c in checks(&self.keywords.keywords, obj) {
+            if let Err(e) = c.call(instance) {
+                units.push(at.unit(&c.keyword, path, e));
+            }
+        }
+    }
+
+    /// applicators checks `instance` against the subschemas of `obj`.
+    fn applicators(
+        &mut self,
+        obj: &'a Map<String, Value>,
+        at: &At,
+        instance: &Value,
+        path: &mut Vec<Token>,
+        units: &mut Vec<Unit>,
+    ) {
+        for kw in ["$ref", "$dynamicRef", "$recursiveRef"] {
+            let Some(Value::String(r)) = obj.get(kw) else {
+                continue;
+            };
+            if let Some((to, schema)) = self.keywords.resolve(&at.base, r) {
+                let to = At {
+                    keyword: format!("{}/{kw}", at.keyword),
+                    in_ref: true,
+                    ..to
+                };
+                units.extend(self.check(schema, &to, instance, path));
+            }
+        }
+
+        if let Some(Value::Array(all)) = obj.get("allOf") {
+            for (i, s) in all.iter().enumerate() {
+                units.extend(self.check(s, &at.child(&["allOf", &i.to_string()]), instance, path));
+            }
+        }
+
+        // The errors of the branches apply only if none is valid.
+        for kw in ["anyOf", "oneOf"] {
+            let Some(Value::Array(branches)) = obj.get(kw) else {
+                continue;
+            };
+            let mut results = Vec::with_capacity(branches.len());
+            for (i, s) in branches.iter().enumerate() {
+                let at = at.child(&[kw, &i.to_string()]);
+                let found = self.check(s, &at, instance, path);
+                results.push((at, found));
+            }
+            if results.iter().all(|(_, found)| found.is_empty()) {
+                continue;
+            }
+            let results: Vec<_> = results
+                .into_iter()
+                .map(|(at, mut found)| {
+                    found.extend(self.compiled(&at, instance, path));
+                    found
+                })
+                .collect();
+            if results.iter().all(|found| !found.is_empty()) {
+                units.extend(results.into_iter().flatten());
+            }
+        }
+
+        let branched = obj.contains_key("then") || obj.contains_key("else");
+        if let (Some(cond), true) = (obj.get("if"), branched) {
+            let at_if = at.child(&["if"]);
+            let found = self.check(cond, &at_if, instance, path);
+            let valid = self.compiled(&at_if, instance, path).is_empty();
+            if found.is_empty() && valid {
+                if let Some(s) = obj.get("then") {
+                    units.extend(self.check(s, &at.child(&["then"]), instance, path));
+                }
+            } else if let Some(s) = obj.get("else") {
+                let at = at.child(&["else"]);
+                units.extend(self.check(s, &at, instance, path));
+                // boon applied then rather than else.
+                if valid {
+                    units.extend(self.compiled(&at, instance, path));
+                }
+            }
+        }
+
+        match instance {
+            Value::Object(props) => {
+                let properties = obj.get("properties").and_then(Value::as_object);
+                let patterns = obj.get("patternProperties").and_then(Value::as_object);
+                for (name, v) in props {
+                    path.push(Token::Prop(name.to_string()));
+                    let mut matched = false;
+                    if let Some(s) = properties.and_then(|p| p.get(name)) {
+                        matched = true;
+                        units.extend(self.check(s, &at.child(&["properties", name]), v, path));
+                    }
+                    for (pattern, s) in patterns.into_iter().flatten() {
+                        if self
+                            .keywords
+                            .patterns
+                            .get(pattern)
+                            .is_some_and(|re| re.is_match(name))
+                        {
+                            matched = true;
+                            let at = at.child(&["patternProperties", pattern]);
+                            units.extend(self.check(s, &at, v, path));
+                        }
+                    }
+                    if let (false, Some(s)) = (matched, obj.get("additionalProperties")) {
+                        units.extend(self.check(s, &at.child(&["additionalProperties"]), v, path));
+                    }
+                    path.pop();
+                }
+                for kw in ["dependentSchemas", "dependencies"] {
+                    let Some(Value::Object(deps)) = obj.get(kw) else {
+                        continue;
+                    };
+                    for (name, s) in deps {
+                        if props.contains_key(name) {
+                            units.extend(self.check(s, &at.child(&[kw, name]), instance, path));
+                        }
+                    }
+                }
+            }
+            Value::Array(items) => {
+                // prefixItems and items, or, before 2020-12, items as an
+                // array and additionalItems.
+                let (prefix, rest) = match (obj.get("prefixItems"), obj.get("items")) {
+                    (Some(_), _) => ("prefixItems", "items"),
+                    (None, Some(Value::Array(_))) => ("items", "additionalItems"),
+                    _ => ("", "items"),
+                };
+                let prefixed = obj.get(prefix).and_then(Value::as_array);
+                for (i, v) in items.iter().enumerate() {
+                    path.push(Token::Item(i));
+                    match prefixed.and_then(|p| p.get(i)) {
+                        Some(s) => {
+                            let at = at.child(&[prefix, &i.to_string()]);
+                            units.extend(self.check(s, &at, v, path));
+                        }
+                        None => {
+                            if let Some(s) = obj.get(rest).filter(|s| !s.is_array()) {
+                                units.extend(self.check(s, &at.child(&[rest]), v, path));
+                            }
+                        }
+                    }
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// compiled validates `instance` against the subschema at `at` with `boon`.
+    fn compiled(&self, at: &At, instance: &Value, path: &[Token]) -> Vec<Unit> {
+        let Some(&index) = self.keywords.branches.get(&at.absolute()) else {
+            return Vec::new();
+        };
+        let Err(e) = self.schemas.validate(instance, index) else {
+            return Vec::new();
+        };
+        let output = serde_json::to_value(e.basic_output()).unwrap_or_default();
+        let prefix = data_ref::pointer(path);
+        output["errors"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|unit| {
+                let kw = unit["keywordLocation"].as_str().unwrap_or_default();
+                let absolute = match unit["absoluteKeywordLocation"].as_str() {
+                    Some(loc) => Some(loc.to_string()),
+                    None => at.in_ref.then(|| format!("{}{kw}", at.absolute())),
+                };
+                Unit {
+                    keyword_location: format!("{}{kw}", at.keyword),
+                    absolute_keyword_location: absolute,
+                    instance_location: format!(
+                        "{prefix}{}",
+                        unit["instanceLocation"].as_str().unwrap_or_default()
+                    ),
+                    error: unit["error"].as_str().unwrap_or_default().to_string(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// join resolves `id` against `base`, returning it without a fragment.
+fn join(base: &str, id: &str) -> String {
+    let id = without_fragment(id);
+    if id.contains(':') {
+        return id.to_string();
+    }
+    let base = without_fragment(base);
+    match base.rfind('/') {
+        Some(i) => format!("{}{id}", &base[..=i]),
+        None => id.to_string(),
+    }
+}
+
+/// without_fragment returns `url` without its fragment.
+pub(crate) fn without_fragment(url: &str) -> &str {
+    url.split('#').next().unwrap_or(url)
+}
+
+/// escape escapes `token` for use in a JSON Pointer.
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// checks returns a Check for each of `keywords` in the schema object `obj`.
+fn checks(keywords: &[Keyword], obj: &Map<String, Value>) -> Vec<Check> {
+    keywords
+        .iter()
+        .filter_map(|k| {
+            obj.get(&k.keyword).map(|value| Check {
+                keyword: k.keyword.clone(),
+                query: format!("SELECT {}($1, $2)", k.function),
+                returns_bool: k.returns_bool,
+                value: value.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Check is a custom keyword with its value in a schema.
+#[derive(Debug, PartialEq)]
+struct Check {
+    keyword: String,
+    query: String,
+    returns_bool: bool,
+    value: Value,
+}
+
+impl Check {
+    /// call returns the error message of the function for `instance`, if any.
+    fn call(&self, instance: &Value) -> Result<(), String> {
+        let args = [
+            JsonB(self.value.clone()).into(),
+            JsonB(instance.clone()).into(),
+        ];
+        if self.returns_bool {
+            match Spi::get_one_with_args::<bool>(&self.query, &args) {
+                Ok(Some(true)) => Ok(()),
+                Ok(_) => Err(format!(r#"does not satisfy "{}""#, self.keyword)),
+                Err(e) => error!("{e}"),
+            }
+        } else {
+            match Spi::get_one_with_args::<String>(&self.query, &args) {
+                Ok(None) => Ok(()),
+                Ok(Some(msg)) => Err(msg),
+                Err(e) => error!("{e}"),
+            }
+        }
+    }
+}
+
+thread_local! {
+    // The registered keywords, reused until the table or a function changes.
+    static CACHE: TableCache<Vec<Keyword>> = const { TableCache::new("jsonschema_keywords") };
+}
+
+/// invalidate clears the cached keywords on an invalidation of `relid`.
+pub(crate) fn invalidate(relid: pg_sys::Oid) {
+    CACHE.with(|cache| cache.invalidate(relid));
+}
+
+/// registered returns the registered keywords, cached until they change.
+fn registered() -> Rc<Vec<Keyword>> {
+    CACHE.with(|cache| cache.get(load))
+}
+
+/// load loads the registered keywords.
+fn load() -> Vec<Keyword> {
+    Spi::connect(|client| {
+        client
+            .select(
+                &format!(
+                    "SELECT k.keyword, k.function::pg_catalog.regproc::text,
+                            p.prorettype = 'pg_catalog.bool'::pg_catalog.regtype
+                       FROM {} k
+                       JOIN pg_catalog.pg_proc p ON p.oid = k.function
+                      ORDER BY k.keyword",
+                    extension_table("jsonschema_keywords")
+                ),
+                None,
+                &[],
+            )?
+            .map(|row| {
+                Ok((
+                    row.get::<String>(1)?,
+                    row.get::<String>(2)?,
+                    row.get::<bool>(3)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, pgrx::spi::SpiError>>()
+    })
+    .unwrap_or_else(|e| error!("{e}"))
+    .into_iter()
+    .filter_map(|(keyword, function, returns_bool)| {
+        Some(Keyword {
+            keyword: keyword?,
+            function: function?,
+            returns_bool: returns_bool?,
+        })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_checks() {
+        let keywords = vec![
+            Keyword {
+                keyword: "x-sku".to_string(),
+                function: "public.sku_exists".to_string(),
+                returns_bool: true,
+            },
+            Keyword {
+                keyword: "x-date-order".to_string(),
+                function: "dates.check_order".to_string(),
+                returns_bool: false,
+            },
+        ];
+
+        let obj = json!({"type": "string", "x-sku": true});
+        assert_eq!(
+            vec![Check {
+                keyword: "x-sku".to_string(),
+                query: "SELECT public.sku_exists($1, $2)".to_string(),
+                returns_bool: true,
+                value: json!(true),
+            }],
+            checks(&keywords, obj.as_object().unwrap()),
+        );
+
+        let obj = json!({
+            "x-date-order": ["start", "end"],
+            "x-sku": {"table": "skus"},
+        });
+        assert_eq!(
+            vec![
+                Check {
+                    keyword: "x-sku".to_string(),
+                    query: "SELECT public.sku_exists($1, $2)".to_string(),
+                    returns_bool: true,
+                    value: json!({"table": "skus"}),
+                },
+                Check {
+                    keyword: "x-date-order".to_string(),
+                    query: "SELECT dates.check_order($1, $2)".to_string(),
+                    returns_bool: false,
+                    value: json!(["start", "end"]),
+                },
+            ],
+            checks(&keywords, obj.as_object().unwrap()),
+        );
+
+        let obj = json!({"type": "object"});
+        assert!(checks(&keywords, obj.as_object().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_keywords() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "max": {"$ref": "#/$defs/max"},
+                "alt": {"anyOf": [{"type": "string"}, {"$ref": "#/$defs/max"}]},
+                "tags": {"items": {"if": {"$ref": "#/$defs/max"}, "else": false}},
+            },
+            "$defs": {"max": {"minimum": {"$data": "/min"}}},
+        });
+        data_ref::with_enabled(true, || {
+            let (schemas, index) = crate::compile(crate::DEFAULT_URL, &[schema]).unwrap();
+            let instance = json!({"min": 5, "max": 6, "alt": "x", "tags": [7]});
+            assert!(crate::validate_instance(&schemas, &instance, index).is_ok());
+
+            // Errors of the keywords follow those of boon.
+            let instance = json!({"min": 5, "max": 4, "alt": 3, "tags": [7, 1]});
+            let Err(e) = crate::validate_instance(&schemas, &instance, index) else {
+                panic!("{instance} is valid");
+            };
+            assert_eq!(
+                "jsonschema validation failed with file:///schema.json#\n\
+                - at '/alt': want string, but got number\n\
+                - at '/alt': must be >= 5, but got 3\n\
+                - at '/max': must be >= 5, but got 4\n\
+                - at '/tags/1': false schema",
+                e.to_string(),
+            );
+            assert_eq!(
+                json!({
+                    "valid": false,
+                    "keywordLocation": "",
+                    "instanceLocation": "",
+                    "errors": [
+                        {
+                            "valid": false,
+                            "keywordLocation": "/properties/alt/anyOf/0/type",
+                            "instanceLocation": "/alt",
+                            "error": "want string, but got number",
+                        },
+                        {
+                            "valid": false,
+                            "keywordLocation": "/properties/alt/anyOf/1/$ref/minimum",
+                            "absoluteKeywordLocation": "file:///schema.json#/$defs/max/minimum",
+                            "instanceLocation": "/alt",
+                            "error": "must be >= 5, but got 3",
+                        },
+                        {
+                            "valid": false,
+                            "keywordLocation": "/properties/max/$ref/minimum",
+                            "absoluteKeywordLocation": "file:///schema.json#/$defs/max/minimum",
+                            "instanceLocation": "/max",
+                            "error": "must be >= 5, but got 4",
+                        },
+                        {
+                            "valid": false,
+                            "keywordLocation": "/properties/tags/items/else",
+                            "instanceLocation": "/tags/1",
+                            "error": "false schema",
+                        },
+                    ],
+                }),
+                e.basic_output(),
+            );
+        });
+    }
+
+    #[test]
+    fn test_resolve() {
+        let schemas = [
+            json!({
+                "$id": "https://example.com/user",
+                "$defs": {
+                    "name": {"$id": "name", "$anchor": "n", "type": "string"},
+                    "id": {"$anchor": "id", "x-pg-references": {"table": "t", "column": "c"}},
+                },
+            }),
+            json!({"$defs": {"x": {"type": "number"}}}),
+        ];
+        let mut c = crate::new_compiler(crate::DEFAULT_URL, &schemas).unwrap();
+        let mut compiled = boon::Schemas::new();
+        let roots = [(
+            c.compile("https://example.com/user", &mut compiled)
+                .unwrap(),
+            "https://example.com/user".to_string(),
+        )];
+        let Some(k) =
+            Keywords::new(&mut c, &mut compiled, crate::DEFAULT_URL, &schemas, &roots).unwrap()
+        else {
+            panic!("found no keywords");
+        };
+        for (base, reference, want) in [
+            (
+                "https://example.com/user",
+                "",
+                Some(("https://example.com/user", "")),
+            ),
+            (
+                "https://example.com/user",
+                "#/$defs/id",
+                Some(("https://example.com/user", "/$defs/id")),
+            ),
+            (
+                "https://example.com/user",
+                "#id",
+                Some(("https://example.com/user", "/$defs/id")),
+            ),
+            (
+                "https://example.com/user",
+                "name",
+                Some(("https://example.com/name", "")),
+            ),
+            (
+                "https://example.com/user",
+                "name#n",
+                Some(("https://example.com/name", "")),
+            ),
+            (
+                "https://example.com/name",
+                "#/type",
+                Some(("https://example.com/name", "/type")),
+            ),
+            (
+                "https://example.com/user",
+                "file:///schema.json1#/$defs/x",
+                Some(("file:///schema.json1", "/$defs/x")),
+            ),
+            ("https://example.com/user", "#nope", None),
+            ("https://example.com/user", "other", None),
+        ] {
+            assert_eq!(
+                want.map(|(base, ptr)| (base.to_string(), ptr.to_string())),
+                k.resolve(base, reference).map(|(at, _)| (at.base, at.ptr)),
+                "{base} {reference}",
+            );
+        }
+    }
+
+    #[test]
+    fn test_join() {
+        assert_eq!(
+            "https://example.com/name",
+            join("https://example.com/user", "name")
+        );
+        assert_eq!(
+            "https://example.org/x",
+            join("https://example.com/user", "https://example.org/x#")
+        );
+        assert_eq!(
+            "urn:example:x",
+            join("https://example.com/user", "urn:example:x")
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        for (name, schema, message) in [
+            (
+                "references",
+                json!({"x-pg-references": {"table": "x"}}),
+                r#"invalid "x-pg-references" {"table":"x"}: must be an object with only "table" and "column" strings"#,
+            ),
+            (
+                "error message",
+                json!({"properties": {"a": {"errorMessage": 1}}}),
+                r#"invalid "errorMessage" 1: must be a string or an object of keywords and strings"#,
+            ),
+        ] {
+            match crate::compile(crate::DEFAULT_URL, &[schema]) {
+                Err(CompileError::Keyword(e)) => assert_eq!(message, e, "{name}"),
+                Err(e) => panic!("{name}: unexpected error: {e:#}"),
+                Ok(_) => panic!("{name}: compiled"),
+            }
+        }
+    }
+}
@@ -1,8 +1,33 @@
-use boon::{CompileError, Compiler, Schemas};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::rc::Rc;
+
+use boon::{Compiler, SchemaIndex};
 use pgrx::prelude::*;
-use pgrx::{Json, JsonB, VariadicArray};
+use pgrx::{Json, JsonB, JsonString, VariadicArray};
 use serde_json::Value;
 
+mod binary;
+mod compat;
+mod data_ref;
+mod dialect;
+mod doc;
+mod draft;
+mod error_message;
+mod import;
+mod keyword;
+mod operator;
+mod pin;
+mod reference;
+mod registry;
+mod strict;
+mod subject;
+mod support;
+mod trigger;
+mod yaml;
+
 pgrx::pg_module_magic!();
 
 const DEFAULT_URL: &str = "file:///schema.json";
@@ -32,7 +57,10 @@ macro_rules! run_compiles {
     };
 }
 
-// run_validate runs validate for the verification functions.
+// run_validate runs validate for the verification functions. Pass the
+// function's fcinfo, the positions of its schema arguments, a closure that
+// returns the `$id` and schemas parsed from them, and the parsed data to
+// compile the schemas once per call site when those arguments are constant.
 macro_rules! run_validate {
     ($x:expr, $y:expr, $z:expr) => {
         match validate($x, $y, $z) {
@@ -40,6 +68,12 @@ macro_rules! run_validate {
             Ok(ok) => ok,
         }
     };
+    ($f:expr, $a:expr, $s:expr, $z:expr) => {
+        match validate_at($f, $a, $s, $z) {
+            Err(e) => error!("{e:#}"),
+            Ok(ok) => ok,
+        }
+    };
 }
 
 // Converts schemas from `pgrx::Array<_>` to `Vec<serde_json::Value>` and
@@ -50,6 +84,56 @@ macro_rules! values_for {
     };
 }
 
+// Raises an error if the VARIADIC $x holds a single array, as when a call
+// meant for a schema set function passes a jsonschema_set.
+macro_rules! reject_set {
+    ($x:expr) => {
+        if let [Value::Array(_)] = $x.as_slice() {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                "schema is an array: pass a jsonschema_set to jsonschema_is_valid_set() or jsonschema_validates_set()",
+            );
+        }
+    };
+}
+
+// Parses $x as JSON or YAML for the text functions, logging the error at the
+// `INFO` level and returning false from the calling function on failure.
+macro_rules! parse_or_false {
+    ($x:expr) => {
+        match $x {
+            Err(e) => {
+                info!("{e}");
+                return false;
+            }
+            Ok(v) => v,
+        }
+    };
+}
+
+// Parses $x as JSON or YAML for the text functions, raising an error on
+// failure.
+macro_rules! parse_or_error {
+    ($x:expr) => {
+        match $x {
+            Err(e) => error!("{e}"),
+            Ok(v) => v,
+        }
+    };
+}
+
+// Decodes binary $y in the format named by $x, raising an error if the format
+// is unknown or $y cannot be decoded.
+macro_rules! decode {
+    ($x:expr, $y:expr) => {
+        match binary::Format::from_name($x).and_then(|f| binary::decode(f, $y)) {
+            Err(e) => error!("{e}"),
+            Ok(v) => v,
+        }
+    };
+}
+
 // Schema validation functions.
 
 // jsonschema_is_valid(schema::json)
@@ -58,14 +142,14 @@ macro_rules! values_for {
 // jsonschema_is_valid(id::text, VARIADIC schema::jsonb)
 
 /// json_schema_is_valid validates `schema`.
-#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_is_valid")]
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_is_valid")]
 fn json_schema_is_valid(schema: Json) -> bool {
     let schemas = [schema.0];
     run_compiles!(id_for!(&schemas[0]), &schemas)
 }
 
 /// jsonb_schema_is_valid validates `schema`.
-#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_is_valid")]
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_is_valid")]
 fn jsonb_schema_is_valid(schema: JsonB) -> bool {
     let schemas = [schema.0];
     run_compiles!(id_for!(&schemas[0]), &schemas)
@@ -73,17 +157,19 @@ fn jsonb_schema_is_valid(schema: JsonB) -> bool {
 
 /// json_schema_id_is_valid validates the schema with the `$id` `id` from the
 /// `schemas`.
-#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_is_valid")]
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_is_valid")]
 fn json_schema_id_is_valid(id: &str, schemas: VariadicArray<Json>) -> bool {
     let schemas = values_for!(schemas);
+    reject_set!(schemas);
     run_compiles!(id, &schemas)
 }
 
 /// jsonb_schema_id_is_valid validates the schema with the `$id` `id` from the
 /// `schemas`.
-#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_is_valid")]
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_is_valid")]
 fn jsonb_schema_id_is_valid(id: &str, schemas: VariadicArray<JsonB>) -> bool {
     let schemas = values_for!(schemas);
+    reject_set!(schemas);
     run_compiles!(id, &schemas)
 }
 
@@ -95,31 +181,45 @@ fn jsonb_schema_id_is_valid(id: &str, schemas: VariadicArray<JsonB>) -> bool {
 // jsonschema_validates(data::jsonb, schema::json)
 
 /// json_schema_validates_json validates `data` against `schema`.
-#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_validates")]
-fn json_schema_validates_json(data: Json, schema: Json) -> bool {
-    let schemas = [schema.0];
-    run_validate!(id_for!(&schemas[0]), &schemas, data.0)
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn json_schema_validates_json(
+    data: JsonString,
+    schema: Json,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let data = parse_or_false!(parse_data(&data.0));
+    run_validate!(fcinfo, &[1], || single(schema.0), Ok(data))
 }
 
 /// jsonb_schema_validates_jsonb validates `data` against `schema`.
-#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_validates")]
-fn jsonb_schema_validates_jsonb(data: JsonB, schema: JsonB) -> bool {
-    let schemas = [schema.0];
-    run_validate!(id_for!(&schemas[0]), &schemas, data.0)
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn jsonb_schema_validates_jsonb(
+    data: JsonB,
+    schema: JsonB,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    run_validate!(fcinfo, &[1], || single(schema.0), Ok(data.0))
 }
 
 /// json_schema_validates_jsonb validates `data` against `schema`.
-#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_validates")]
-fn json_schema_validates_jsonb(data: Json, schema: JsonB) -> bool {
-    let schemas = [schema.0];
-    run_validate!(id_for!(&schemas[0]), &schemas, data.0)
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn json_schema_validates_jsonb(
+    data: JsonString,
+    schema: JsonB,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let data = parse_or_false!(parse_data(&data.0));
+    run_validate!(fcinfo, &[1], || single(schema.0), Ok(data))
 }
 
 /// jsonb_schema_validates_json validates `data` against `schema`.
-#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_validates")]
-fn jsonb_schema_validates_json(data: JsonB, schema: Json) -> bool {
-    let schemas = [schema.0];
-    run_validate!(id_for!(&schemas[0]), &schemas, data.0)
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn jsonb_schema_validates_json(
+    data: JsonB,
+    schema: Json,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    run_validate!(fcinfo, &[1], || single(schema.0), Ok(data.0))
 }
 
 // Multi-file document validation functions.
@@ -131,44 +231,681 @@ fn jsonb_schema_validates_json(data: JsonB, schema: Json) -> bool {
 
 /// json_schema_id_validates_json validates `data` against the schema with the
 /// `$id` `id` in `schemas`.
-#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_validates")]
-fn json_schema_id_validates_json(data: Json, id: &str, schemas: VariadicArray<Json>) -> bool {
-    let schemas = values_for!(schemas);
-    run_validate!(id, &schemas, data.0)
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn json_schema_id_validates_json(
+    data: JsonString,
+    id: &str,
+    schemas: VariadicArray<Json>,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let data = parse_or_false!(parse_data(&data.0));
+    let sources = || {
+        let schemas = values_for!(schemas);
+        reject_set!(schemas);
+        (id.to_string(), schemas)
+    };
+    run_validate!(fcinfo, &[1, 2], sources, Ok(data))
 }
 
 /// jsonb_schema_id_validates_jsonb validates `data` against the schema with
 /// the `$id` `id` in `schemas`.
-#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_validates")]
-fn jsonb_schema_id_validates_jsonb(data: JsonB, id: &str, schemas: VariadicArray<JsonB>) -> bool {
-    let schemas = values_for!(schemas);
-    run_validate!(id, &schemas, data.0)
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn jsonb_schema_id_validates_jsonb(
+    data: JsonB,
+    id: &str,
+    schemas: VariadicArray<JsonB>,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let sources = || {
+        let schemas = values_for!(schemas);
+        reject_set!(schemas);
+        (id.to_string(), schemas)
+    };
+    run_validate!(fcinfo, &[1, 2], sources, Ok(data.0))
 }
 
 /// json_schema_id_validates_jsonb validates `data` against the schema with
 /// the `$id` `id` in `schemas`.
-#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_validates")]
-fn json_schema_id_validates_jsonb(data: Json, id: &str, schemas: VariadicArray<JsonB>) -> bool {
-    let schemas = values_for!(schemas);
-    run_validate!(id, &schemas, data.0)
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn json_schema_id_validates_jsonb(
+    data: JsonString,
+    id: &str,
+    schemas: VariadicArray<JsonB>,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let data = parse_or_false!(parse_data(&data.0));
+    let sources = || {
+        let schemas = values_for!(schemas);
+        reject_set!(schemas);
+        (id.to_string(), schemas)
+    };
+    run_validate!(fcinfo, &[1, 2], sources, Ok(data))
 }
 
 /// jsonb_schema_id_validates_json validates `data` against the schema with
 /// the `$id` `id` in `schemas`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn jsonb_schema_id_validates_json(
+    data: JsonB,
+    id: &str,
+    schemas: VariadicArray<Json>,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let sources = || {
+        let schemas = values_for!(schemas);
+        reject_set!(schemas);
+        (id.to_string(), schemas)
+    };
+    run_validate!(fcinfo, &[1, 2], sources, Ok(data.0))
+}
+
+// Draft-pinned validation functions. Unlike the functions above, these
+// depend on neither jsonschema.default_draft nor
+// jsonschema.reject_duplicate_keys, and so are immutable.
+
+// jsonschema_is_valid(schema::json,  draft::text)
+// jsonschema_is_valid(schema::jsonb, draft::text)
+// jsonschema_validates(data::json,  schema::json,  draft::text)
+// jsonschema_validates(data::jsonb, schema::jsonb, draft::text)
+// jsonschema_validates(data::json,  schema::jsonb, draft::text)
+// jsonschema_validates(data::jsonb, schema::json,  draft::text)
+
+/// json_schema_is_valid_draft validates `schema`, defaulting to `draft`.
+#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_is_valid")]
+fn json_schema_is_valid_draft(schema: Json, draft: &str) -> bool {
+    is_valid_draft(draft_named(draft), schema.0)
+}
+
+/// jsonb_schema_is_valid_draft validates `schema`, defaulting to `draft`.
+#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_is_valid")]
+fn jsonb_schema_is_valid_draft(schema: JsonB, draft: &str) -> bool {
+    is_valid_draft(draft_named(draft), schema.0)
+}
+
+/// json_schema_validates_json_draft validates `data` against `schema`,
+/// defaulting to `draft`.
+#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_validates")]
+fn json_schema_validates_json_draft(
+    data: JsonString,
+    schema: Json,
+    draft: &str,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let data = parse_or_false!(parse(&data.0));
+    validates_draft(fcinfo, draft_named(draft), schema.0, data)
+}
+
+/// jsonb_schema_validates_jsonb_draft validates `data` against `schema`,
+/// defaulting to `draft`.
+#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_validates")]
+fn jsonb_schema_validates_jsonb_draft(
+    data: JsonB,
+    schema: JsonB,
+    draft: &str,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    validates_draft(fcinfo, draft_named(draft), schema.0, data.0)
+}
+
+/// json_schema_validates_jsonb_draft validates `data` against `schema`,
+/// defaulting to `draft`.
+#[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_validates")]
+fn json_schema_validates_jsonb_draft(
+    data: JsonString,
+    schema: JsonB,
+    draft: &str,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let data = parse_or_false!(parse(&data.0));
+    validates_draft(fcinfo, draft_named(draft), schema.0, data)
+}
+
+/// jsonb_schema_validates_json_draft validates `data` against `schema`,
+/// defaulting to `draft`.
 #[pg_extern(immutable, strict, parallel_safe, name = "jsonschema_validates")]
-fn jsonb_schema_id_validates_json(data: JsonB, id: &str, schemas: VariadicArray<Json>) -> bool {
+fn jsonb_schema_validates_json_draft(
+    data: JsonB,
+    schema: Json,
+    draft: &str,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    validates_draft(fcinfo, draft_named(draft), schema.0, data.0)
+}
+
+// $data reference validation functions. These enable or disable $data
+// references for the call, regardless of jsonschema.data_references.
+
+// jsonschema_validates(data::json,  schema::json,  data_refs::bool)
+// jsonschema_validates(data::jsonb, schema::jsonb, data_refs::bool)
+
+/// json_schema_validates_json_data_refs validates `data` against `schema`,
+/// with `$data` references enabled if `data_refs` is true.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn json_schema_validates_json_data_refs(
+    data: JsonString,
+    schema: Json,
+    data_refs: bool,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let data = parse_or_false!(parse_data(&data.0));
+    data_ref::with_enabled(data_refs, || {
+        run_validate!(fcinfo, &[1], || single(schema.0), Ok(data))
+    })
+}
+
+/// jsonb_schema_validates_jsonb_data_refs validates `data` against `schema`,
+/// with `$data` references enabled if `data_refs` is true.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn jsonb_schema_validates_jsonb_data_refs(
+    data: JsonB,
+    schema: JsonB,
+    data_refs: bool,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    data_ref::with_enabled(data_refs, || {
+        run_validate!(fcinfo, &[1], || single(schema.0), Ok(data.0))
+    })
+}
+
+/// draft_named returns the Draft named `name`, raising an error if there is
+/// no such draft.
+fn draft_named(name: &str) -> Draft {
+    Draft::from_name(name)
+        .unwrap_or_else(|e| ereport!(ERROR, PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE, e))
+}
+
+/// is_valid_draft validates `schema` with `draft` as the default draft.
+/// Logs the reason at the `INFO` level and returns false if it's invalid.
+fn is_valid_draft(draft: Draft, schema: Value) -> bool {
+    let schemas = [schema];
+    match compile_draft(draft, id_for!(&schemas[0]), &schemas) {
+        Err(e) => {
+            info!("{e:#}");
+            false
+        }
+        Ok(_) => true,
+    }
+}
+
+/// validates_draft validates `instance` against `schema` with `draft` as the
+/// default draft, compiling `schema` once per call site when it's constant.
+/// Raises an error if `schema` is invalid.
+fn validates_draft(
+    fcinfo: pg_sys::FunctionCallInfo,
+    draft: Draft,
+    schema: Value,
+    instance: Value,
+) -> bool {
+    match with_compiled_draft(
+        fcinfo,
+        &[1],
+        draft,
+        || single(schema),
+        |schemas, index| {
+            // Validate with boon alone rather than validate_instance(): the
+            // x-pg-references, $data, and errorMessage keywords depend on the
+            // database or session, and this function is IMMUTABLE.
+            if let Err(e) = schemas.compiled.validate(&instance, index) {
+                info!("{e}");
+                return false;
+            }
+            true
+        },
+    ) {
+        Err(e) => error!("{e:#}"),
+        Ok(ok) => ok,
+    }
+}
+
+// Text validation functions.
+
+// jsonschema_is_valid(schema::text)
+// jsonschema_is_valid(id::text, VARIADIC schema::text)
+// jsonschema_validates(data::text, schema::text)
+// jsonschema_validates(data::text, id::text, VARIADIC schema::text)
+
+/// text_schema_is_valid parses and validates `schema`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_is_valid")]
+fn text_schema_is_valid(schema: &str) -> bool {
+    let schemas = [parse_or_false!(parse(schema))];
+    run_compiles!(id_for!(&schemas[0]), &schemas)
+}
+
+/// text_schema_id_is_valid parses `schemas` and validates the schema with the
+/// `$id` `id`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_is_valid")]
+fn text_schema_id_is_valid(id: &str, schemas: VariadicArray<&str>) -> bool {
+    let schemas = parse_or_false!(parse_all(schemas.iter_deny_null()));
+    run_compiles!(id, &schemas)
+}
+
+/// text_schema_validates_text parses `data` and `schema` and validates `data`
+/// against `schema`. Returns false if `data` cannot be parsed.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn text_schema_validates_text(data: &str, schema: &str, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    let sources = || single(parse_or_error!(parse(schema)));
+    run_validate!(fcinfo, &[1], sources, parse_data(data))
+}
+
+/// text_schema_id_validates_text parses `data` and `schemas` and validates
+/// `data` against the schema with the `$id` `id`. Returns false if `data`
+/// cannot be parsed.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn text_schema_id_validates_text(
+    data: &str,
+    id: &str,
+    schemas: VariadicArray<&str>,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let sources = || {
+        let schemas = parse_or_error!(parse_all(schemas.iter_deny_null()));
+        (id.to_string(), schemas)
+    };
+    run_validate!(fcinfo, &[1, 2], sources, parse_data(data))
+}
+
+// Binary document validation functions.
+
+// jsonschema_validates(data::bytea, format::text, schema::json)
+// jsonschema_validates(data::bytea, format::text, schema::jsonb)
+// jsonschema_validates(data::bytea, format::text, id::text, VARIADIC schema::json)
+// jsonschema_validates(data::bytea, format::text, id::text, VARIADIC schema::jsonb)
+
+/// bytea_schema_validates_json decodes `data` from `format` and validates it
+/// against `schema`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn bytea_schema_validates_json(
+    data: &[u8],
+    format: &str,
+    schema: Json,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let data = decode!(format, data);
+    run_validate!(fcinfo, &[2], || single(schema.0), Ok(data))
+}
+
+/// bytea_schema_validates_jsonb decodes `data` from `format` and validates it
+/// against `schema`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn bytea_schema_validates_jsonb(
+    data: &[u8],
+    format: &str,
+    schema: JsonB,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let data = decode!(format, data);
+    run_validate!(fcinfo, &[2], || single(schema.0), Ok(data))
+}
+
+/// bytea_schema_id_validates_json decodes `data` from `format` and validates
+/// it against the schema with the `$id` `id` in `schemas`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn bytea_schema_id_validates_json(
+    data: &[u8],
+    format: &str,
+    id: &str,
+    schemas: VariadicArray<Json>,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let data = decode!(format, data);
+    let sources = || (id.to_string(), values_for!(schemas));
+    run_validate!(fcinfo, &[2, 3], sources, Ok(data))
+}
+
+/// bytea_schema_id_validates_jsonb decodes `data` from `format` and validates
+/// it against the schema with the `$id` `id` in `schemas`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates")]
+fn bytea_schema_id_validates_jsonb(
+    data: &[u8],
+    format: &str,
+    id: &str,
+    schemas: VariadicArray<JsonB>,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let data = decode!(format, data);
+    let sources = || (id.to_string(), values_for!(schemas));
+    run_validate!(fcinfo, &[2, 3], sources, Ok(data))
+}
+
+// YAML validation functions.
+
+// jsonschema_is_valid_yaml(schema::text)
+// jsonschema_is_valid_yaml(id::text, VARIADIC schema::text)
+// jsonschema_validates_yaml(data::text,  schema::text)
+// jsonschema_validates_yaml(data::text,  schema::json)
+// jsonschema_validates_yaml(data::text,  schema::jsonb)
+// jsonschema_validates_yaml(data::json,  schema::text)
+// jsonschema_validates_yaml(data::jsonb, schema::text)
+// jsonschema_validates_yaml(data::text,  id::text, VARIADIC schema::text)
+// jsonschema_validates_yaml(data::text,  id::text, VARIADIC schema::json)
+// jsonschema_validates_yaml(data::text,  id::text, VARIADIC schema::jsonb)
+// jsonschema_validates_yaml(data::json,  id::text, VARIADIC schema::text)
+// jsonschema_validates_yaml(data::jsonb, id::text, VARIADIC schema::text)
+
+/// yaml_schema_is_valid parses YAML `schema` and validates it.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_is_valid_yaml")]
+fn yaml_schema_is_valid(schema: &str) -> bool {
+    let schemas = [parse_or_false!(yaml::parse(schema))];
+    run_compiles!(id_for!(&schemas[0]), &schemas)
+}
+
+/// yaml_schema_id_is_valid parses YAML `schemas` and validates the schema
+/// with the `$id` `id`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_is_valid_yaml")]
+fn yaml_schema_id_is_valid(id: &str, schemas: VariadicArray<&str>) -> bool {
+    let schemas = parse_or_false!(yaml::parse_all(schemas.iter_deny_null()));
+    run_compiles!(id, &schemas)
+}
+
+/// yaml_schema_validates_yaml parses YAML `data` and `schema` and validates
+/// `data` against `schema`. Returns false if `data` cannot be parsed.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_yaml")]
+fn yaml_schema_validates_yaml(data: &str, schema: &str) -> bool {
+    let schemas = [parse_or_error!(yaml::parse(schema))];
+    let data = parse_or_false!(yaml::parse(data));
+    run_validate!(id_for!(&schemas[0]), &schemas, data)
+}
+
+/// yaml_schema_validates_json parses YAML `data` and validates it against
+/// `schema`. Returns false if `data` cannot be parsed.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_yaml")]
+fn yaml_schema_validates_json(data: &str, schema: Json) -> bool {
+    let schemas = [schema.0];
+    let data = parse_or_false!(yaml::parse(data));
+    run_validate!(id_for!(&schemas[0]), &schemas, data)
+}
+
+/// yaml_schema_validates_jsonb parses YAML `data` and validates it against
+/// `schema`. Returns false if `data` cannot be parsed.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_yaml")]
+fn yaml_schema_validates_jsonb(data: &str, schema: JsonB) -> bool {
+    let schemas = [schema.0];
+    let data = parse_or_false!(yaml::parse(data));
+    run_validate!(id_for!(&schemas[0]), &schemas, data)
+}
+
+/// json_schema_validates_yaml parses YAML `schema` and validates `data`
+/// against it.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_yaml")]
+fn json_schema_validates_yaml(data: JsonString, schema: &str) -> bool {
+    let schemas = [parse_or_error!(yaml::parse(schema))];
+    let data = parse_or_false!(parse_data(&data.0));
+    run_validate!(id_for!(&schemas[0]), &schemas, data)
+}
+
+/// jsonb_schema_validates_yaml parses YAML `schema` and validates `data`
+/// against it.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_yaml")]
+fn jsonb_schema_validates_yaml(data: JsonB, schema: &str) -> bool {
+    let schemas = [parse_or_error!(yaml::parse(schema))];
+    run_validate!(id_for!(&schemas[0]), &schemas, data.0)
+}
+
+/// yaml_schema_id_validates_yaml parses YAML `data` and `schemas` and
+/// validates `data` against the schema with the `$id` `id`. Returns false if
+/// `data` cannot be parsed.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_yaml")]
+fn yaml_schema_id_validates_yaml(data: &str, id: &str, schemas: VariadicArray<&str>) -> bool {
+    let schemas = parse_or_error!(yaml::parse_all(schemas.iter_deny_null()));
+    let data = parse_or_false!(yaml::parse(data));
+    run_validate!(id, &schemas, data)
+}
+
+/// yaml_schema_id_validates_json parses YAML `data` and validates it against
+/// the schema with the `$id` `id` in `schemas`. Returns false if `data`
+/// cannot be parsed.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_yaml")]
+fn yaml_schema_id_validates_json(data: &str, id: &str, schemas: VariadicArray<Json>) -> bool {
+    let schemas = values_for!(schemas);
+    let data = parse_or_false!(yaml::parse(data));
+    run_validate!(id, &schemas, data)
+}
+
+/// yaml_schema_id_validates_jsonb parses YAML `data` and validates it against
+/// the schema with the `$id` `id` in `schemas`. Returns false if `data`
+/// cannot be parsed.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_yaml")]
+fn yaml_schema_id_validates_jsonb(data: &str, id: &str, schemas: VariadicArray<JsonB>) -> bool {
     let schemas = values_for!(schemas);
+    let data = parse_or_false!(yaml::parse(data));
+    run_validate!(id, &schemas, data)
+}
+
+/// json_schema_id_validates_yaml parses YAML `schemas` and validates `data`
+/// against the schema with the `$id` `id`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_yaml")]
+fn json_schema_id_validates_yaml(data: JsonString, id: &str, schemas: VariadicArray<&str>) -> bool {
+    let schemas = parse_or_error!(yaml::parse_all(schemas.iter_deny_null()));
+    let data = parse_or_false!(parse_data(&data.0));
+    run_validate!(id, &schemas, data)
+}
+
+/// jsonb_schema_id_validates_yaml parses YAML `schemas` and validates `data`
+/// against the schema with the `$id` `id`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_yaml")]
+fn jsonb_schema_id_validates_yaml(data: JsonB, id: &str, schemas: VariadicArray<&str>) -> bool {
+    let schemas = parse_or_error!(yaml::parse_all(schemas.iter_deny_null()));
     run_validate!(id, &schemas, data.0)
 }
 
+// Schema set validation functions.
+
+// jsonschema_is_valid_set(id::text, schemas::jsonschema_set)
+// jsonschema_validates_set(data::json,  id::text, schemas::jsonschema_set)
+// jsonschema_validates_set(data::jsonb, id::text, schemas::jsonschema_set)
+// jsonschema_agg(schema::jsonb)
+
+/// set_schema_id_is_valid validates the schema with the `$id` `id` from the
+/// schema set `schemas`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn set_schema_id_is_valid(id: &str, schemas: JsonB) -> bool {
+    let schemas = parse_or_error!(set_values(schemas.0));
+    run_compiles!(id, &schemas)
+}
+
+/// json_schema_id_validates_set validates `data` against the schema with the
+/// `$id` `id` in the schema set `schemas`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn json_schema_id_validates_set(
+    data: JsonString,
+    id: &str,
+    schemas: JsonB,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let data = parse_or_false!(parse_data(&data.0));
+    let sources = || (id.to_string(), parse_or_error!(set_values(schemas.0)));
+    run_validate!(fcinfo, &[1, 2], sources, Ok(data))
+}
+
+/// jsonb_schema_id_validates_set validates `data` against the schema with the
+/// `$id` `id` in the schema set `schemas`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn jsonb_schema_id_validates_set(
+    data: JsonB,
+    id: &str,
+    schemas: JsonB,
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> bool {
+    let sources = || (id.to_string(), parse_or_error!(set_values(schemas.0)));
+    run_validate!(fcinfo, &[1, 2], sources, Ok(data.0))
+}
+
+// The schema set functions have names of their own because Postgres resolves
+// a call passing a jsonb set to an overload of jsonschema_is_valid() or
+// jsonschema_validates() to the VARIADIC jsonb function, which treats the set
+// as a single schema. They take a jsonschema_set domain, which checks that the
+// set is an array or object, and to which Postgres casts jsonb and unknown
+// literals.
+extension_sql!(
+    r#"
+-- jsonschema_set is an array of JSON Schemas or an object mapping "$id"s to
+-- JSON Schemas.
+CREATE DOMAIN jsonschema_set AS jsonb
+CHECK (jsonb_typeof(VALUE) IN ('array', 'object'));
+
+CREATE FUNCTION jsonschema_is_valid_set(
+	"id" TEXT, /* &str */
+	"schemas" jsonschema_set /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'set_schema_id_is_valid_wrapper';
+
+CREATE FUNCTION jsonschema_validates_set(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"id" TEXT, /* &str */
+	"schemas" jsonschema_set /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_schema_id_validates_set_wrapper';
+
+CREATE FUNCTION jsonschema_validates_set(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"id" TEXT, /* &str */
+	"schemas" jsonschema_set /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_schema_id_validates_set_wrapper';
+
+-- jsonschema_set_append appends schema to the schemas array. Used by
+-- jsonschema_agg.
+CREATE FUNCTION jsonschema_set_append(
+	"schemas" jsonschema_set,
+	"schema" jsonb
+) RETURNS jsonschema_set
+IMMUTABLE STRICT PARALLEL SAFE
+LANGUAGE sql
+AS $$ SELECT ($1 || jsonb_build_array($2))::jsonschema_set $$;
+
+-- jsonschema_agg aggregates schemas into a jsonschema_set array, skipping
+-- NULLs.
+CREATE AGGREGATE jsonschema_agg(jsonb) (
+	SFUNC = jsonschema_set_append,
+	STYPE = jsonschema_set,
+	INITCOND = '[]',
+	PARALLEL = SAFE
+);
+"#,
+    name = "jsonschema_set",
+);
+
+// Classification functions.
+
+// jsonschema_matching(data::json,  VARIADIC schema::json)
+// jsonschema_matching(data::jsonb, VARIADIC schema::jsonb)
+// jsonschema_matching(data::json,  VARIADIC schema::jsonb)
+// jsonschema_matching(data::jsonb, VARIADIC schema::json)
+// jsonschema_first_match(data::json,  VARIADIC schema::json)
+// jsonschema_first_match(data::jsonb, VARIADIC schema::jsonb)
+// jsonschema_first_match(data::json,  VARIADIC schema::jsonb)
+// jsonschema_first_match(data::jsonb, VARIADIC schema::json)
+
+/// json_schema_matching_json returns the IDs of the schemas in `schemas`
+/// that `data` validates against.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_matching")]
+fn json_schema_matching_json(
+    data: JsonString,
+    schemas: VariadicArray<Json>,
+) -> SetOfIterator<'static, String> {
+    SetOfIterator::new(matching(parse_data(&data.0), &values_for!(schemas), false))
+}
+
+/// jsonb_schema_matching_jsonb returns the IDs of the schemas in `schemas`
+/// that `data` validates against.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_matching")]
+fn jsonb_schema_matching_jsonb(
+    data: JsonB,
+    schemas: VariadicArray<JsonB>,
+) -> SetOfIterator<'static, String> {
+    SetOfIterator::new(matching(Ok(data.0), &values_for!(schemas), false))
+}
+
+/// json_schema_matching_jsonb returns the IDs of the schemas in `schemas`
+/// that `data` validates against.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_matching")]
+fn json_schema_matching_jsonb(
+    data: JsonString,
+    schemas: VariadicArray<JsonB>,
+) -> SetOfIterator<'static, String> {
+    SetOfIterator::new(matching(parse_data(&data.0), &values_for!(schemas), false))
+}
+
+/// jsonb_schema_matching_json returns the IDs of the schemas in `schemas`
+/// that `data` validates against.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_matching")]
+fn jsonb_schema_matching_json(
+    data: JsonB,
+    schemas: VariadicArray<Json>,
+) -> SetOfIterator<'static, String> {
+    SetOfIterator::new(matching(Ok(data.0), &values_for!(schemas), false))
+}
+
+/// json_schema_first_match_json returns the ID of the first schema in
+/// `schemas` that `data` validates against.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_first_match")]
+fn json_schema_first_match_json(data: JsonString, schemas: VariadicArray<Json>) -> Option<String> {
+    matching(parse_data(&data.0), &values_for!(schemas), true).pop()
+}
+
+/// jsonb_schema_first_match_jsonb returns the ID of the first schema in
+/// `schemas` that `data` validates against.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_first_match")]
+fn jsonb_schema_first_match_jsonb(data: JsonB, schemas: VariadicArray<JsonB>) -> Option<String> {
+    matching(Ok(data.0), &values_for!(schemas), true).pop()
+}
+
+/// json_schema_first_match_jsonb returns the ID of the first schema in
+/// `schemas` that `data` validates against.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_first_match")]
+fn json_schema_first_match_jsonb(
+    data: JsonString,
+    schemas: VariadicArray<JsonB>,
+) -> Option<String> {
+    matching(parse_data(&data.0), &values_for!(schemas), true).pop()
+}
+
+/// jsonb_schema_first_match_json returns the ID of the first schema in
+/// `schemas` that `data` validates against.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_first_match")]
+fn jsonb_schema_first_match_json(data: JsonB, schemas: VariadicArray<Json>) -> Option<String> {
+    matching(Ok(data.0), &values_for!(schemas), true).pop()
+}
+
+/// matching compiles all of `schemas` once and returns the IDs of those that
+/// `data` validates against, in order, or only the first if `first` is true.
+/// Raises an error if any schema fails to compile. Logs the error at the
+/// `INFO` level and returns no IDs if `data` failed to parse.
+fn matching(data: Result<Value, String>, schemas: &[Value], first: bool) -> Vec<String> {
+    let data = match data {
+        Err(e) => {
+            info!("{e}");
+            return Vec::new();
+        }
+        Ok(v) => v,
+    };
+    let (compiled, indexes) = compile_all(schemas).unwrap_or_else(|e| error!("{e:#}"));
+    let matches = indexes
+        .into_iter()
+        .filter(|(_, index)| validate_instance(&compiled, &data, *index).is_ok())
+        .map(|(id, _)| id);
+    if first {
+        matches.take(1).collect()
+    } else {
+        matches.collect()
+    }
+}
+
 // pg_jsonschema-compatible functions.
-#[pg_extern(immutable, strict, parallel_safe)]
-fn json_matches_schema(schema: Json, instance: Json) -> bool {
+#[pg_extern(stable, strict, parallel_safe)]
+fn json_matches_schema(schema: Json, instance: JsonString) -> bool {
     let schemas = [schema.0];
-    run_validate!(id_for!(&schemas[0]), &schemas, instance.0)
+    let instance = parse_or_false!(parse_data(&instance.0));
+    run_validate!(id_for!(&schemas[0]), &schemas, instance)
 }
 
-#[pg_extern(immutable, strict, parallel_safe)]
+#[pg_extern(stable, strict, parallel_safe)]
 fn jsonb_matches_schema(schema: Json, instance: JsonB) -> bool {
     let schemas = [schema.0];
     run_validate!(id_for!(&schemas[0]), &schemas, instance.0)
@@ -204,10 +941,39 @@ impl Into<boon::Draft> for Draft {
     }
 }
 
+impl Draft {
+    /// from_name returns the Draft named `name`, case-insensitively, as for
+    /// the values of jsonschema.default_draft.
+    fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_ascii_uppercase().as_str() {
+            "V4" => Ok(Draft::V4),
+            "V6" => Ok(Draft::V6),
+            "V7" => Ok(Draft::V7),
+            "V2019" => Ok(Draft::V2019),
+            "V2020" => Ok(Draft::V2020),
+            _ => Err(format!(
+                r#"invalid draft "{name}": must be V4, V6, V7, V2019, or V2020"#
+            )),
+        }
+    }
+}
+
 // GUC fetches the jsonschema.default_draft GUC value.
 static GUC: pgrx::GucSetting<Draft> = pgrx::GucSetting::<Draft>::new(Draft::V2020);
 
-// initialize the jsonschema.default_draft GUC.
+// REJECT_DUPLICATE_KEYS fetches the jsonschema.reject_duplicate_keys GUC
+// value.
+static REJECT_DUPLICATE_KEYS: pgrx::GucSetting<bool> = pgrx::GucSetting::<bool>::new(false);
+
+// DATA_REFERENCES fetches the jsonschema.data_references GUC value.
+static DATA_REFERENCES: pgrx::GucSetting<bool> = pgrx::GucSetting::<bool>::new(false);
+
+// TRIGGER_MODE fetches the jsonschema.trigger_mode GUC value.
+static TRIGGER_MODE: pgrx::GucSetting<trigger::Mode> =
+    pgrx::GucSetting::<trigger::Mode>::new(trigger::Mode::Default);
+
+// initialize the jsonschema.default_draft, jsonschema.reject_duplicate_keys,
+// jsonschema.data_references, and jsonschema.trigger_mode GUCs.
 fn init_guc() {
     // Register the GUC jsonschema.default_draft, with values defined by the
     // Draft enum.
@@ -219,6 +985,38 @@ fn init_guc() {
         pgrx::GucContext::Userset,
         pgrx::GucFlags::default(),
     );
+
+    // Register the GUC jsonschema.reject_duplicate_keys.
+    pgrx::GucRegistry::define_bool_guc(
+        "jsonschema.reject_duplicate_keys",
+        "Reject duplicate object keys in JSON data",
+        "Fail validation of json and text data containing duplicate object keys at any depth.",
+        &REJECT_DUPLICATE_KEYS,
+        pgrx::GucContext::Userset,
+        pgrx::GucFlags::default(),
+    );
+
+    // Register the GUC jsonschema.data_references.
+    pgrx::GucRegistry::define_bool_guc(
+        "jsonschema.data_references",
+        "Enable $data references in schemas",
+        "Allow the values of comparison keywords to be $data references, relative JSON Pointers into the data being validated.",
+        &DATA_REFERENCES,
+        pgrx::GucContext::Userset,
+        pgrx::GucFlags::default(),
+    );
+
+    // Register the GUC jsonschema.trigger_mode, with values defined by the
+    // trigger::Mode enum.
+    pgrx::GucRegistry::define_enum_guc(
+        "jsonschema.trigger_mode",
+        "Enforcement mode for jsonschema_validate_trigger()",
+        "Overrides the mode argument of jsonschema_validate_trigger() triggers unless set to Default.",
+        &TRIGGER_MODE,
+        // Only superusers may override the mode, lest others bypass enforcement.
+        pgrx::GucContext::Suset,
+        pgrx::GucFlags::default(),
+    );
 }
 
 /// _PG_init executes when Postgres loads the extension shared object library,
@@ -227,96 +1025,697 @@ fn init_guc() {
 #[pg_guard]
 extern "C-unwind" fn _PG_init() {
     init_guc();
+    unsafe {
+        pg_sys::CacheRegisterRelcacheCallback(Some(invalidate_relcache), pg_sys::Datum::from(0));
+        pg_sys::CacheRegisterSyscacheCallback(
+            pg_sys::SysCacheIdentifier::PROCOID as i32,
+            Some(invalidate_proc),
+            pg_sys::Datum::from(0),
+        );
+    }
 }
 
 /// new_compiler creates and returns a new `boon::Compiler` loaded with
 /// `schemas`. Each schema in `schemas` is named for its `$id` field or, if it
 /// has none, `id` is used for the first schema, and `"{id}{i}"` for
 /// subsequent schemas.
-fn new_compiler(id: &str, schemas: &[Value]) -> Result<Compiler, CompileError> {
+fn new_compiler(id: &str, schemas: &[Value]) -> Result<Compiler, boon::CompileError> {
+    let mut compiler = new_draft_compiler(GUC.get(), id, schemas)?;
+    // Load the registered dialects, so that schemas may name them in $schema.
+    dialect::add_resources(&mut compiler, id, schemas)?;
+    Ok(compiler)
+}
+
+/// new_draft_compiler creates and returns a new `boon::Compiler` loaded with
+/// `schemas` like `new_compiler`, but with `draft` as the default draft
+/// rather than jsonschema.default_draft, and without the registered dialects.
+fn new_draft_compiler(
+    draft: Draft,
+    id: &str,
+    schemas: &[Value],
+) -> Result<Compiler, boon::CompileError> {
     let mut compiler = Compiler::new();
-    compiler.set_default_draft(GUC.get().into());
+    compiler.set_default_draft(draft.into());
     // Use an empty loader to prevent boon from accessing the file system.
     compiler.use_loader(Box::new(boon::SchemeUrlLoader::new()));
 
     for (i, s) in schemas.iter().enumerate() {
-        let sid = if let Value::String(s) = &s["$id"] {
-            s.to_string()
-        } else if i == 0 {
-            // Use id for the first item.
-            id.to_string()
-        } else {
-            // Use loc{i} for others.
-            format!("{id}{i}")
-        };
-
-        compiler.add_resource(&sid, s.to_owned())?;
+        compiler.add_resource(&resource_id(id, i, s), s.to_owned())?;
     }
 
     Ok(compiler)
 }
 
-/// compiles compiles the schema named `id` in `schemas`, returning `Ok(())`
-/// on success and an error on failure.
-fn compiles(id: &str, schemas: &[Value]) -> Result<(), CompileError> {
-    let mut c = new_compiler(id, schemas)?;
-    let mut schemas = Schemas::new();
-    c.compile(id, &mut schemas)?;
-    Ok(())
+/// resource_id returns the `$id` of `schema`, the `i`th of the schemas passed
+/// to `new_compiler` with `id`, or the URL `new_compiler` uses for it if it
+/// has no `$id`.
+fn resource_id(id: &str, i: usize, schema: &Value) -> String {
+    if let Value::String(s) = &schema["$id"] {
+        s.to_string()
+    } else if i == 0 {
+        // Use id for the first item.
+        id.to_string()
+    } else {
+        // Use loc{i} for others.
+        format!("{id}{i}")
+    }
 }
 
-// Mock info!() during tests to just go to STDOUT. Would be nice to capture it
-// somehow, but the lack of reference to a std::io::Write in pgrx's info!()
-// makes it tricky.
-#[cfg(test)]
-macro_rules! info {
-    ($($arg:tt)*) => {{ println!($($arg)*)}};
+/// Schemas holds schemas compiled by `compile`, along with the keywords that
+/// `validate_instance` checks after `boon` validates an instance.
+pub(crate) struct Schemas {
+    compiled: boon::Schemas,
+    keywords: Option<keyword::Keywords>,
 }
 
-/// validate validates `instance` against schema `id` in `schemas`.
-fn validate(id: &str, schemas: &[Value], instance: Value) -> Result<bool, CompileError> {
-    match new_compiler(id, schemas) {
-        Err(e) => Err(e),
-        Ok(mut c) => {
-            let mut schemas = Schemas::new();
-            match c.compile(id, &mut schemas) {
-                Err(e) => Err(e),
-                Ok(index) => {
-                    if let Err(e) = schemas.validate(&instance, index) {
-                        info!("{e}");
-                        return Ok(false);
-                    }
-                    Ok(true)
-                }
-            }
-        }
+/// CompileError is an error compiling schemas: either an error from `boon`
+/// or an invalid value of a keyword `boon` ignores.
+#[derive(Debug)]
+pub(crate) enum CompileError {
+    Boon(boon::CompileError),
+    Keyword(String),
+}
+
+impl From<boon::CompileError> for CompileError {
+    fn from(e: boon::CompileError) -> Self {
+        CompileError::Boon(e)
     }
 }
 
-// Utility functions for the tests and pg_tests modules to use.
-#[cfg(any(test, feature = "pg_test"))]
-pub mod test_util {
-    use serde_json::Value;
-    // Load the user and address schemas (bytes loaded at compile time).
-    pub fn user_schema() -> Value {
-        let bytes = include_bytes!("../eg/user-profile.schema.json");
-        assert!(!bytes.is_empty());
-        serde_json::from_slice(bytes).unwrap()
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Boon(e) if f.alternate() => write!(f, "{e:#}"),
+            CompileError::Boon(e) => write!(f, "{e}"),
+            CompileError::Keyword(msg) => f.write_str(msg),
+        }
     }
+}
 
-    pub fn addr_schema() -> Value {
-        let bytes = include_bytes!("../eg/address.schema.json");
-        assert!(!bytes.is_empty());
-        serde_json::from_slice(bytes).unwrap()
+impl std::error::Error for CompileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CompileError::Boon(e) => e.source(),
+            CompileError::Keyword(_) => None,
+        }
     }
 }
 
-// Rust-only tests.
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::test_util::*;
-    use serde_json::json;
+/// compile compiles the schema named `id` in `schemas`, returning the
+/// compiled schemas and the index of the schema named `id`.
+fn compile(id: &str, schemas: &[Value]) -> Result<(Schemas, SchemaIndex), CompileError> {
+    let (compiled, indexes) = compile_ids(id, schemas, &[id.to_string()])?;
+    Ok((compiled, indexes[0]))
+}
+
+/// compile_draft compiles the schema named `id` in `schemas` like `compile`,
+/// but with a compiler created by `new_draft_compiler` for `draft`, and
+/// without the keywords `boon` ignores.
+fn compile_draft(
+    draft: Draft,
+    id: &str,
+    schemas: &[Value],
+) -> Result<(Schemas, SchemaIndex), CompileError> {
+    let mut c = new_draft_compiler(draft, id, schemas)?;
+    let mut compiled = boon::Schemas::new();
+    let index = c.compile(id, &mut compiled)?;
+    Ok((
+        Schemas {
+            compiled,
+            keywords: None,
+        },
+        index,
+    ))
+}
+
+/// compile_ids compiles the schemas named `ids` in `schemas`, loaded as by
+/// `new_compiler` with `id`, returning the compiled schemas and the index of
+/// each, in order.
+fn compile_ids(
+    id: &str,
+    schemas: &[Value],
+    ids: &[String],
+) -> Result<(Schemas, Vec<SchemaIndex>), CompileError> {
+    // Compile without $data references, if enabled, which boon would reject;
+    // the keyword pass checks them.
+    let mut c = new_compiler(id, &data_ref::strip(schemas))?;
+    let mut compiled = boon::Schemas::new();
+    let roots = ids
+        .iter()
+        .map(|id| Ok((c.compile(id, &mut compiled)?, id.to_string())))
+        .collect::<Result<Vec<_>, boon::CompileError>>()?;
+    let keywords = keyword::Keywords::new(&mut c, &mut compiled, id, schemas, &roots)?;
+    Ok((
+        Schemas { compiled, keywords },
+        roots.into_iter().map(|(index, _)| index).collect(),
+    ))
+}
+
+/// compile_all compiles every schema in `schemas` with a single compiler,
+/// returning the compiled schemas and the ID and index of each, in order.
+/// Schemas without an `$id` are named as by `new_compiler` with
+/// `DEFAULT_URL`.
+fn compile_all(schemas: &[Value]) -> Result<(Schemas, Vec<(String, SchemaIndex)>), CompileError> {
+    let ids: Vec<_> = schemas
+        .iter()
+        .enumerate()
+        .map(|(i, s)| resource_id(DEFAULT_URL, i, s))
+        .collect();
+    let (compiled, indexes) = compile_ids(DEFAULT_URL, schemas, &ids)?;
+    Ok((compiled, ids.into_iter().zip(indexes).collect()))
+}
+
+/// compiles compiles the schema named `id` in `schemas`, returning `Ok(())`
+/// on success and an error on failure.
+fn compiles(id: &str, schemas: &[Value]) -> Result<(), CompileError> {
+    compile(id, schemas)?;
+    Ok(())
+}
+
+/// extension_table returns the quoted, schema-qualified name of the
+/// extension's table or type `name`. The extension is relocatable, so its
+/// schema may not be in the search path.
+fn extension_table(name: &str) -> String {
+    let schema = Spi::get_one::<String>(
+        "SELECT n.nspname::text
+           FROM pg_catalog.pg_extension e
+           JOIN pg_catalog.pg_namespace n ON n.oid = e.extnamespace
+          WHERE e.extname = 'jsonschema'",
+    )
+    .unwrap_or_else(|e| error!("{e}"))
+    .unwrap_or_else(|| "public".to_string());
+    format!(
+        "{}.{}",
+        spi::quote_identifier(schema),
+        spi::quote_identifier(name)
+    )
+}
+
+/// extension_table_oid returns the OID of the extension table `name`.
+fn extension_table_oid(name: &str) -> pg_sys::Oid {
+    Spi::get_one_with_args::<pg_sys::Oid>(
+        "SELECT $1::pg_catalog.regclass::pg_catalog.oid",
+        &[extension_table(name).into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"))
+    .unwrap_or(pg_sys::InvalidOid)
+}
+
+/// parse parses `json` into a `serde_json::Value`, returning a message
+/// describing the failure if `json` is malformed.
+fn parse(json: &str) -> Result<Value, String> {
+    serde_json::from_str(json).map_err(|e| format!("invalid JSON: {e}"))
+}
+
+/// parse_all parses each item in `jsons` into a `serde_json::Value`,
+/// returning the message for the first one that's malformed.
+fn parse_all<'a, I: IntoIterator<Item = &'a str>>(jsons: I) -> Result<Vec<Value>, String> {
+    jsons.into_iter().map(parse).collect()
+}
+
+/// parse_data parses `json` data to be validated into a `serde_json::Value`.
+/// When jsonschema.reject_duplicate_keys is enabled, it also returns a
+/// validation failure message for the first duplicate object key in `json`.
+fn parse_data(json: &str) -> Result<Value, String> {
+    if !REJECT_DUPLICATE_KEYS.get() {
+        return parse(json);
+    }
+    match strict::parse(json) {
+        Err(e) => Err(format!("invalid JSON: {e}")),
+        Ok((_, Some(ptr))) => Err(format!(
+            "jsonschema validation failed: duplicate key at '{ptr}'"
+        )),
+        Ok((data, None)) => Ok(data),
+    }
+}
+
+/// set_values converts a schema set into a list of schemas, skipping nulls.
+/// The set may be either an array of schemas or an object mapping `$id`s to
+/// schemas; object schemas in the latter lacking an `$id` are assigned their
+/// key.
+fn set_values(set: Value) -> Result<Vec<Value>, String> {
+    match set {
+        Value::Array(list) => Ok(list.into_iter().filter(|s| !s.is_null()).collect()),
+        Value::Object(map) => Ok(map
+            .into_iter()
+            .filter(|(_, s)| !s.is_null())
+            .map(|(id, mut s)| {
+                if let Value::Object(obj) = &mut s {
+                    obj.entry("$id").or_insert(Value::String(id));
+                }
+                s
+            })
+            .collect()),
+        _ => Err("jsonschema_set must be an array or object".to_string()),
+    }
+}
+
+// Mock info!() during tests to just go to STDOUT. Would be nice to capture it
+// somehow, but the lack of reference to a std::io::Write in pgrx's info!()
+// makes it tricky.
+#[cfg(test)]
+macro_rules! info {
+    ($($arg:tt)*) => {{ println!($($arg)*)}};
+}
+
+/// validate validates `instance` against schema `id` in `schemas`.
+fn validate(id: &str, schemas: &[Value], instance: Value) -> Result<bool, CompileError> {
+    let (schemas, index) = compile(id, schemas)?;
+    if let Err(e) = validate_instance(&schemas, &instance, index) {
+        info!("{e}");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// single returns the `$id` of `schema` and a list of `schema` alone, for
+/// the functions that validate against a single schema.
+fn single(schema: Value) -> (String, Vec<Value>) {
+    (id_for!(&schema).to_string(), vec![schema])
+}
+
+/// validate_instance validates `instance` against the schema at `index` in
+/// `schemas`, then checks it against the keywords `boon` ignores, batching
+/// the table lookups of its `x-pg-references` keywords, resolving its `$data`
+/// references against `instance`, and reporting the messages of its
+/// `errorMessage` keywords.
+fn validate_instance<'s, 'v>(
+    schemas: &'s Schemas,
+    instance: &'v Value,
+    index: SchemaIndex,
+) -> Result<(), error_message::Invalid<'s, 'v>> {
+    let error = schemas.compiled.validate(instance, index).err();
+    let Some(keywords) = &schemas.keywords else {
+        return match error {
+            Some(e) => Err(error_message::Invalid::new(
+                Some(e),
+                "",
+                Vec::new(),
+                None,
+                instance,
+            )),
+            None => Ok(()),
+        };
+    };
+    let units = keywords.check(&schemas.compiled, instance, index);
+    if error.is_none() && units.is_empty() {
+        return Ok(());
+    }
+    Err(error_message::Invalid::new(
+        error,
+        keywords.url(index),
+        units,
+        Some(keywords),
+        instance,
+    ))
+}
+
+/// Restore calls a function to restore thread-local state when dropped, so
+/// that state set for the duration of a call is restored even if an error
+/// unwinds past it.
+struct Restore<F: FnOnce()>(Option<F>);
+
+impl<F: FnOnce()> Restore<F> {
+    fn new(restore: F) -> Self {
+        Restore(Some(restore))
+    }
+}
+
+impl<F: FnOnce()> Drop for Restore<F> {
+    fn drop(&mut self) {
+        if let Some(restore) = self.0.take() {
+            restore();
+        }
+    }
+}
+
+/// StatementCache caches values by key for the rest of the statement that
+/// created them, identified by its start time and command ID.
+pub(crate) struct StatementCache<K, V> {
+    entries: RefCell<HashMap<K, ((pg_sys::TimestampTz, pg_sys::CommandId), Rc<V>)>>,
+}
+
+impl<K: Eq + Hash, V> StatementCache<K, V> {
+    pub(crate) fn new() -> Self {
+        StatementCache {
+            entries: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// get returns the value cached for `key` in the current statement,
+    /// calling `create` to create and cache it if there is none. Evicts the
+    /// values cached for previous statements.
+    pub(crate) fn get(&self, key: K, create: impl FnOnce() -> V) -> Rc<V> {
+        let stmt = unsafe {
+            (
+                pg_sys::GetCurrentStatementStartTimestamp(),
+                pg_sys::GetCurrentCommandId(false),
+            )
+        };
+        if let Some((_, v)) = self.entries.borrow().get(&key).filter(|(s, _)| *s == stmt) {
+            return v.clone();
+        }
+
+        // Don't hold the borrow while creating, which may use the cache.
+        let v = Rc::new(create());
+        let mut entries = self.entries.borrow_mut();
+        entries.retain(|_, (s, _)| *s == stmt);
+        entries.insert(key, (stmt, v.clone()));
+        v
+    }
+}
+
+thread_local! {
+    // The number of relcache and syscache invalidations processed, so that
+    // TableCache discards values loaded while invalidations arrived.
+    static INVALIDATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// TableCache caches a value loaded from an extension table for the rest of
+/// the session, until an invalidation of the table clears it. Functions that
+/// modify the table call `invalidate_table()` to notify every session.
+pub(crate) struct TableCache<T> {
+    #[cfg_attr(test, allow(dead_code))]
+    table: &'static str,
+    entry: RefCell<Option<(pg_sys::Oid, Rc<T>)>>,
+}
+
+impl<T> TableCache<T> {
+    pub(crate) const fn new(table: &'static str) -> Self {
+        TableCache {
+            table,
+            entry: RefCell::new(None),
+        }
+    }
+
+    /// get returns the cached value, calling `load` to load and cache it if
+    /// there is none.
+    #[cfg(not(test))]
+    pub(crate) fn get(&self, load: impl FnOnce() -> T) -> Rc<T> {
+        if let Some((_, v)) = &*self.entry.borrow() {
+            return v.clone();
+        }
+
+        let seen = INVALIDATIONS.get();
+        let relid = extension_table_oid(self.table);
+        let v = Rc::new(load());
+        if INVALIDATIONS.get() == seen {
+            *self.entry.borrow_mut() = Some((relid, v.clone()));
+        }
+        v
+    }
+
+    /// get returns the default value: the Rust-only tests run without
+    /// Postgres, and so without extension tables.
+    #[cfg(test)]
+    pub(crate) fn get(&self, _: impl FnOnce() -> T) -> Rc<T>
+    where
+        T: Default,
+    {
+        Rc::new(T::default())
+    }
+
+    /// invalidate clears the cached value if it was loaded from the table
+    /// with OID `relid`, or if `relid` is `InvalidOid`.
+    pub(crate) fn invalidate(&self, relid: pg_sys::Oid) {
+        let mut entry = self.entry.borrow_mut();
+        if entry
+            .as_ref()
+            .is_some_and(|(r, _)| relid == pg_sys::InvalidOid || *r == relid)
+        {
+            *entry = None;
+        }
+    }
+}
+
+/// invalidate_table sends an invalidation of the extension table `name` to
+/// every session when the current transaction commits, and to the current
+/// session when the current command ends, clearing the TableCaches loaded
+/// from it.
+fn invalidate_table(name: &str) {
+    unsafe { pg_sys::CacheInvalidateRelcacheByRelid(extension_table_oid(name)) };
+}
+
+/// invalidate_relcache clears the TableCaches loaded from the table with OID
+/// `relid`, or all of them if `relid` is `InvalidOid`.
+#[pg_guard]
+unsafe extern "C-unwind" fn invalidate_relcache(_: pg_sys::Datum, relid: pg_sys::Oid) {
+    INVALIDATIONS.set(INVALIDATIONS.get() + 1);
+    dialect::invalidate(relid);
+    keyword::invalidate(relid);
+}
+
+/// invalidate_proc clears the custom keyword functions, which may have been
+/// dropped or replaced, when `pg_proc` changes.
+#[pg_guard]
+unsafe extern "C-unwind" fn invalidate_proc(_: pg_sys::Datum, _: i32, _: u32) {
+    INVALIDATIONS.set(INVALIDATIONS.get() + 1);
+    keyword::invalidate(pg_sys::InvalidOid);
+}
+
+/// CallSite holds schemas compiled for a function call site, kept in its
+/// `fn_extra`.
+struct CallSite {
+    args: Args,
+    compiled: Option<Compiled>,
+}
+
+/// Args describes the schema arguments of a call site, decided on its first
+/// call.
+enum Args {
+    /// Constants, which are the same for every call.
+    Constant,
+    /// Constants and external parameters. PL/pgSQL reuses a call site while
+    /// the values of its variables change, so a call site compares the raw
+    /// bytes of its arguments to decide whether to recompile.
+    Param,
+    /// Other expressions, which may change on every call.
+    Varying,
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+thread_local! {
+    // The number of schemas compiled by call sites, for tests.
+    static COMPILES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Compiled holds the schemas a CallSite compiled and the settings and
+/// arguments it compiled them with.
+struct Compiled {
+    draft: Draft,
+    data_refs: bool,
+    raw: Vec<Vec<u8>>,
+    schemas: Schemas,
+    index: SchemaIndex,
+}
+
+/// validate_at validates `data` like `validate`, using `with_compiled` to
+/// compile the schema returned by `sources` once per call site when the
+/// arguments of `fcinfo` at positions `args` are constant. Logs a `data`
+/// parse error at the `INFO` level and returns false.
+fn validate_at(
+    fcinfo: pg_sys::FunctionCallInfo,
+    args: &[i32],
+    sources: impl FnOnce() -> (String, Vec<Value>),
+    data: Result<Value, String>,
+) -> Result<bool, CompileError> {
+    with_compiled(fcinfo, args, sources, |schemas, index| {
+        let instance = match data {
+            Err(e) => {
+                info!("{e}");
+                return false;
+            }
+            Ok(v) => v,
+        };
+        if let Err(e) = validate_instance(schemas, &instance, index) {
+            info!("{e}");
+            return false;
+        }
+        true
+    })
+}
+
+/// with_compiled compiles the schema `id` in `schemas`, as returned by
+/// `sources`, and passes it to `f`. If the arguments of `fcinfo` at
+/// positions `args`, from which `sources` parses `id` and `schemas`, are
+/// constant for the lifetime of the calling expression, as they are for
+/// literals in queries and `CHECK` constraints, it keeps the compiled schema
+/// in the expression's `fn_extra` and reuses it on subsequent calls without
+/// calling `sources`, rather than recompiling it for every row.
+fn with_compiled<T>(
+    fcinfo: pg_sys::FunctionCallInfo,
+    args: &[i32],
+    sources: impl FnOnce() -> (String, Vec<Value>),
+    f: impl FnOnce(&Schemas, SchemaIndex) -> T,
+) -> Result<T, CompileError> {
+    with_call_site(
+        fcinfo,
+        args,
+        GUC.get(),
+        || {
+            let (id, schemas) = sources();
+            compile(&id, &schemas)
+        },
+        f,
+    )
+}
+
+/// with_compiled_draft compiles and passes the schema to `f` like
+/// `with_compiled`, but with `compile_draft` for `draft`.
+fn with_compiled_draft<T>(
+    fcinfo: pg_sys::FunctionCallInfo,
+    args: &[i32],
+    draft: Draft,
+    sources: impl FnOnce() -> (String, Vec<Value>),
+    f: impl FnOnce(&Schemas, SchemaIndex) -> T,
+) -> Result<T, CompileError> {
+    with_call_site(
+        fcinfo,
+        args,
+        draft,
+        || {
+            let (id, schemas) = sources();
+            compile_draft(draft, &id, &schemas)
+        },
+        f,
+    )
+}
+
+/// with_call_site passes the schema compiled by `compile` for the default
+/// draft `draft` to `f`, keeping it in the call site's `fn_extra` when the
+/// arguments of `fcinfo` at positions `args` are constant. It decides
+/// whether they are on the first call. A call site recompiles its schema if
+/// `draft` changes, if `$data` references are enabled or disabled, or if
+/// parameter arguments change.
+fn with_call_site<T>(
+    fcinfo: pg_sys::FunctionCallInfo,
+    args: &[i32],
+    draft: Draft,
+    compile: impl FnOnce() -> Result<(Schemas, SchemaIndex), CompileError>,
+    f: impl FnOnce(&Schemas, SchemaIndex) -> T,
+) -> Result<T, CompileError> {
+    let compile = || {
+        #[cfg(any(test, feature = "pg_test"))]
+        COMPILES.set(COMPILES.get() + 1);
+        compile()
+    };
+    if unsafe { (*fcinfo).flinfo.is_null() } {
+        let (schemas, index) = compile()?;
+        return Ok(f(&schemas, index));
+    }
+
+    let site = unsafe { &mut *pgrx::fcinfo::pg_func_extra(fcinfo, || None::<CallSite>) };
+    let site = site.get_or_insert_with(|| CallSite {
+        args: unsafe { Args::of(fcinfo, args) },
+        compiled: None,
+    });
+    let raw = match site.args {
+        Args::Varying => {
+            let (schemas, index) = compile()?;
+            return Ok(f(&schemas, index));
+        }
+        Args::Constant => Vec::new(),
+        Args::Param => unsafe { raw_args(fcinfo, args) },
+    };
+
+    let data_refs = data_ref::enabled();
+    let compiled = match site.compiled.take() {
+        Some(c) if c.draft == draft && c.data_refs == data_refs && c.raw == raw => {
+            site.compiled.insert(c)
+        }
+        _ => {
+            let (schemas, index) = compile()?;
+            site.compiled.insert(Compiled {
+                draft,
+                data_refs,
+                raw,
+                schemas,
+                index,
+            })
+        }
+    };
+    Ok(f(&compiled.schemas, compiled.index))
+}
+
+impl Args {
+    /// of describes the arguments of `fcinfo` at positions `args`:
+    /// `Constant` if they are all constants, `Param` if they are constants
+    /// or external parameters, and `Varying` otherwise.
+    ///
+    /// # Safety
+    ///
+    /// `fcinfo` must be a valid function call info pointer with `flinfo`.
+    unsafe fn of(fcinfo: pg_sys::FunctionCallInfo, args: &[i32]) -> Args {
+        let flinfo = (*fcinfo).flinfo;
+        let expr = (*flinfo).fn_expr;
+        if expr.is_null()
+            || !args
+                .iter()
+                .all(|&arg| pg_sys::get_fn_expr_arg_stable(flinfo, arg))
+        {
+            return Args::Varying;
+        }
+        let list = if pgrx::is_a(expr, pg_sys::NodeTag::T_FuncExpr) {
+            (*expr.cast::<pg_sys::FuncExpr>()).args
+        } else if pgrx::is_a(expr, pg_sys::NodeTag::T_OpExpr) {
+            (*expr.cast::<pg_sys::OpExpr>()).args
+        } else {
+            return Args::Param;
+        };
+        let list = pgrx::PgList::<pg_sys::Node>::from_pg(list);
+        let constant = args.iter().all(|&arg| {
+            list.get_ptr(arg as usize)
+                .is_some_and(|node| pgrx::is_a(node, pg_sys::NodeTag::T_Const))
+        });
+        if constant {
+            Args::Constant
+        } else {
+            Args::Param
+        }
+    }
+}
+
+/// raw_args returns the bytes of the `varlena` arguments of `fcinfo` at
+/// positions `args`, detoasted and flattened.
+///
+/// # Safety
+///
+/// `fcinfo` must be a valid function call info pointer whose arguments at
+/// positions `args` are non-`NULL` `varlena` values.
+unsafe fn raw_args(fcinfo: pg_sys::FunctionCallInfo, args: &[i32]) -> Vec<Vec<u8>> {
+    args.iter()
+        .map(|&arg| {
+            let datum = pgrx::fcinfo::pg_getarg_datum_raw(fcinfo, arg as usize);
+            let ptr = pg_sys::pg_detoast_datum_packed(datum.cast_mut_ptr());
+            std::slice::from_raw_parts(ptr.cast::<u8>(), pgrx::varsize_any(ptr)).to_vec()
+        })
+        .collect()
+}
+
+// Utility functions for the tests and pg_tests modules to use.
+#[cfg(any(test, feature = "pg_test"))]
+pub mod test_util {
+    use serde_json::Value;
+    // Load the user and address schemas (bytes loaded at compile time).
+    pub fn user_schema() -> Value {
+        let bytes = include_bytes!("../eg/user-profile.schema.json");
+        assert!(!bytes.is_empty());
+        serde_json::from_slice(bytes).unwrap()
+    }
+
+    pub fn addr_schema() -> Value {
+        let bytes = include_bytes!("../eg/address.schema.json");
+        assert!(!bytes.is_empty());
+        serde_json::from_slice(bytes).unwrap()
+    }
+}
+
+// Rust-only tests.
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::*;
+    use serde_json::json;
     use std::error::Error;
 
     // Make sure our Draft enum converts properly into boon's.
@@ -329,6 +1728,23 @@ mod test {
         assert_eq!(boon::Draft::V2020_12, Draft::V2020.into());
     }
 
+    #[test]
+    fn test_draft_from_name() {
+        for (name, draft) in [
+            ("V4", Draft::V4),
+            ("v6", Draft::V6),
+            ("V7", Draft::V7),
+            ("v2019", Draft::V2019),
+            ("V2020", Draft::V2020),
+        ] {
+            assert_eq!(Ok(draft), Draft::from_name(name), "{name}");
+        }
+        assert_eq!(
+            Err(r#"invalid draft "2020-12": must be V4, V6, V7, V2019, or V2020"#.to_string()),
+            Draft::from_name("2020-12"),
+        );
+    }
+
     #[test]
     fn test_compiles() -> Result<(), Box<dyn Error>> {
         let address = addr_schema();
@@ -368,10 +1784,10 @@ mod test {
 
         // Make sure it compiles user and address.
         let mut c = c.unwrap();
-        let mut schemas: Schemas = Schemas::new();
+        let mut schemas: boon::Schemas = boon::Schemas::new();
         c.compile(&id, &mut schemas)?;
 
-        let mut schemas = Schemas::new();
+        let mut schemas = boon::Schemas::new();
         assert!(c
             .compile("https://example.com/address.schema.json", &mut schemas)
             .is_ok());
@@ -383,15 +1799,15 @@ mod test {
 
         // It should have used the id.
         let mut c = c.unwrap();
-        let mut schemas = Schemas::new();
+        let mut schemas = boon::Schemas::new();
         assert!(c.compile(&id, &mut schemas).is_ok());
 
         // And appended "1" to the second schema.
-        let mut schemas = Schemas::new();
+        let mut schemas = boon::Schemas::new();
         assert!(c.compile("file:test.json1", &mut schemas).is_ok());
 
         // But no more.
-        let mut schemas = Schemas::new();
+        let mut schemas = boon::Schemas::new();
         let err = c.compile("file:test.json2", &mut schemas);
         assert!(err.is_err());
 
@@ -412,7 +1828,7 @@ mod test {
 
         // Test an invalid draft.
         let mut c = new_compiler(&id, &[json!({"$schema": "lol"})]).unwrap();
-        let mut schemas: Schemas = Schemas::new();
+        let mut schemas: boon::Schemas = boon::Schemas::new();
         assert!(c.compile(&id, &mut schemas).is_err());
 
         Ok(())
@@ -427,6 +1843,56 @@ mod test {
         assert_eq!(id_for!(json!(null)), "file:///schema.json");
     }
 
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(r#"{"x": "y"}"#), Ok(json!({"x": "y"})));
+        assert_eq!(parse("[1, 2]"), Ok(json!([1, 2])));
+        assert_eq!(
+            parse(r#"{"x": "y""#),
+            Err("invalid JSON: EOF while parsing an object at line 1 column 9".to_string()),
+        );
+
+        assert_eq!(
+            parse_all([r#"{"type": "object"}"#, "true"]),
+            Ok(vec![json!({"type": "object"}), json!(true)]),
+        );
+        assert!(parse_all([r#"{"type": "object"}"#, "nope"]).is_err());
+    }
+
+    #[test]
+    fn test_set_values() {
+        for (name, set, values) in [
+            ("empty array", json!([]), vec![]),
+            ("empty object", json!({}), vec![]),
+            (
+                "array",
+                json!([{"type": "object"}, true, null, {"$id": "x"}, null]),
+                vec![json!({"type": "object"}), json!(true), json!({"$id": "x"})],
+            ),
+            (
+                "object",
+                json!({
+                    "https://example.com/a": {"type": "object"},
+                    "https://example.com/b": {"$id": "https://example.com/c"},
+                    "https://example.com/d": null,
+                    "https://example.com/e": false,
+                }),
+                vec![
+                    json!({"type": "object", "$id": "https://example.com/a"}),
+                    json!({"$id": "https://example.com/c"}),
+                    json!(false),
+                ],
+            ),
+        ] {
+            assert_eq!(Ok(values), set_values(set), "{name}");
+        }
+
+        assert_eq!(
+            Err("jsonschema_set must be an array or object".to_string()),
+            set_values(json!("nope")),
+        );
+    }
+
     #[test]
     fn test_validate() -> Result<(), Box<dyn Error>> {
         let address_schema = addr_schema();
@@ -515,7 +1981,7 @@ mod tests {
     use super::*;
     use crate::test_util::*;
     use pgrx::pg_sys::panic::CaughtError::PostgresError;
-    use pgrx::{spi::SpiError, Json, JsonB};
+    use pgrx::{spi::SpiError, Json, JsonB, JsonString};
     use serde_json::json;
 
     // Enum used to record handling expected errors.
@@ -539,7 +2005,7 @@ mod tests {
         ));
         assert!(crate::json_matches_schema(
             Json(json!({"type": "object"})),
-            Json(json!({"hi": "there"})),
+            JsonString(json!({"hi": "there"}).to_string()),
         ));
     }
 
@@ -903,35 +2369,2621 @@ mod tests {
     }
 
     #[pg_test]
-    fn test_draft_schema_guc() -> spi::Result<()> {
-        let draft = Spi::get_one("SELECT current_setting('jsonschema.default_draft')")?;
-        assert_eq!(Some("V2020"), draft);
-        assert_eq!(Draft::V2020, GUC.get());
+    fn test_jsonschema_text() -> spi::Result<()> {
+        let address_schema = addr_schema();
+        let user_schema = user_schema();
+        let user_id = user_schema.get("$id").unwrap().as_str().unwrap();
 
-        Spi::run("SELECT set_config('jsonschema.default_draft', 'V2019', false)")?;
-        let draft = Spi::get_one("SELECT current_setting('jsonschema.default_draft')")?;
-        assert_eq!(Some("V2019"), draft);
-        assert_eq!(Draft::V2019, GUC.get());
+        // Valid schema.
+        let query = format!(
+            "SELECT jsonschema_is_valid('{}'::text)",
+            json!({"type": "object"}),
+        );
+        assert_eq!(Spi::get_one(&query)?, Some(true));
 
-        Spi::run("SET jsonschema.default_draft TO 'V4'")?;
-        let draft = Spi::get_one("SHOW jsonschema.default_draft")?;
-        assert_eq!(Some("V4"), draft);
-        assert_eq!(Draft::V4, GUC.get());
+        // Malformed schema.
+        let result = Spi::get_one("SELECT jsonschema_is_valid('{\"type\": '::text)")?;
+        assert_eq!(result, Some(false));
 
-        // Make sure it fails for an invalid setting.
+        // Multiple schemas.
+        let query = format!(
+            "SELECT jsonschema_is_valid('{}'::text, '{}'::text, '{}'::text)",
+            user_id, user_schema, address_schema,
+        );
+        assert_eq!(Spi::get_one(&query)?, Some(true));
+
+        // Valid data.
+        let query = format!(
+            "SELECT jsonschema_validates('{}'::text, '{}'::text)",
+            json!({"x": "y"}),
+            json!({"type": "object"}),
+        );
+        assert_eq!(Spi::get_one(&query)?, Some(true));
+
+        // Invalid data.
+        let query = format!(
+            "SELECT jsonschema_validates('{}'::text, '{}'::text)",
+            json!(["x", "y"]),
+            json!({"type": "object"}),
+        );
+        assert_eq!(Spi::get_one(&query)?, Some(false));
+
+        // Malformed data.
+        let query = format!(
+            "SELECT jsonschema_validates('{{\"x\": '::text, '{}'::text)",
+            json!({"type": "object"}),
+        );
+        assert_eq!(Spi::get_one(&query)?, Some(false));
+
+        // Multiple schemas, malformed data.
+        let query = format!(
+            "SELECT jsonschema_validates('nope'::text, '{}'::text, '{}'::text, '{}'::text)",
+            user_id, user_schema, address_schema,
+        );
+        assert_eq!(Spi::get_one(&query)?, Some(false));
+
+        // Malformed schema.
+        let query = format!(
+            "SELECT jsonschema_validates('{}'::text, '{{\"type\": '::text)",
+            json!({"x": "y"}),
+        );
         let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
-            Spi::run("SET jsonschema.default_draft TO 'NONESUCH'")?;
+            Spi::run(&query)?;
             Ok(ErrorCaught::False)
         })
-        .catch_when(PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE, |_| {
+        .catch_when(PgSqlErrorCode::ERRCODE_INTERNAL_ERROR, |e| {
+            if let PostgresError(e) = e {
+                assert_eq!(
+                    "invalid JSON: EOF while parsing a value at line 1 column 9",
+                    e.message(),
+                );
+            }
             Ok(ErrorCaught::True)
         })
         .catch_others(|e| e.rethrow())
         .execute();
         assert_eq!(res, Ok(ErrorCaught::True));
 
-        // GUC should be unchanged.
-        assert_eq!(Draft::V4, GUC.get());
+        // NULL data.
+        let query = format!(
+            "SELECT jsonschema_validates(NULL::text, '{}'::text)",
+            json!({"type": "object"}),
+        );
+        let result: Option<bool> = Spi::get_one(&query)?;
+        assert_eq!(result, None);
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_validates_bytea() -> spi::Result<()> {
+        let address_schema = addr_schema();
+        let user_schema = user_schema();
+        let user_id = user_schema.get("$id").unwrap().as_str().unwrap();
+
+        // Encode JSON values as bytea literals.
+        let hex = |b: Vec<u8>| b.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        let cbor = |v: &Value| {
+            let mut buf = Vec::new();
+            ciborium::into_writer(v, &mut buf).unwrap();
+            format!("'\\x{}'::bytea", hex(buf))
+        };
+        fn to_msgpack(v: &Value) -> rmpv::Value {
+            match v {
+                Value::Null => rmpv::Value::Nil,
+                Value::Bool(b) => rmpv::Value::from(*b),
+                Value::Number(n) => match n.as_i64() {
+                    Some(i) => rmpv::Value::from(i),
+                    None => rmpv::Value::F64(n.as_f64().unwrap()),
+                },
+                Value::String(s) => rmpv::Value::from(s.as_str()),
+                Value::Array(a) => rmpv::Value::Array(a.iter().map(to_msgpack).collect()),
+                Value::Object(o) => rmpv::Value::Map(
+                    o.iter()
+                        .map(|(k, v)| (rmpv::Value::from(k.as_str()), to_msgpack(v)))
+                        .collect(),
+                ),
+            }
+        }
+        let msgpack = |v: &Value| {
+            let mut buf = Vec::new();
+            rmpv::encode::write_value(&mut buf, &to_msgpack(v)).unwrap();
+            format!("'\\x{}'::bytea", hex(buf))
+        };
+
+        let user = json!({"username": "user123", "email": "user@example.com"});
+        for (format, encode) in [
+            ("cbor", &cbor as &dyn Fn(&Value) -> String),
+            ("msgpack", &msgpack),
+        ] {
+            for schema_type in ["json", "jsonb"] {
+                // Valid.
+                let query = format!(
+                    "SELECT jsonschema_validates({}, '{format}', '{}'::{schema_type})",
+                    encode(&json!({"x": "y"})),
+                    json!({"type": "object"}),
+                );
+                assert_eq!(Spi::get_one(&query)?, Some(true));
+
+                // Invalid.
+                let query = format!(
+                    "SELECT jsonschema_validates({}, '{format}', '{}'::{schema_type})",
+                    encode(&json!(["x", "y"])),
+                    json!({"type": "object"}),
+                );
+                assert_eq!(Spi::get_one(&query)?, Some(false));
+
+                // Multiple schemas.
+                let query = format!(
+                    "SELECT jsonschema_validates({}, '{format}', '{user_id}', '{}'::{schema_type}, '{}'::{schema_type})",
+                    encode(&user),
+                    user_schema,
+                    address_schema,
+                );
+                assert_eq!(Spi::get_one(&query)?, Some(true));
+
+                // Malformed.
+                let query = format!(
+                    "SELECT jsonschema_validates('\\x'::bytea, '{format}', '{}'::{schema_type})",
+                    json!({"type": "object"}),
+                );
+                let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+                    Spi::run(&query)?;
+                    Ok(ErrorCaught::False)
+                })
+                .catch_when(PgSqlErrorCode::ERRCODE_INTERNAL_ERROR, |_| {
+                    Ok(ErrorCaught::True)
+                })
+                .catch_others(|e| e.rethrow())
+                .execute();
+                assert_eq!(res, Ok(ErrorCaught::True));
+            }
+        }
+
+        // Unknown format.
+        let query = format!(
+            "SELECT jsonschema_validates('\\x00'::bytea, 'bson', '{}'::jsonb)",
+            json!({"type": "object"}),
+        );
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run(&query)?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_INTERNAL_ERROR, |e| {
+            if let PostgresError(e) = e {
+                assert_eq!(
+                    r#"unsupported binary format "bson": must be "cbor" or "msgpack""#,
+                    e.message(),
+                );
+            }
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True));
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_yaml() -> spi::Result<()> {
+        // Valid schema.
+        let query = "SELECT jsonschema_is_valid_yaml(E'type: object\\nrequired: [x]\\n')";
+        assert_eq!(Spi::get_one(query)?, Some(true));
+
+        // Malformed schema.
+        let result = Spi::get_one("SELECT jsonschema_is_valid_yaml('{type: object')")?;
+        assert_eq!(result, Some(false));
+
+        // Multiple schemas.
+        let query = "SELECT jsonschema_is_valid_yaml('https://example.com/a', \
+            E'$id: https://example.com/a\\n$ref: b\\n', \
+            E'$id: https://example.com/b\\ntype: string\\n')";
+        assert_eq!(Spi::get_one(query)?, Some(true));
+
+        // Single schema.
+        for (data, schema, ok) in [
+            // YAML data and schema.
+            ("'x: y'", "'required: [x]'", true),
+            ("'z: y'", "'required: [x]'", false),
+            ("'200: ok'", "'required: [\"200\"]'", true),
+            // Malformed data.
+            ("E'x: y\\n  z: ['", "'type: object'", false),
+            // YAML data, JSON schema.
+            ("'x: y'", "'{\"type\": \"object\"}'::json", true),
+            ("'[x, y]'", "'{\"type\": \"object\"}'::jsonb", false),
+            // JSON data, YAML schema.
+            ("'{\"x\": \"y\"}'::json", "'type: object'", true),
+            ("'[\"x\", \"y\"]'::jsonb", "'type: object'", false),
+        ] {
+            let query = format!("SELECT jsonschema_validates_yaml({data}, {schema})");
+            assert_eq!(Spi::get_one(&query)?, Some(ok), "{query}");
+        }
+
+        // Multiple schemas.
+        let yaml = "'{$id: \"https://example.com/a\", $ref: b}', \
+                    '{$id: \"https://example.com/b\", type: string}'";
+        let json = "'{\"$id\": \"https://example.com/a\", \"$ref\": \"b\"}'::{t}, \
+                    '{\"$id\": \"https://example.com/b\", \"type\": \"string\"}'::{t}";
+        for (data, schemas, ok) in [
+            // YAML data and schemas.
+            ("'a'", yaml.to_string(), true),
+            ("'[a]'", yaml.to_string(), false),
+            // YAML data, JSON schemas.
+            ("'a'", json.replace("{t}", "json"), true),
+            ("'[a]'", json.replace("{t}", "jsonb"), false),
+            // JSON data, YAML schemas.
+            ("'\"a\"'::json", yaml.to_string(), true),
+            ("'[\"a\"]'::jsonb", yaml.to_string(), false),
+        ] {
+            let query = format!(
+                "SELECT jsonschema_validates_yaml({data}, 'https://example.com/a', {schemas})",
+            );
+            assert_eq!(Spi::get_one(&query)?, Some(ok), "{query}");
+        }
+
+        // Malformed schema.
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run("SELECT jsonschema_validates_yaml('x: y', '{type: object')")?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_INTERNAL_ERROR, |e| {
+            if let PostgresError(e) = e {
+                assert_eq!(
+                    "invalid YAML: did not find expected ',' or '}' at line 2 column 1, while parsing a flow mapping",
+                    e.message(),
+                );
+            }
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True));
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_set() -> spi::Result<()> {
+        let address_schema = addr_schema();
+        let user_schema = user_schema();
+        let user_id = user_schema.get("$id").unwrap().as_str().unwrap();
+        let addr_id = address_schema.get("$id").unwrap().as_str().unwrap();
+        let user = json!({
+            "username": "user123",
+            "email": "user@example.com",
+            "address": {"locality": "Big City", "region": "State", "countryName": "Country"},
+        });
+        let bad_user = json!({"username": "user123", "address": {"region": "State"}});
+
+        Spi::run("CREATE TEMPORARY TABLE schemas (schema jsonb)")?;
+        Spi::run_with_args(
+            "INSERT INTO schemas VALUES ($1), ($2), (NULL)",
+            &[
+                JsonB(user_schema.clone()).into(),
+                JsonB(address_schema.clone()).into(),
+            ],
+        )?;
+        let agg = "(SELECT jsonschema_agg(schema) FROM schemas)";
+
+        // Aggregate skips NULLs.
+        let set: Option<JsonB> = Spi::get_one(&format!("SELECT {agg}"))?;
+        assert_eq!(json!([user_schema, address_schema]), set.unwrap().0,);
+        let set: Option<JsonB> =
+            Spi::get_one("SELECT jsonschema_agg(schema) FROM schemas WHERE schema IS NULL")?;
+        assert_eq!(json!([]), set.unwrap().0);
+
+        // Valid schema sets.
+        let query = format!("SELECT jsonschema_is_valid_set('{user_id}', {agg})");
+        assert_eq!(Spi::get_one(&query)?, Some(true));
+        let query = format!(
+            "SELECT jsonschema_is_valid_set('{user_id}', '{}'::jsonschema_set)",
+            json!({user_id: user_schema, addr_id: address_schema}),
+        );
+        assert_eq!(Spi::get_one(&query)?, Some(true));
+
+        // Missing schema.
+        let query = format!(
+            "SELECT jsonschema_is_valid_set('{user_id}', '{}'::jsonschema_set)",
+            json!([user_schema]),
+        );
+        assert_eq!(Spi::get_one(&query)?, Some(false));
+
+        for data_type in ["json", "jsonb"] {
+            // Aggregated schemas.
+            for (data, ok) in [(&user, true), (&bad_user, false)] {
+                let query = format!(
+                    "SELECT jsonschema_validates_set('{data}'::{data_type}, '{user_id}', {agg})",
+                );
+                assert_eq!(Spi::get_one(&query)?, Some(ok), "{query}");
+            }
+
+            // Array with NULLs.
+            let query = format!(
+                "SELECT jsonschema_validates_set('{user}'::{data_type}, '{user_id}', '{}'::jsonschema_set)",
+                json!([null, user_schema, null, address_schema]),
+            );
+            assert_eq!(Spi::get_one(&query)?, Some(true), "{query}");
+
+            // Object mapping IDs to schemas.
+            let mut anon_addr = address_schema.clone();
+            anon_addr.as_object_mut().unwrap().remove("$id");
+            let query = format!(
+                "SELECT jsonschema_validates_set('{user}'::{data_type}, '{user_id}', '{}'::jsonschema_set)",
+                json!({user_id: user_schema, addr_id: anon_addr, "nope": null}),
+            );
+            assert_eq!(Spi::get_one(&query)?, Some(true), "{query}");
+        }
+
+        // Single jsonb schemas still use the variadic functions.
+        let query = format!(
+            "SELECT jsonschema_validates('{user}'::jsonb, '{user_id}', '{user_schema}'::jsonb, '{address_schema}'::jsonb)",
+        );
+        assert_eq!(Spi::get_one(&query)?, Some(true));
+        let query = format!(
+            "SELECT jsonschema_validates('{}'::jsonb, '{addr_id}', '{address_schema}'::jsonb)",
+            user["address"],
+        );
+        assert_eq!(Spi::get_one(&query)?, Some(true));
+
+        // But reject a set passed as a single schema.
+        for query in [
+            format!("SELECT jsonschema_is_valid('{user_id}', {agg})"),
+            format!("SELECT jsonschema_validates('{user}'::jsonb, '{user_id}', {agg})"),
+            format!("SELECT jsonschema_validates('{user}'::json, '{user_id}', {agg})"),
+        ] {
+            assert_error(
+                &query,
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                "schema is an array: pass a jsonschema_set to jsonschema_is_valid_set() or jsonschema_validates_set()",
+            );
+        }
+
+        // Sets must be arrays or objects.
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run("SELECT '\"nope\"'::jsonschema_set")?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_CHECK_VIOLATION, |_| {
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True));
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_operators() -> spi::Result<()> {
+        let schema = json!({"type": "object"});
+        let types = ["json", "jsonb"];
+        for schema_type in types {
+            for data_type in types {
+                for (data, ok) in [(json!({"x": "y"}), true), (json!(["x", "y"]), false)] {
+                    let data = format!("'{data}'::{data_type}");
+                    let schema = format!("'{schema}'::{schema_type}");
+                    for (query, want) in [
+                        (format!("SELECT {data} @~ {schema}"), ok),
+                        (format!("SELECT {data} !@~ {schema}"), !ok),
+                        (format!("SELECT {schema} ~@ {data}"), ok),
+                        (format!("SELECT {schema} !~@ {data}"), !ok),
+                    ] {
+                        assert_eq!(Spi::get_one(&query)?, Some(want), "{query}");
+                    }
+                }
+
+                // NULLs.
+                for op in ["@~", "!@~"] {
+                    let query = format!("SELECT NULL::{data_type} {op} '{schema}'::{schema_type}");
+                    let result: Option<bool> = Spi::get_one(&query)?;
+                    assert_eq!(result, None, "{query}");
+                    let query = format!("SELECT '{{}}'::{data_type} {op} NULL::{schema_type}");
+                    let result: Option<bool> = Spi::get_one(&query)?;
+                    assert_eq!(result, None, "{query}");
+                }
+
+                // Invalid schema.
+                for op in ["@~", "!@~"] {
+                    assert_error(
+                        &format!(
+                            "SELECT '{{}}'::{data_type} {op} '{}'::{schema_type}",
+                            json!({"type": "nonesuch"}),
+                        ),
+                        PgSqlErrorCode::ERRCODE_INTERNAL_ERROR,
+                        "file:///schema.json# is not valid against metaschema: jsonschema validation failed with https://json-schema.org/draft/2020-12/schema#\n- at '/type': anyOf failed\n  - at '/type': value must be one of 'array', 'boolean', 'integer', 'null', 'number', 'object', 'string'\n  - at '/type': want array, but got string",
+                    );
+                }
+
+                // Commutators and negators.
+                for (op, com, neg) in [
+                    ("@~", "~@", "!@~"),
+                    ("!@~", "!~@", "@~"),
+                    ("~@", "@~", "!~@"),
+                    ("!~@", "!@~", "~@"),
+                ] {
+                    let query = format!(
+                        "SELECT oprcom = '{com}({schema_type},{data_type})'::regoperator
+                            AND oprnegate = '{neg}({data_type},{schema_type})'::regoperator
+                            AND oprrest = 'matchingsel'::regproc
+                            AND oprjoin = 'matchingjoinsel'::regproc
+                           FROM pg_operator
+                          WHERE oid = '{op}({data_type},{schema_type})'::regoperator",
+                    );
+                    assert_eq!(Spi::get_one(&query)?, Some(true), "{query}");
+                }
+            }
+        }
+
+        // Filter rows.
+        Spi::run("CREATE TEMPORARY TABLE docs (doc jsonb)")?;
+        Spi::run(r#"INSERT INTO docs VALUES ('{"x": 1}'), ('[1]'), ('{}'), (NULL)"#)?;
+        let query = format!("SELECT count(*) FROM docs WHERE doc @~ '{schema}'");
+        assert_eq!(Spi::get_one::<i64>(&query)?, Some(2));
+        let query = format!("SELECT count(*) FROM docs WHERE doc !@~ '{schema}'::jsonb");
+        assert_eq!(Spi::get_one::<i64>(&query)?, Some(1));
+        let query = format!("SELECT count(*) FROM docs WHERE NOT '{schema}'::jsonb ~@ doc");
+        assert_eq!(Spi::get_one::<i64>(&query)?, Some(1));
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_validates_support() -> spi::Result<()> {
+        Spi::run("CREATE TEMPORARY TABLE docs (doc jsonb)")?;
+        Spi::run(
+            r#"INSERT INTO docs
+               SELECT CASE WHEN i % 10 = 0 THEN '[1]' ELSE '{"x": 1}' END::jsonb
+                 FROM generate_series(1, 1000) i"#,
+        )?;
+        Spi::run("ANALYZE docs")?;
+        let plan = |filter: String| -> spi::Result<Value> {
+            let query = format!("EXPLAIN (FORMAT JSON) SELECT * FROM docs WHERE {filter}");
+            let plan: Option<Json> = Spi::get_one(&query)?;
+            Ok(plan.unwrap().0[0]["Plan"].clone())
+        };
+
+        // Assume a fixed selectivity, not one validated from the statistics.
+        let schema = json!({"type": "object"});
+        for call in [
+            format!("jsonschema_validates(doc, '{schema}'::json)"),
+            format!("jsonschema_validates(doc, '{schema}'::jsonb)"),
+            format!("jsonschema_validates(doc, 'https://example.com/x', '{schema}'::jsonb)"),
+            format!(
+                "jsonschema_validates_set(doc, 'https://example.com/x', '{{\"https://example.com/x\": {schema}}}'::jsonschema_set)"
+            ),
+            format!("jsonschema_validates(doc, '{schema}'::jsonb, 'V7')"),
+            format!("jsonschema_validates(doc, '{schema}'::jsonb, false)"),
+            "jsonschema_validates_yaml(doc, 'type: object')".to_string(),
+        ] {
+            for (filter, rows) in [(call.clone(), 10), (format!("NOT {call}"), 990)] {
+                let plan = plan(filter)?;
+                assert_eq!(json!(rows), plan["Plan Rows"], "{call}: {plan}");
+            }
+        }
+
+        // Estimate cost from the schema.
+        let big = json!({
+            "type": "object",
+            "required": ["x"],
+            "properties": {"x": {"type": "integer", "minimum": 0, "maximum": 10}},
+        });
+        let small_cost = plan(format!("jsonschema_validates(doc, '{schema}'::jsonb)"))?;
+        let big_cost = plan(format!("jsonschema_validates(doc, '{big}'::jsonb)"))?;
+        assert!(
+            big_cost["Total Cost"].as_f64() > small_cost["Total Cost"].as_f64(),
+            "{big_cost} vs {small_cost}",
+        );
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_compile_per_call_site() -> spi::Result<()> {
+        Spi::run("CREATE TEMPORARY TABLE docs (doc jsonb, schema jsonb)")?;
+        Spi::run(
+            r#"INSERT INTO docs VALUES
+                ('{"x": 1}', '{"type": "object"}'),
+                ('[1]',      '{"type": "object"}'),
+                ('[2]',      '{"type": "array"}'),
+                ('"x"',      '{"type": "array"}')"#,
+        )?;
+
+        // Constant schemas compile once.
+        for (schema, count) in [(r#"{"type": "object"}"#, 1), (r#"{"type": "array"}"#, 2)] {
+            for query in [
+                format!("SELECT count(*) FROM docs WHERE jsonschema_validates(doc, '{schema}'::jsonb)"),
+                format!("SELECT count(*) FROM docs WHERE jsonschema_validates(doc::json, '{schema}'::json)"),
+                format!("SELECT count(*) FROM docs WHERE jsonschema_validates(doc, 'https://example.com/x', '{schema}'::jsonb)"),
+                format!("SELECT count(*) FROM docs WHERE doc @~ '{schema}'::jsonb"),
+                format!("SELECT count(*) FROM docs WHERE '{schema}'::jsonb ~@ doc"),
+            ] {
+                COMPILES.set(0);
+                assert_eq!(Spi::get_one::<i64>(&query)?, Some(count), "{query}");
+                assert_eq!(1, COMPILES.get(), "{query}");
+            }
+        }
+
+        // Schemas that vary by row compile for every row.
+        for query in [
+            "SELECT count(*) FROM docs WHERE jsonschema_validates(doc, schema)",
+            "SELECT count(*) FROM docs WHERE doc @~ schema",
+            "SELECT count(*) FROM docs WHERE schema ~@ doc",
+        ] {
+            COMPILES.set(0);
+            assert_eq!(Spi::get_one::<i64>(query)?, Some(2), "{query}");
+            assert_eq!(4, COMPILES.get(), "{query}");
+        }
+
+        // Parameters are constant for each execution.
+        Spi::run("SET LOCAL plan_cache_mode = force_generic_plan")?;
+        Spi::run(
+            "PREPARE validates(jsonb) AS
+             SELECT count(*) FROM docs WHERE jsonschema_validates(doc, $1)",
+        )?;
+        for (schema, count) in [(r#"{"type": "object"}"#, 1), (r#"{"type": "array"}"#, 2)] {
+            let query = format!("EXECUTE validates('{schema}')");
+            COMPILES.set(0);
+            assert_eq!(Spi::get_one::<i64>(&query)?, Some(count), "{query}");
+            assert_eq!(1, COMPILES.get(), "{query}");
+        }
+        Spi::run("DEALLOCATE validates")?;
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_draft_schema_guc() -> spi::Result<()> {
+        let draft = Spi::get_one("SELECT current_setting('jsonschema.default_draft')")?;
+        assert_eq!(Some("V2020"), draft);
+        assert_eq!(Draft::V2020, GUC.get());
+
+        Spi::run("SELECT set_config('jsonschema.default_draft', 'V2019', false)")?;
+        let draft = Spi::get_one("SELECT current_setting('jsonschema.default_draft')")?;
+        assert_eq!(Some("V2019"), draft);
+        assert_eq!(Draft::V2019, GUC.get());
+
+        Spi::run("SET jsonschema.default_draft TO 'V4'")?;
+        let draft = Spi::get_one("SHOW jsonschema.default_draft")?;
+        assert_eq!(Some("V4"), draft);
+        assert_eq!(Draft::V4, GUC.get());
+
+        // Make sure it fails for an invalid setting.
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run("SET jsonschema.default_draft TO 'NONESUCH'")?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE, |_| {
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True));
+
+        // GUC should be unchanged.
+        assert_eq!(Draft::V4, GUC.get());
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_reject_duplicate_keys_guc() -> spi::Result<()> {
+        let setting = Spi::get_one("SHOW jsonschema.reject_duplicate_keys")?;
+        assert_eq!(Some("off"), setting);
+        assert!(!REJECT_DUPLICATE_KEYS.get());
+
+        let schema = json!({
+            "type": "object",
+            "properties": {"role": {"enum": ["user", "admin"]}},
+        });
+        let dup = r#"{"role": "user", "role": "admin"}"#;
+        let nested = r#"{"a": [{"b": 1, "b": 2}]}"#;
+        let queries = [
+            format!("SELECT jsonschema_validates('{dup}'::json, '{schema}'::json)"),
+            format!("SELECT jsonschema_validates('{dup}'::json, '{schema}'::jsonb)"),
+            format!("SELECT jsonschema_validates('{dup}'::json, 'x', '{schema}'::json)"),
+            format!("SELECT jsonschema_validates('{dup}'::json, 'x', '{schema}'::jsonb)"),
+            format!("SELECT jsonschema_validates('{dup}'::text, '{schema}'::text)"),
+            format!("SELECT jsonschema_validates('{dup}'::text, 'x', '{schema}'::text)"),
+            format!("SELECT jsonschema_validates_yaml('{dup}'::json, 'type: object')"),
+            format!("SELECT jsonschema_validates_yaml('{dup}'::json, 'x', 'type: object')"),
+            format!("SELECT json_matches_schema('{schema}', '{dup}')"),
+            format!("SELECT jsonschema_validates('{nested}'::json, '{schema}'::json)"),
+        ];
+
+        // Duplicates pass by default.
+        for query in &queries {
+            assert_eq!(Spi::get_one(query)?, Some(true), "{query}");
+        }
+
+        // Duplicates fail when rejected.
+        Spi::run("SET jsonschema.reject_duplicate_keys TO on")?;
+        assert!(REJECT_DUPLICATE_KEYS.get());
+        for query in &queries {
+            assert_eq!(Spi::get_one(query)?, Some(false), "{query}");
+        }
+
+        // JSONB has no duplicates.
+        let query = format!("SELECT jsonschema_validates('{dup}'::jsonb, '{schema}'::jsonb)");
+        assert_eq!(Spi::get_one(&query)?, Some(true));
+
+        // No duplicates.
+        let query = format!(
+            "SELECT jsonschema_validates('{}'::json, '{schema}'::json)",
+            json!({"role": "user", "a": {"role": "admin"}}),
+        );
+        assert_eq!(Spi::get_one(&query)?, Some(true));
+
+        // Invalid data still fails.
+        let query = format!(
+            "SELECT jsonschema_validates('{}'::json, '{schema}'::json)",
+            json!({"role": "root"}),
+        );
+        assert_eq!(Spi::get_one(&query)?, Some(false));
+
+        Ok(())
+    }
+
+    // Runs `query` and asserts that it raises a check violation with
+    // `message` and `detail`.
+    fn assert_check_violation(query: &str, message: &str, detail: &str) {
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run(query)?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_CHECK_VIOLATION, |e| {
+            if let PostgresError(e) = e {
+                assert_eq!(message, e.message());
+                assert_eq!(Some(detail), e.detail());
+            }
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True), "{query}");
+    }
+
+    // assert_error asserts that `query` raises an error with `code` and
+    // `message`.
+    fn assert_error(query: &str, code: PgSqlErrorCode, message: &str) {
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run(query)?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(code, |e| {
+            if let PostgresError(e) = e {
+                assert_eq!(message, e.message());
+            }
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True), "{query}");
+    }
+
+    #[pg_test]
+    fn test_jsonschema_validate_trigger() -> spi::Result<()> {
+        let schema = json!({
+            "$id": "https://example.com/doc",
+            "type": "object",
+            "properties": {"title": {"type": "string"}},
+        });
+        Spi::run("CREATE TABLE docs (id int PRIMARY KEY, body jsonb, meta json, note text)")?;
+        Spi::run(&format!(
+            "CREATE TRIGGER validate_docs BEFORE INSERT OR UPDATE ON docs
+             FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+                'column=body', 'column=meta', 'column=note', 'schema={schema}'
+             )"
+        ))?;
+
+        // Valid rows, including NULLs.
+        Spi::run(
+            r#"INSERT INTO docs VALUES
+               (1, '{"title": "hi"}', '{}', '{"title": "yo"}'),
+               (2, NULL, NULL, NULL)"#,
+        )?;
+        assert_eq!(Spi::get_one("SELECT count(*) FROM docs")?, Some(2i64));
+
+        // Invalid jsonb.
+        let detail = "Failing row has (id)=(3).\n\
+            jsonschema validation failed with https://example.com/doc#\n\
+            - at '/title': want string, but got number";
+        assert_check_violation(
+            r#"INSERT INTO docs VALUES (3, '{"title": 42}', NULL, NULL)"#,
+            r#"new row for relation "docs" violates jsonschema for column "body""#,
+            detail,
+        );
+
+        // Invalid json on update.
+        let detail = "Failing row has (id)=(2).\n\
+            jsonschema validation failed with https://example.com/doc#\n\
+            - at '': want object, but got array";
+        assert_check_violation(
+            "UPDATE docs SET meta = '[]' WHERE id = 2",
+            r#"new row for relation "docs" violates jsonschema for column "meta""#,
+            detail,
+        );
+
+        // Malformed text.
+        let detail = "Failing row has (id)=(4).\n\
+            invalid JSON: EOF while parsing an object at line 1 column 1";
+        assert_check_violation(
+            "INSERT INTO docs VALUES (4, NULL, NULL, '{')",
+            r#"new row for relation "docs" violates jsonschema for column "note""#,
+            detail,
+        );
+
+        // Sneak in an invalid value and update without changing it.
+        Spi::run("ALTER TABLE docs DISABLE TRIGGER validate_docs")?;
+        Spi::run(r#"UPDATE docs SET body = '{"title": true}' WHERE id = 2"#)?;
+        Spi::run("ALTER TABLE docs ENABLE TRIGGER validate_docs")?;
+        let detail = "Failing row has (id)=(2).\n\
+            jsonschema validation failed with https://example.com/doc#\n\
+            - at '/title': want string, but got boolean";
+        assert_check_violation(
+            "UPDATE docs SET note = NULL WHERE id = 2",
+            r#"new row for relation "docs" violates jsonschema for column "body""#,
+            detail,
+        );
+
+        // Skip unchanged columns.
+        Spi::run("DROP TRIGGER validate_docs ON docs")?;
+        Spi::run(&format!(
+            "CREATE TRIGGER validate_docs BEFORE INSERT OR UPDATE ON docs
+             FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+                'column=body', 'schema={schema}', 'on_update_only_if_changed=true'
+             )"
+        ))?;
+        Spi::run("UPDATE docs SET note = NULL WHERE id = 2")?;
+        let detail = "Failing row has (id)=(2).\n\
+            jsonschema validation failed with https://example.com/doc#\n\
+            - at '/title': want string, but got null";
+        assert_check_violation(
+            r#"UPDATE docs SET body = '{"title": null}' WHERE id = 2"#,
+            r#"new row for relation "docs" violates jsonschema for column "body""#,
+            detail,
+        );
+
+        // No primary key, multiple schemas.
+        Spi::run("CREATE TABLE logs (entry jsonb)")?;
+        Spi::run(
+            r#"CREATE TRIGGER validate_logs BEFORE INSERT ON logs
+               FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+                  'column=entry',
+                  'id=https://example.com/a',
+                  'schema={"$id": "https://example.com/a", "$ref": "b"}',
+                  'schema={"$id": "https://example.com/b", "type": "string"}'
+               )"#,
+        )?;
+        Spi::run(r#"INSERT INTO logs VALUES ('"hi"')"#)?;
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run("INSERT INTO logs VALUES ('42')")?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_CHECK_VIOLATION, |e| {
+            if let PostgresError(e) = e {
+                assert_eq!(
+                    r#"new row for relation "logs" violates jsonschema for column "entry""#,
+                    e.message(),
+                );
+                assert!(e
+                    .detail()
+                    .unwrap()
+                    .starts_with("jsonschema validation failed with https://example.com/a#"));
+            }
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True));
+
+        // Registered schemas, with their references.
+        Spi::run(
+            r#"SELECT jsonschema_register('{"$id": "https://example.com/b", "type": "string"}'),
+                      jsonschema_register('{"$id": "https://example.com/a", "properties": {"x": {"$ref": "b"}}}')"#,
+        )?;
+        Spi::run("CREATE TABLE notes (id int PRIMARY KEY, entry jsonb)")?;
+        Spi::run(
+            "CREATE TRIGGER validate_notes BEFORE INSERT ON notes
+             FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+                'column=entry', 'schema_id=https://example.com/a'
+             )",
+        )?;
+        Spi::run(r#"INSERT INTO notes VALUES (1, '{"x": "hi"}')"#)?;
+        assert_check_violation(
+            r#"INSERT INTO notes VALUES (2, '{"x": 42}')"#,
+            r#"new row for relation "notes" violates jsonschema for column "entry""#,
+            "Failing row has (id)=(2).\n\
+             jsonschema validation failed with https://example.com/a#\n\
+             - at '/x': want string, but got number",
+        );
+
+        // Invalid configurations.
+        for (args, code, message) in [
+            (
+                "'column=entry', 'schema_id=https://example.com/nonesuch'",
+                PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+                r#"invalid arguments for trigger "bad": no schema registered for "https://example.com/nonesuch""#,
+            ),
+            (
+                "'schema={}'",
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                r#"invalid arguments for trigger "bad": missing required argument "column""#,
+            ),
+            (
+                "'column=entry', 'schema={\"type\": \"nonesuch\"}'",
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                "",
+            ),
+            (
+                "'column=nope', 'schema={}'",
+                PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+                r#"column "nope" of relation "logs" does not exist"#,
+            ),
+        ] {
+            Spi::run("DROP TRIGGER IF EXISTS bad ON logs")?;
+            Spi::run(&format!(
+                "CREATE TRIGGER bad BEFORE INSERT ON logs
+                 FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger({args})"
+            ))?;
+            let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+                Spi::run(r#"INSERT INTO logs VALUES ('"hi"')"#)?;
+                Ok(ErrorCaught::False)
+            })
+            .catch_when(code, |e| {
+                if let PostgresError(e) = e {
+                    if !message.is_empty() {
+                        assert_eq!(message, e.message());
+                    }
+                }
+                Ok(ErrorCaught::True)
+            })
+            .catch_others(|e| e.rethrow())
+            .execute();
+            assert_eq!(res, Ok(ErrorCaught::True), "{args}");
+        }
+
+        // Statement-level triggers require a transition table.
+        Spi::run("DROP TRIGGER bad ON logs")?;
+        Spi::run(
+            "CREATE TRIGGER bad BEFORE INSERT ON logs
+             FOR EACH STATEMENT EXECUTE FUNCTION jsonschema_validate_trigger('column=entry', 'schema={}')",
+        )?;
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run(r#"INSERT INTO logs VALUES ('"hi"')"#)?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(
+            PgSqlErrorCode::ERRCODE_E_R_I_E_TRIGGER_PROTOCOL_VIOLATED,
+            |_| Ok(ErrorCaught::True),
+        )
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True));
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_validate_statement_trigger() -> spi::Result<()> {
+        let schema = json!({
+            "$id": "https://example.com/doc",
+            "type": "object",
+            "properties": {"title": {"type": "string"}},
+        });
+        Spi::run("CREATE TABLE items (id int PRIMARY KEY, body jsonb)")?;
+        for op in ["INSERT", "UPDATE"] {
+            Spi::run(&format!(
+                "CREATE TRIGGER validate_items_{op} AFTER {op} ON items
+                 REFERENCING NEW TABLE AS new_items
+                 FOR EACH STATEMENT EXECUTE FUNCTION jsonschema_validate_trigger(
+                    'column=body', 'schema={schema}', 'max_errors=2'
+                 )"
+            ))?;
+        }
+
+        // Valid rows, including NULLs.
+        Spi::run(r#"INSERT INTO items VALUES (1, '{"title": "hi"}'), (2, NULL)"#)?;
+        assert_eq!(Spi::get_one("SELECT count(*) FROM items")?, Some(2i64));
+
+        // One invalid row.
+        assert_check_violation(
+            r#"INSERT INTO items VALUES (3, '{"title": "yo"}'), (4, '{"title": 4}')"#,
+            r#"1 new row for relation "items" violates jsonschema"#,
+            "Failing row has (id)=(4).\n\
+            Column \"body\": jsonschema validation failed with https://example.com/doc#\n\
+            - at '/title': want string, but got number",
+        );
+
+        // More invalid rows than max_errors.
+        assert_check_violation(
+            r#"INSERT INTO items VALUES
+               (5, '{"title": 5}'), (6, '[]'), (7, '{"title": "ok"}'), (8, 'true')"#,
+            r#"3 new rows for relation "items" violate jsonschema"#,
+            "Failing row has (id)=(5).\n\
+            Column \"body\": jsonschema validation failed with https://example.com/doc#\n\
+            - at '/title': want string, but got number\n\
+            Failing row has (id)=(6).\n\
+            Column \"body\": jsonschema validation failed with https://example.com/doc#\n\
+            - at '': want object, but got array\n\
+            And 1 more invalid row.",
+        );
+
+        // Invalid update.
+        assert_check_violation(
+            "UPDATE items SET body = '[]'",
+            r#"2 new rows for relation "items" violate jsonschema"#,
+            "Failing row has (id)=(1).\n\
+            Column \"body\": jsonschema validation failed with https://example.com/doc#\n\
+            - at '': want object, but got array\n\
+            Failing row has (id)=(2).\n\
+            Column \"body\": jsonschema validation failed with https://example.com/doc#\n\
+            - at '': want object, but got array",
+        );
+        assert_eq!(Spi::get_one("SELECT count(*) FROM items")?, Some(2i64));
+
+        // No primary key.
+        Spi::run("CREATE TABLE events (payload json, extra text)")?;
+        Spi::run(&format!(
+            "CREATE TRIGGER validate_events AFTER INSERT ON events
+             REFERENCING NEW TABLE AS new_events
+             FOR EACH STATEMENT EXECUTE FUNCTION jsonschema_validate_trigger(
+                'column=payload', 'column=extra', 'schema={schema}'
+             )"
+        ))?;
+        assert_check_violation(
+            r#"INSERT INTO events VALUES ('{}', NULL), ('[]', '{')"#,
+            r#"1 new row for relation "events" violates jsonschema"#,
+            "Failing row 2.\n\
+            Column \"payload\": jsonschema validation failed with https://example.com/doc#\n\
+            - at '': want object, but got array\n\
+            Column \"extra\": invalid JSON: EOF while parsing an object at line 1 column 1",
+        );
+
+        // on_update_only_if_changed requires FOR EACH ROW.
+        Spi::run("DROP TRIGGER validate_events ON events")?;
+        Spi::run(&format!(
+            "CREATE TRIGGER validate_events AFTER UPDATE ON events
+             REFERENCING NEW TABLE AS new_events
+             FOR EACH STATEMENT EXECUTE FUNCTION jsonschema_validate_trigger(
+                'column=payload', 'schema={schema}', 'on_update_only_if_changed=true'
+             )"
+        ))?;
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run("UPDATE events SET extra = NULL")?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE, |e| {
+            if let PostgresError(e) = e {
+                assert_eq!(
+                    r#"invalid arguments for trigger "validate_events": "on_update_only_if_changed" requires FOR EACH ROW"#,
+                    e.message(),
+                );
+            }
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True));
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_validate_trigger_modes() -> spi::Result<()> {
+        let schema = json!({
+            "$id": "https://example.com/doc",
+            "type": "object",
+            "properties": {"title": {"type": "string"}},
+        });
+        let mode = Spi::get_one("SHOW jsonschema.trigger_mode")?;
+        assert_eq!(Some("Default"), mode);
+        assert_eq!(trigger::Mode::Default, TRIGGER_MODE.get());
+
+        Spi::run("CREATE TABLE posts (id int PRIMARY KEY, body jsonb)")?;
+        let create = |mode: &str| {
+            Spi::run("DROP TRIGGER IF EXISTS validate_posts ON posts")?;
+            Spi::run(&format!(
+                "CREATE TRIGGER validate_posts BEFORE INSERT OR UPDATE ON posts
+                 FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+                    'column=body', 'schema={schema}', 'mode={mode}'
+                 )"
+            ))
+        };
+        let quarantined = || {
+            Spi::get_one::<JsonB>(
+                "SELECT jsonb_agg(jsonb_build_object(
+                    'relation', relation::text, 'trigger', trigger_name,
+                    'op', operation, 'row', row_data, 'errors', errors
+                 ) ORDER BY id) FROM jsonschema_quarantine",
+            )
+            .map(|v| v.map(|v| v.0))
+        };
+
+        // Warn allows invalid rows.
+        create("warn")?;
+        Spi::run(r#"INSERT INTO posts VALUES (1, '{"title": 1}')"#)?;
+        assert_eq!(Spi::get_one("SELECT count(*) FROM posts")?, Some(1i64));
+
+        // The GUC overrides the trigger mode.
+        Spi::run("SET jsonschema.trigger_mode TO 'Enforce'")?;
+        assert_eq!(trigger::Mode::Enforce, TRIGGER_MODE.get());
+        assert_check_violation(
+            r#"INSERT INTO posts VALUES (2, '{"title": 2}')"#,
+            r#"new row for relation "posts" violates jsonschema for column "body""#,
+            "Failing row has (id)=(2).\n\
+            jsonschema validation failed with https://example.com/doc#\n\
+            - at '/title': want string, but got number",
+        );
+        Spi::run("RESET jsonschema.trigger_mode")?;
+
+        // Quarantine skips invalid rows and records them.
+        create("quarantine")?;
+        Spi::run(r#"INSERT INTO posts VALUES (3, '{"title": "ok"}'), (4, '[]')"#)?;
+        Spi::run(r#"UPDATE posts SET body = '{"title": false}' WHERE id = 3"#)?;
+        assert_eq!(
+            Spi::get_one::<JsonB>("SELECT jsonb_object_agg(id, body) FROM posts")?.map(|v| v.0),
+            Some(json!({"1": {"title": 1}, "3": {"title": "ok"}})),
+        );
+        assert_eq!(
+            quarantined()?,
+            Some(json!([
+                {
+                    "relation": "posts",
+                    "trigger": "validate_posts",
+                    "op": "INSERT",
+                    "row": {"id": 4, "body": []},
+                    "errors": [{
+                        "column": "body",
+                        "error": "jsonschema validation failed with https://example.com/doc#\n- at '': want object, but got array",
+                    }],
+                },
+                {
+                    "relation": "posts",
+                    "trigger": "validate_posts",
+                    "op": "UPDATE",
+                    "row": {"id": 3, "body": {"title": false}},
+                    "errors": [{
+                        "column": "body",
+                        "error": "jsonschema validation failed with https://example.com/doc#\n- at '/title': want string, but got boolean",
+                    }],
+                },
+            ])),
+        );
+
+        // The GUC can quarantine rows for an enforcing trigger.
+        Spi::run("DELETE FROM jsonschema_quarantine")?;
+        create("enforce")?;
+        Spi::run("SET jsonschema.trigger_mode TO 'Quarantine'")?;
+        Spi::run(r#"INSERT INTO posts VALUES (5, '"hi"')"#)?;
+        assert_eq!(
+            Spi::get_one("SELECT count(*) FROM posts WHERE id = 5")?,
+            Some(0i64)
+        );
+        assert_eq!(
+            Spi::get_one("SELECT count(*) FROM jsonschema_quarantine")?,
+            Some(1i64)
+        );
+
+        // Triggers that cannot skip rows warn instead.
+        Spi::run("DROP TRIGGER validate_posts ON posts")?;
+        Spi::run(&format!(
+            "CREATE TRIGGER validate_posts AFTER INSERT ON posts
+             REFERENCING NEW TABLE AS new_posts
+             FOR EACH STATEMENT EXECUTE FUNCTION jsonschema_validate_trigger(
+                'column=body', 'schema={schema}'
+             )"
+        ))?;
+        Spi::run(r#"INSERT INTO posts VALUES (6, '"hi"')"#)?;
+        assert_eq!(
+            Spi::get_one("SELECT count(*) FROM posts WHERE id = 6")?,
+            Some(1i64)
+        );
+        Spi::run("RESET jsonschema.trigger_mode")?;
+
+        // But they cannot be configured for quarantine.
+        Spi::run("DROP TRIGGER validate_posts ON posts")?;
+        Spi::run(&format!(
+            "CREATE TRIGGER validate_posts AFTER INSERT ON posts
+             FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+                'column=body', 'schema={schema}', 'mode=quarantine'
+             )"
+        ))?;
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run(r#"INSERT INTO posts VALUES (7, '{}')"#)?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE, |e| {
+            if let PostgresError(e) = e {
+                assert_eq!(
+                    r#"invalid arguments for trigger "validate_posts": "mode=quarantine" requires BEFORE and FOR EACH ROW"#,
+                    e.message(),
+                );
+            }
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True));
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_validate_deferred_trigger() -> spi::Result<()> {
+        let schema = json!({
+            "$id": "https://example.com/doc",
+            "type": "object",
+            "properties": {"title": {"type": "string"}},
+        });
+        Spi::run("CREATE TABLE drafts (id int PRIMARY KEY, body jsonb)")?;
+        Spi::run(&format!(
+            "CREATE CONSTRAINT TRIGGER validate_drafts AFTER INSERT OR UPDATE ON drafts
+             DEFERRABLE INITIALLY DEFERRED
+             FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+                'column=body', 'schema={schema}'
+             )"
+        ))?;
+
+        // Fix up an invalid row before the check.
+        Spi::run(r#"INSERT INTO drafts VALUES (1, '{"title": 1}')"#)?;
+        Spi::run(r#"UPDATE drafts SET body = '{"title": "one"}' WHERE id = 1"#)?;
+        Spi::run("SET CONSTRAINTS ALL IMMEDIATE")?;
+
+        // Delete an invalid row before the check.
+        Spi::run("SET CONSTRAINTS ALL DEFERRED")?;
+        Spi::run(r#"INSERT INTO drafts VALUES (2, '{"title": 2}')"#)?;
+        Spi::run("DELETE FROM drafts WHERE id = 2")?;
+        Spi::run("SET CONSTRAINTS ALL IMMEDIATE")?;
+
+        // Leave an invalid row for the check.
+        Spi::run("SET CONSTRAINTS ALL DEFERRED")?;
+        Spi::run(r#"INSERT INTO drafts VALUES (3, '{"title": 3}')"#)?;
+        assert_check_violation(
+            "SET CONSTRAINTS ALL IMMEDIATE",
+            r#"new row for relation "drafts" violates jsonschema for column "body""#,
+            "Failing row has (id)=(3).\n\
+            jsonschema validation failed with https://example.com/doc#\n\
+            - at '/title': want string, but got number",
+        );
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_doc() -> spi::Result<()> {
+        let user = json!({
+            "$id": "https://example.com/user",
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": {"type": "string"},
+                "address": {"$ref": "https://example.com/address"},
+            },
+        });
+        let address = json!({
+            "$id": "https://example.com/address",
+            "type": "object",
+            "properties": {"city": {"type": "string"}},
+        });
+
+        // Schemas must have an $id and compile.
+        assert_error(
+            r#"SELECT jsonschema_register('{"type": "object"}')"#,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"cannot register a schema without an "$id""#,
+        );
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run(&format!("SELECT jsonschema_register('{user}')"))?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE, |e| {
+            if let PostgresError(e) = e {
+                assert!(e
+                    .message()
+                    .starts_with(r#"invalid schema "https://example.com/user": "#));
+            }
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True));
+
+        // Register the referenced schema first.
+        for (schema, id) in [
+            (&address, "https://example.com/address"),
+            (&user, "https://example.com/user"),
+        ] {
+            let query = format!("SELECT jsonschema_register('{schema}')");
+            assert_eq!(Spi::get_one(&query)?, Some(id));
+        }
+        assert_eq!(
+            Spi::get_one("SELECT count(*) FROM jsonschema_registry")?,
+            Some(2i64)
+        );
+
+        // The type modifier names the schema.
+        Spi::run(
+            "CREATE TABLE users (id int, profile jsonschema_doc('https://example.com/user'))",
+        )?;
+        assert_eq!(
+            Spi::get_one(
+                "SELECT format_type(atttypid, atttypmod) FROM pg_attribute
+                  WHERE attrelid = 'users'::regclass AND attname = 'profile'"
+            )?,
+            Some("jsonschema_doc('https://example.com/user')"),
+        );
+        assert_eq!(
+            Spi::get_one(
+                "SELECT atttypmod FROM pg_attribute
+                  WHERE attrelid = 'users'::regclass AND attname = 'profile'"
+            )?,
+            Some(registry::typmod("https://example.com/user")),
+        );
+
+        // Type modifiers need not be registered until values are input or
+        // the type is output.
+        Spi::run("CREATE TABLE nope (doc jsonschema_doc('https://example.com/nope'))")?;
+        let unregistered = format!(
+            "no schema registered for jsonschema_doc type modifier {}",
+            registry::typmod("https://example.com/nope"),
+        );
+        assert_error(
+            "INSERT INTO nope VALUES ('{}')",
+            PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            &unregistered,
+        );
+        assert_error(
+            "SELECT format_type(atttypid, atttypmod) FROM pg_attribute
+              WHERE attrelid = 'nope'::regclass AND attname = 'doc'",
+            PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            &unregistered,
+        );
+        Spi::run("DROP TABLE nope")?;
+        assert_error(
+            "CREATE TABLE nope (doc jsonschema_doc('a', 'b'))",
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            "jsonschema_doc type modifier must be a single schema $id",
+        );
+
+        // Valid values, via input and assignment casts.
+        Spi::run(
+            r#"INSERT INTO users VALUES
+               (1, '{"name": "Naomi", "address": {"city": "Ceres"}}'),
+               (2, jsonb_build_object('name', 'Amos')),
+               (3, '{"name": "Alex", "n": 12345678901234567890.1234567890}'::json),
+               (4, NULL)"#,
+        )?;
+        assert_eq!(
+            Spi::get_one::<&str>("SELECT profile->>'name' FROM users WHERE id = 2")?,
+            Some("Amos"),
+        );
+        assert_eq!(
+            Spi::get_one::<&str>("SELECT profile::text FROM users WHERE id = 3")?,
+            Some(r#"{"n": 12345678901234567890.1234567890, "name": "Alex"}"#),
+        );
+
+        // Invalid values.
+        let message =
+            "value for type jsonschema_doc('https://example.com/user') violates jsonschema";
+        for (query, detail) in [
+            (
+                r#"INSERT INTO users VALUES (5, '{"name": 5}')"#,
+                "jsonschema validation failed with https://example.com/user#\n\
+                - at '/name': want string, but got number",
+            ),
+            (
+                r#"INSERT INTO users VALUES (5, '{"name": "Bobbie", "address": {"city": 5}}'::jsonb)"#,
+                "jsonschema validation failed with https://example.com/user#\n\
+                - at '/address/city': want string, but got number",
+            ),
+            (
+                "UPDATE users SET profile = profile::jsonb - 'name' WHERE id = 1",
+                "jsonschema validation failed with https://example.com/user#\n\
+                - at '': missing properties 'name'",
+            ),
+            (
+                "SELECT '[]'::jsonschema_doc('https://example.com/user')",
+                "jsonschema validation failed with https://example.com/user#\n\
+                - at '': want object, but got array",
+            ),
+        ] {
+            assert_check_violation(query, message, detail);
+        }
+
+        // No type modifier, no validation.
+        assert_eq!(
+            Spi::get_one::<&str>("SELECT '[1, 2]'::jsonschema_doc::text")?,
+            Some("[1, 2]"),
+        );
+
+        // Schemas in use cannot be replaced or unregistered.
+        assert_eq!(
+            Spi::get_one(&format!("SELECT jsonschema_register('{user}')"))?,
+            Some("https://example.com/user"),
+        );
+        assert_error(
+            r#"SELECT jsonschema_register('{"$id": "https://example.com/user"}')"#,
+            PgSqlErrorCode::ERRCODE_DEPENDENT_OBJECTS_STILL_EXIST,
+            r#"cannot replace schema "https://example.com/user" because column profile of relation users uses it"#,
+        );
+        assert_error(
+            "SELECT jsonschema_unregister('https://example.com/user')",
+            PgSqlErrorCode::ERRCODE_DEPENDENT_OBJECTS_STILL_EXIST,
+            r#"cannot unregister schema "https://example.com/user" because column profile of relation users uses it"#,
+        );
+        Spi::run("DROP TABLE users")?;
+        Spi::run("CREATE DOMAIN user_doc AS jsonschema_doc('https://example.com/user')")?;
+        assert_error(
+            "SELECT jsonschema_unregister('https://example.com/user')",
+            PgSqlErrorCode::ERRCODE_DEPENDENT_OBJECTS_STILL_EXIST,
+            r#"cannot unregister schema "https://example.com/user" because domain user_doc uses it"#,
+        );
+        Spi::run("DROP DOMAIN user_doc")?;
+        assert_eq!(
+            Spi::get_one("SELECT jsonschema_unregister('https://example.com/user')")?,
+            Some(true),
+        );
+        assert_eq!(
+            Spi::get_one("SELECT jsonschema_unregister('https://example.com/user')")?,
+            Some(false),
+        );
+
+        Ok(())
+    }
+
+    #[cfg(any(feature = "pg16", feature = "pg17"))]
+    #[pg_test]
+    fn test_soft_errors() -> spi::Result<()> {
+        let user = json!({
+            "$id": "https://example.com/user",
+            "type": "object",
+            "required": ["name"],
+            "properties": {"name": {"type": "string"}},
+        });
+        Spi::run(&format!("SELECT jsonschema_register('{user}')"))?;
+        Spi::run(&format!(
+            "CREATE DOMAIN user_doc AS jsonb CHECK (jsonschema_validates(VALUE, '{user}'::jsonb))"
+        ))?;
+
+        for type_name in ["jsonschema_doc(''https://example.com/user'')", "user_doc"] {
+            // Valid.
+            let query =
+                format!(r#"SELECT pg_input_is_valid('{{"name": "Kamala"}}', '{type_name}')"#);
+            assert_eq!(Spi::get_one(&query)?, Some(true), "{query}");
+
+            // Invalid.
+            let query = format!(r#"SELECT pg_input_is_valid('{{"name": 42}}', '{type_name}')"#);
+            assert_eq!(Spi::get_one(&query)?, Some(false), "{query}");
+
+            // Malformed.
+            let query = format!(r#"SELECT pg_input_is_valid('{{"name"', '{type_name}')"#);
+            assert_eq!(Spi::get_one(&query)?, Some(false), "{query}");
+        }
+
+        // Soft errors include the details.
+        let (message, detail, code) = Spi::get_three::<String, String, String>(
+            r#"SELECT message, detail, sql_error_code
+                 FROM pg_input_error_info('{"name": 42}', 'jsonschema_doc(''https://example.com/user'')')"#,
+        )?;
+        assert_eq!(
+            message.as_deref(),
+            Some("value for type jsonschema_doc('https://example.com/user') violates jsonschema"),
+        );
+        assert_eq!(
+            detail.as_deref(),
+            Some("jsonschema validation failed with https://example.com/user#\n- at '/name': want string, but got number"),
+        );
+        assert_eq!(code.as_deref(), Some("23514"));
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_import() -> spi::Result<()> {
+        let schema = json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {"name": {"type": "string"}},
+        });
+        let source = [
+            r#"{"name": "Kamala"}"#,
+            r#"{"name": 42}"#,
+            r#"{"name": "#,
+            "",
+            r#"{"name": "Tim", "age": 60}"#,
+        ]
+        .join("\n");
+        Spi::run("CREATE TABLE people (id serial, doc jsonb)")?;
+
+        for schema_type in ["json", "jsonb"] {
+            let query = format!(
+                "SELECT imported, rejected FROM jsonschema_import($1, 'people', 'doc', '{schema}'::{schema_type})"
+            );
+            let counts = Spi::get_two_with_args::<i64, i64>(&query, &[source.as_str().into()])?;
+            assert_eq!((Some(2), Some(2)), counts, "{query}");
+        }
+
+        // Schema composition.
+        let query = format!(
+            "SELECT imported, rejected FROM jsonschema_import($1, 'people', 'doc', 'https://example.com/person', '{}'::jsonb)",
+            json!({"$id": "https://example.com/person", "$ref": "https://example.com/name"}),
+        );
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run_with_args(&query, &[source.as_str().into()])?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_INTERNAL_ERROR, |_| {
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True));
+        let query = format!(
+            "SELECT imported, rejected FROM jsonschema_import($1, 'people', 'doc', 'https://example.com/person', '{}'::jsonb, '{}'::jsonb)",
+            json!({"$id": "https://example.com/person", "$ref": "https://example.com/name"}),
+            json!({"$id": "https://example.com/name", "required": ["name"]}),
+        );
+        let counts = Spi::get_two_with_args::<i64, i64>(&query, &[source.as_str().into()])?;
+        assert_eq!((Some(3), Some(1)), counts, "{query}");
+
+        // Valid lines are inserted.
+        assert_eq!(
+            Spi::get_one::<i64>(r#"SELECT count(*) FROM people WHERE doc->>'name' = 'Kamala'"#)?,
+            Some(3)
+        );
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM people")?, Some(7));
+
+        // Invalid lines are rejected with their line numbers and errors.
+        let rejects: Vec<(i64, String, bool)> =
+            Spi::connect(|client| {
+                client
+                .select(
+                    r#"SELECT line, data, errors @? '$.errors[*] ? (@.instanceLocation == "/name")'
+                         FROM jsonschema_import_rejects
+                        WHERE relation = 'people'::regclass AND column_name = 'doc'
+                        ORDER BY id"#,
+                    None,
+                    &[],
+                )?
+                .map(|row| Ok((row.get(1)?.unwrap(), row.get(2)?.unwrap(), row.get(3)?.unwrap())))
+                .collect::<Result<Vec<_>, SpiError>>()
+            })?;
+        assert_eq!(
+            vec![
+                (2, r#"{"name": 42}"#.to_string(), true),
+                (3, r#"{"name": "#.to_string(), false),
+                (2, r#"{"name": 42}"#.to_string(), true),
+                (3, r#"{"name": "#.to_string(), false),
+                (3, r#"{"name": "#.to_string(), false),
+            ],
+            rejects,
+        );
+
+        // Lines that fail to insert are rejected without aborting the import.
+        Spi::run("ALTER TABLE people ADD CHECK (doc->>'name' <> 'Tim')")?;
+        let counts = Spi::get_two_with_args::<i64, i64>(
+            &format!("SELECT imported, rejected FROM jsonschema_import($1, 'people', 'doc', '{schema}'::jsonb)"),
+            &[source.as_str().into()],
+        )?;
+        assert_eq!((Some(1), Some(3)), counts);
+        assert_eq!(Spi::get_one::<i64>("SELECT count(*) FROM people")?, Some(8));
+        let (line, error) = Spi::get_two::<i64, String>(
+            "SELECT line, errors->'errors'->0->>'error'
+               FROM jsonschema_import_rejects ORDER BY id DESC LIMIT 1",
+        )?;
+        assert_eq!(Some(5), line);
+        assert!(
+            error.unwrap().contains("violates check constraint"),
+            "insert error recorded"
+        );
+
+        // The column must exist.
+        assert_error(
+            &format!("SELECT jsonschema_import('{{}}', 'people', 'nope', '{schema}'::jsonb)"),
+            PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            r#"column "nope" of relation "people" does not exist"#,
+        );
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_matching() -> spi::Result<()> {
+        let click = json!({"$id": "https://example.com/click", "required": ["x", "y"]});
+        let key = json!({"$id": "https://example.com/key", "required": ["key"]});
+        let any = json!({"$id": "https://example.com/any", "type": "object"});
+
+        for (data_type, schema_type) in [
+            ("json", "json"),
+            ("jsonb", "jsonb"),
+            ("json", "jsonb"),
+            ("jsonb", "json"),
+        ] {
+            for (data, all, first) in [
+                (
+                    json!({"x": 1, "y": 2}),
+                    vec!["https://example.com/click", "https://example.com/any"],
+                    Some("https://example.com/click"),
+                ),
+                (
+                    json!({"key": "a"}),
+                    vec!["https://example.com/key", "https://example.com/any"],
+                    Some("https://example.com/key"),
+                ),
+                (json!([]), vec![], None),
+            ] {
+                let args = format!(
+                    "'{data}'::{data_type}, '{click}'::{schema_type}, '{key}'::{schema_type}, '{any}'::{schema_type}"
+                );
+                let query = format!("SELECT array_agg(m) FROM jsonschema_matching({args}) m");
+                let got = Spi::get_one::<Vec<String>>(&query)?.unwrap_or_default();
+                assert_eq!(all, got, "{query}");
+
+                let query = format!("SELECT jsonschema_first_match({args})");
+                let got = Spi::get_one::<String>(&query)?;
+                assert_eq!(first.map(String::from), got, "{query}");
+            }
+        }
+
+        // Invalid data matches nothing.
+        assert_eq!(
+            None,
+            Spi::get_one::<String>(&format!(
+                "SELECT jsonschema_first_match('{{'::json, '{any}'::json)"
+            ))?,
+        );
+
+        // Schemas without $id get default IDs.
+        assert_eq!(
+            Spi::get_one::<Vec<String>>(
+                r#"SELECT array_agg(m) FROM jsonschema_matching('"x"'::jsonb, '{"type": "string"}'::jsonb, '{"minLength": 1}'::jsonb) m"#
+            )?,
+            Some(vec![
+                "file:///schema.json".to_string(),
+                "file:///schema.json2".to_string(),
+            ]),
+        );
+
+        // Invalid schemas raise an error.
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run(r#"SELECT jsonschema_first_match('{}'::jsonb, '{"type": "nope"}'::jsonb)"#)?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_INTERNAL_ERROR, |_| {
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True));
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_validates_self() -> spi::Result<()> {
+        for version in ["v1", "v2"] {
+            let schema = json!({
+                "$id": format!("https://example.com/event/{version}"),
+                "type": "object",
+                "required": [version],
+            });
+            Spi::run(&format!("SELECT jsonschema_register('{schema}')"))?;
+        }
+
+        for data_type in ["json", "jsonb"] {
+            for (name, data, property, valid) in [
+                (
+                    "v1",
+                    json!({"$schema": "https://example.com/event/v1", "v1": 1}),
+                    None,
+                    true,
+                ),
+                (
+                    "v2",
+                    json!({"$schema": "https://example.com/event/v2", "v2": 1}),
+                    None,
+                    true,
+                ),
+                (
+                    "wrong version",
+                    json!({"$schema": "https://example.com/event/v2", "v1": 1}),
+                    None,
+                    false,
+                ),
+                (
+                    "fragment",
+                    json!({"$schema": "https://example.com/event/v1#", "v1": 1}),
+                    None,
+                    true,
+                ),
+                ("missing", json!({"v1": 1}), None, false),
+                ("not string", json!({"$schema": 1, "v1": 1}), None, false),
+                ("not object", json!([]), None, false),
+                (
+                    "property",
+                    json!({"schemaVersion": "https://example.com/event/v1", "v1": 1}),
+                    Some("schemaVersion"),
+                    true,
+                ),
+                (
+                    "property missing",
+                    json!({"$schema": "https://example.com/event/v1", "v1": 1}),
+                    Some("schemaVersion"),
+                    false,
+                ),
+            ] {
+                let query = match property {
+                    None => format!("SELECT jsonschema_validates_self('{data}'::{data_type})"),
+                    Some(p) => {
+                        format!("SELECT jsonschema_validates_self('{data}'::{data_type}, '{p}')")
+                    }
+                };
+                assert_eq!(
+                    Some(valid),
+                    Spi::get_one::<bool>(&query)?,
+                    "{name}: {query}"
+                );
+            }
+
+            assert_error(
+                &format!(
+                    r#"SELECT jsonschema_validates_self('{{"$schema": "https://example.com/event/v3"}}'::{data_type})"#
+                ),
+                PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+                r#"no schema registered for "https://example.com/event/v3""#,
+            );
+        }
+
+        // Use in a check constraint.
+        Spi::run("CREATE TABLE events (payload jsonb CHECK (jsonschema_validates_self(payload)))")?;
+        Spi::run(
+            r#"INSERT INTO events VALUES
+               ('{"$schema": "https://example.com/event/v1", "v1": 1}'),
+               ('{"$schema": "https://example.com/event/v2", "v2": 1}')"#,
+        )?;
+        assert_error(
+            r#"INSERT INTO events VALUES ('{"$schema": "https://example.com/event/v1"}')"#,
+            PgSqlErrorCode::ERRCODE_CHECK_VIOLATION,
+            r#"new row for relation "events" violates check constraint "events_payload_check""#,
+        );
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_subjects() -> spi::Result<()> {
+        let register = |subject: &str, schema: Value| {
+            Spi::get_one::<i32>(&format!(
+                "SELECT jsonschema_register_version('{subject}', '{schema}')"
+            ))
+        };
+
+        // Subjects default to BACKWARD compatibility.
+        let v1 = json!({"type": "object", "properties": {"id": {"type": "integer"}}, "required": ["id"]});
+        let v2 =
+            json!({"type": "object", "properties": {"id": {"type": "number"}}, "required": ["id"]});
+        assert_eq!(Some(1), register("orders", v1.clone())?);
+        assert_eq!(Some(2), register("orders", v2.clone())?);
+        assert_eq!(Some(2), register("orders", v2)?, "same as latest");
+        assert_error(
+            &format!(
+                "SELECT jsonschema_register_version('orders', '{}')",
+                json!({"type": "object", "properties": {"id": {"type": "number"}}, "required": ["id", "total"]}),
+            ),
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"schema is not BACKWARD compatible with version 2 of subject "orders""#,
+        );
+        assert_error(
+            r#"SELECT jsonschema_register_version('orders', '{"type": "nope"}')"#,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"invalid schema for subject "orders": file:///schema.json# is not valid against metaschema: jsonschema validation failed with https://json-schema.org/draft/2020-12/schema#
+- at '/type': anyOf failed
+  - at '/type': value must be one of 'array', 'boolean', 'integer', 'null', 'number', 'object', 'string'
+  - at '/type': want array, but got string"#,
+        );
+
+        // FORWARD compatibility requires earlier versions to accept new data.
+        assert_eq!(
+            Some("FORWARD".to_string()),
+            Spi::get_one("SELECT jsonschema_set_compatibility('orders', 'forward')")?,
+        );
+        let v3 = json!({"type": "object", "properties": {"id": {"type": "integer"}}, "required": ["id"]});
+        assert_eq!(Some(3), register("orders", v3)?);
+        assert_error(
+            &format!(
+                "SELECT jsonschema_register_version('orders', '{}')",
+                json!({"type": "object"})
+            ),
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"schema is not FORWARD compatible with version 3 of subject "orders""#,
+        );
+
+        // Transitive levels check all earlier versions.
+        Spi::run("SELECT jsonschema_set_compatibility('events', 'BACKWARD_TRANSITIVE')")?;
+        assert_eq!(Some(1), register("events", json!({"type": "string"}))?);
+        Spi::run("SELECT jsonschema_set_compatibility('events', 'NONE')")?;
+        assert_eq!(Some(2), register("events", json!({"type": "integer"}))?);
+        Spi::run("SELECT jsonschema_set_compatibility('events', 'BACKWARD')")?;
+        assert_eq!(
+            Some(3),
+            register("events", json!({"type": ["integer", "null"]}))?
+        );
+        Spi::run("SELECT jsonschema_set_compatibility('events', 'BACKWARD_TRANSITIVE')")?;
+        assert_error(
+            &format!(
+                "SELECT jsonschema_register_version('events', '{}')",
+                json!({"type": ["integer", "null", "boolean"]}),
+            ),
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"schema is not BACKWARD_TRANSITIVE compatible with version 1 of subject "events""#,
+        );
+        assert_error(
+            "SELECT jsonschema_set_compatibility('events', 'sideways')",
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"invalid compatibility level "sideways": must be NONE, BACKWARD, FORWARD, or FULL, optionally with a _TRANSITIVE suffix"#,
+        );
+
+        // Validate against the latest or a specific version.
+        for data_type in ["json", "jsonb"] {
+            for (data, version, valid) in [
+                (json!({"id": 1}), None, true),
+                (json!({"id": 1.5}), None, false),
+                (json!({"id": 1.5}), Some(1), false),
+                (json!({"id": 1.5}), Some(2), true),
+                (json!({}), Some(2), false),
+            ] {
+                let query = match version {
+                    None => format!(
+                        "SELECT jsonschema_validates_subject('{data}'::{data_type}, 'orders')"
+                    ),
+                    Some(v) => format!(
+                        "SELECT jsonschema_validates_subject('{data}'::{data_type}, 'orders', {v})"
+                    ),
+                };
+                assert_eq!(Some(valid), Spi::get_one::<bool>(&query)?, "{query}");
+            }
+            assert_error(
+                &format!("SELECT jsonschema_validates_subject('{{}}'::{data_type}, 'orders', 9)"),
+                PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+                r#"subject "orders" has no version 9"#,
+            );
+            assert_error(
+                &format!("SELECT jsonschema_validates_subject('{{}}'::{data_type}, 'nope')"),
+                PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+                r#"subject "nope" has no versions"#,
+            );
+        }
+
+        // Versions are immutable.
+        assert_error(
+            "UPDATE jsonschema_subject_versions SET schema = '{}' WHERE subject = 'orders' AND version = 1",
+            PgSqlErrorCode::ERRCODE_RESTRICT_VIOLATION,
+            r#"cannot update version 1 of subject "orders": schema versions are immutable"#,
+        );
+        assert_error(
+            "DELETE FROM jsonschema_subject_versions WHERE subject = 'orders'",
+            PgSqlErrorCode::ERRCODE_RESTRICT_VIOLATION,
+            r#"cannot delete version 1 of subject "orders": schema versions are immutable"#,
+        );
+        assert_eq!(
+            Some(v1),
+            Spi::get_one::<JsonB>(
+                "SELECT schema FROM jsonschema_subject_versions WHERE subject = 'orders' AND version = 1"
+            )?
+            .map(|s| s.0),
+        );
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_validates_pinned() -> spi::Result<()> {
+        let user = json!({
+            "$id": "https://example.com/user",
+            "type": "object",
+            "required": ["name"],
+        });
+        Spi::run(&format!("SELECT jsonschema_register('{user}')"))?;
+        let pinned = Spi::get_one::<String>("SELECT jsonschema_pin('https://example.com/user')")?
+            .unwrap_or_default();
+        let digest = Spi::get_one::<String>(&format!(
+            "SELECT encode(sha256(convert_to('{user}'::jsonb::text, 'UTF8')), 'hex')"
+        ))?
+        .unwrap_or_default();
+        assert_eq!(format!("https://example.com/user@sha256:{digest}"), pinned);
+
+        for data_type in ["json", "jsonb"] {
+            for (data, valid) in [(json!({"name": "Kamala"}), true), (json!({}), false)] {
+                let query = format!(
+                    "SELECT jsonschema_validates_pinned('{data}'::{data_type}, '{pinned}')"
+                );
+                assert_eq!(Some(valid), Spi::get_one::<bool>(&query)?, "{query}");
+            }
+        }
+
+        // Usable in CHECK constraints, but STABLE, so not in indexes.
+        Spi::run(&format!(
+            "CREATE TABLE users (doc jsonb CHECK (jsonschema_validates_pinned(doc, '{pinned}')))"
+        ))?;
+        assert_error(
+            &format!(
+                "CREATE INDEX users_valid ON users ((jsonschema_validates_pinned(doc, '{pinned}')))"
+            ),
+            PgSqlErrorCode::ERRCODE_INVALID_OBJECT_DEFINITION,
+            "functions in index expression must be marked IMMUTABLE",
+        );
+        Spi::run(r#"INSERT INTO users VALUES ('{"name": "Tim"}')"#)?;
+        assert_error(
+            "INSERT INTO users VALUES ('{}')",
+            PgSqlErrorCode::ERRCODE_CHECK_VIOLATION,
+            r#"new row for relation "users" violates check constraint "users_doc_check""#,
+        );
+
+        // Changing the registered schema breaks the pin.
+        Spi::run(&format!(
+            "SELECT jsonschema_register('{}')",
+            json!({"$id": "https://example.com/user", "type": "object"}),
+        ))?;
+        assert_error(
+            &format!(
+                r#"SELECT jsonschema_validates_pinned('{{"name": "Tim"}}'::jsonb, '{pinned}')"#
+            ),
+            PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            &format!(r#"no schema found for pinned reference "{pinned}""#),
+        );
+
+        // Subject versions are immutable, so pins to them remain valid.
+        Spi::run(&format!(
+            "SELECT jsonschema_register_version('people', '{user}')"
+        ))?;
+        Spi::run(&format!(
+            "SELECT jsonschema_register_version('people', '{}')",
+            json!({"$id": "https://example.com/user", "type": "object", "required": ["name"], "title": "User"}),
+        ))?;
+        let pinned =
+            Spi::get_one::<String>("SELECT jsonschema_pin('people', 1)")?.unwrap_or_default();
+        assert_eq!(format!("people@sha256:{digest}"), pinned);
+        assert_eq!(
+            Some(false),
+            Spi::get_one::<bool>(&format!(
+                "SELECT jsonschema_validates_pinned('{{}}'::jsonb, '{pinned}')"
+            ))?,
+        );
+        assert_eq!(
+            Some(true),
+            Spi::get_one::<bool>(&format!(
+                r#"SELECT jsonschema_validates_pinned('{{"name": "Tim"}}'::jsonb, 'https://example.com/user@sha256:{digest}')"#
+            ))?,
+        );
+
+        // Errors.
+        assert_error(
+            "SELECT jsonschema_validates_pinned('{}'::jsonb, 'https://example.com/user')",
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"invalid pinned schema reference "https://example.com/user": must be "id@sha256:" followed by a hex SHA-256 digest"#,
+        );
+        assert_error(
+            "SELECT jsonschema_pin('https://example.com/nope')",
+            PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            r#"no schema registered for "https://example.com/nope""#,
+        );
+        assert_error(
+            "SELECT jsonschema_pin('people', 3)",
+            PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            r#"subject "people" has no version 3"#,
+        );
+
+        // Schemas that reference other schemas cannot be pinned.
+        Spi::run(&format!(
+            "SELECT jsonschema_register('{}')",
+            json!({"$id": "https://example.com/address", "type": "object"}),
+        ))?;
+        Spi::run(&format!(
+            "SELECT jsonschema_register('{}')",
+            json!({"$id": "https://example.com/person", "$ref": "https://example.com/address"}),
+        ))?;
+        let res: Result<ErrorCaught, SpiError> = PgTryBuilder::new(|| {
+            Spi::run("SELECT jsonschema_pin('https://example.com/person')")?;
+            Ok(ErrorCaught::False)
+        })
+        .catch_when(PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE, |e| {
+            if let PostgresError(e) = e {
+                assert!(e
+                    .message()
+                    .starts_with(r#"cannot pin "https://example.com/person": "#));
+            }
+            Ok(ErrorCaught::True)
+        })
+        .catch_others(|e| e.rethrow())
+        .execute();
+        assert_eq!(res, Ok(ErrorCaught::True));
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_validates_draft() -> spi::Result<()> {
+        // A boolean exclusiveMinimum is valid only in draft 4.
+        let schema = json!({"minimum": 1, "exclusiveMinimum": true});
+        for schema_type in ["json", "jsonb"] {
+            for (draft, valid) in [("V4", true), ("v4", true), ("V7", false), ("V2020", false)] {
+                let query =
+                    format!("SELECT jsonschema_is_valid('{schema}'::{schema_type}, '{draft}')");
+                assert_eq!(Some(valid), Spi::get_one::<bool>(&query)?, "{query}");
+            }
+
+            for data_type in ["json", "jsonb"] {
+                for (data, valid) in [(1, false), (2, true)] {
+                    let query = format!(
+                        "SELECT jsonschema_validates('{data}'::{data_type}, '{schema}'::{schema_type}, 'V4')"
+                    );
+                    assert_eq!(Some(valid), Spi::get_one::<bool>(&query)?, "{query}");
+                }
+            }
+        }
+
+        // Independent of jsonschema.default_draft and
+        // jsonschema.reject_duplicate_keys.
+        Spi::run("SET LOCAL jsonschema.default_draft TO 'V4'")?;
+        Spi::run("SET LOCAL jsonschema.reject_duplicate_keys TO on")?;
+        assert_eq!(
+            Some(true),
+            Spi::get_one::<bool>(&format!("SELECT jsonschema_is_valid('{schema}'::jsonb)"))?,
+        );
+        assert_eq!(
+            Some(false),
+            Spi::get_one::<bool>(&format!(
+                "SELECT jsonschema_is_valid('{schema}'::jsonb, 'V2020')"
+            ))?,
+        );
+        assert_eq!(
+            Some(true),
+            Spi::get_one::<bool>(
+                r#"SELECT jsonschema_validates('{"a": 1, "a": 2}'::json, '{"type": "object"}'::jsonb, 'V2020')"#
+            )?,
+        );
+
+        // Only the draft-pinned functions are immutable.
+        for (query, volatility) in [
+            ("jsonschema_validates(jsonb, jsonb, text)", "i"),
+            ("jsonschema_is_valid(jsonb, text)", "i"),
+            ("jsonschema_validates(jsonb, jsonb)", "s"),
+            ("jsonschema_is_valid(jsonb)", "s"),
+            ("jsonschema_valid(jsonb, jsonb)", "s"),
+        ] {
+            assert_eq!(
+                Some(volatility.to_string()),
+                Spi::get_one::<String>(&format!(
+                    "SELECT provolatile::text FROM pg_proc WHERE oid = '{query}'::regprocedure"
+                ))?,
+                "{query}",
+            );
+        }
+        Spi::run("CREATE TABLE docs (doc jsonb)")?;
+        Spi::run(
+            r#"CREATE INDEX docs_valid ON docs ((jsonschema_validates(doc, '{"type": "object"}'::jsonb, 'V2020')))"#,
+        )?;
+        assert_error(
+            r#"CREATE INDEX docs_invalid ON docs ((jsonschema_validates(doc, '{"type": "object"}'::jsonb)))"#,
+            PgSqlErrorCode::ERRCODE_INVALID_OBJECT_DEFINITION,
+            "functions in index expression must be marked IMMUTABLE",
+        );
+
+        // Invalid draft.
+        assert_error(
+            r#"SELECT jsonschema_validates('{}'::jsonb, '{}'::jsonb, '2020-12')"#,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"invalid draft "2020-12": must be V4, V6, V7, V2019, or V2020"#,
+        );
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_draft_of() -> spi::Result<()> {
+        for schema_type in ["json", "jsonb"] {
+            for (schema, draft) in [
+                (json!({"type": "object"}), "V2020"),
+                (
+                    json!({"$schema": "http://json-schema.org/draft-04/schema#"}),
+                    "V4",
+                ),
+                (
+                    json!({"$schema": "http://json-schema.org/draft-06/schema"}),
+                    "V6",
+                ),
+                (
+                    json!({"$schema": "http://json-schema.org/draft-07/schema#"}),
+                    "V7",
+                ),
+                (
+                    json!({"$schema": "https://json-schema.org/draft/2019-09/schema"}),
+                    "V2019",
+                ),
+                (
+                    json!({"$schema": "https://json-schema.org/draft/2020-12/schema"}),
+                    "V2020",
+                ),
+                (json!(true), "V2020"),
+            ] {
+                let query = format!("SELECT jsonschema_draft_of('{schema}'::{schema_type})::text");
+                assert_eq!(Some(draft.to_string()), Spi::get_one(&query)?, "{query}");
+            }
+            assert_error(
+                &format!(r#"SELECT jsonschema_draft_of('{{"$schema": "lol"}}'::{schema_type})"#),
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                r#"unsupported draft "lol""#,
+            );
+        }
+
+        // Schemas without $schema resolve to jsonschema.default_draft.
+        Spi::run("SET LOCAL jsonschema.default_draft TO 'V7'")?;
+        assert_eq!(
+            Some(true),
+            Spi::get_one::<bool>("SELECT jsonschema_draft_of('{}'::jsonb) = 'V7'")?,
+        );
+
+        // Each meta-schema identifies its draft and is valid.
+        assert_eq!(
+            Some(5),
+            Spi::get_one::<i64>(
+                "SELECT count(*) FROM unnest(enum_range(NULL::jsonschema_draft)) d
+                  WHERE jsonschema_draft_of(jsonschema_meta_schema(d)) = d
+                    AND jsonschema_is_valid(jsonschema_meta_schema(d))",
+            )?,
+        );
+        assert_eq!(
+            Some("http://json-schema.org/draft-07/schema#".to_string()),
+            Spi::get_one("SELECT jsonschema_meta_schema('V7')->>'$id'")?,
+        );
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_dialects() -> spi::Result<()> {
+        // Register a vocabulary meta-schema and a dialect that requires it.
+        let vocab = json!({
+            "$id": "https://example.com/meta/sku",
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$vocabulary": {"https://example.com/vocab/sku": true},
+            "$dynamicAnchor": "meta",
+            "properties": {"x-sku": {"type": "string"}},
+        });
+        let dialect = json!({
+            "$id": "https://example.com/dialect/sku",
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$vocabulary": {
+                "https://json-schema.org/draft/2020-12/vocab/core": true,
+                "https://json-schema.org/draft/2020-12/vocab/applicator": true,
+                "https://json-schema.org/draft/2020-12/vocab/unevaluated": true,
+                "https://json-schema.org/draft/2020-12/vocab/validation": true,
+                "https://json-schema.org/draft/2020-12/vocab/meta-data": true,
+                "https://json-schema.org/draft/2020-12/vocab/format-annotation": true,
+                "https://json-schema.org/draft/2020-12/vocab/content": true,
+                "https://example.com/vocab/sku": true,
+            },
+            "$dynamicAnchor": "meta",
+            "allOf": [
+                {"$ref": "https://json-schema.org/draft/2020-12/schema"},
+                {"$ref": "https://example.com/meta/sku"},
+            ],
+        });
+        for meta in [&vocab, &dialect] {
+            assert_eq!(
+                meta["$id"].as_str().map(str::to_string),
+                Spi::get_one_with_args::<String>(
+                    "SELECT jsonschema_register_dialect($1)",
+                    &[JsonB(meta.clone()).into()],
+                )?,
+            );
+        }
+
+        // Schemas may now name the dialect in $schema, and resolve to its draft.
+        let schema = json!({
+            "$schema": "https://example.com/dialect/sku",
+            "type": "object",
+            "x-sku": "widget",
+            "properties": {"qty": {"type": "integer", "x-sku": "qty"}},
+        });
+        assert_eq!(
+            Some("V2020".to_string()),
+            Spi::get_one(&format!(
+                "SELECT jsonschema_draft_of('{schema}'::jsonb)::text"
+            ))?,
+        );
+        assert_eq!(
+            Some(true),
+            Spi::get_one(&format!("SELECT jsonschema_is_valid('{schema}'::jsonb)"))?,
+        );
+        assert_eq!(
+            Some(true),
+            Spi::get_one(&format!(
+                r#"SELECT jsonschema_validates('{{"qty": 3}}'::jsonb, '{schema}'::jsonb)"#
+            ))?,
+        );
+        assert_eq!(
+            Some(false),
+            Spi::get_one(&format!(
+                r#"SELECT jsonschema_validates('{{"qty": "3"}}'::jsonb, '{schema}'::jsonb)"#
+            ))?,
+        );
+
+        // The dialect meta-schema applies to schemas that name it.
+        let bad = json!({"$schema": "https://example.com/dialect/sku", "x-sku": 42});
+        assert_eq!(
+            Some(false),
+            Spi::get_one(&format!("SELECT jsonschema_is_valid('{bad}'::jsonb)"))?,
+        );
+
+        // Invalid dialects.
+        for (meta, message) in [
+            (
+                json!({"$schema": "https://json-schema.org/draft/2020-12/schema"}),
+                r#"cannot register a dialect without an "$id""#,
+            ),
+            (
+                json!({"$id": "http://json-schema.org/draft-07/schema#"}),
+                r#"cannot register dialect "http://json-schema.org/draft-07/schema#": it is a supported draft"#,
+            ),
+            (
+                json!({"$id": "https://example.com/nope", "$schema": "https://example.com/lol"}),
+                r#"invalid dialect "https://example.com/nope": "$schema" must name a supported draft or another registered dialect"#,
+            ),
+            (
+                json!({"$id": "https://example.com/nope", "$schema": "https://example.com/nope"}),
+                r#"invalid dialect "https://example.com/nope": "$schema" must name a supported draft or another registered dialect"#,
+            ),
+        ] {
+            assert_error(
+                &format!("SELECT jsonschema_register_dialect('{meta}')"),
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                message,
+            );
+        }
+
+        // Unregistering the dialect makes it unsupported again.
+        assert_eq!(
+            Some(true),
+            Spi::get_one(
+                "SELECT jsonschema_unregister_dialect('https://example.com/dialect/sku')"
+            )?,
+        );
+        assert_eq!(
+            Some(false),
+            Spi::get_one(
+                "SELECT jsonschema_unregister_dialect('https://example.com/dialect/sku')"
+            )?,
+        );
+        assert_error(
+            &format!("SELECT jsonschema_draft_of('{schema}'::jsonb)"),
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"unsupported draft "https://example.com/dialect/sku""#,
+        );
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_jsonschema_keywords() -> spi::Result<()> {
+        Spi::run("CREATE TABLE skus (sku text PRIMARY KEY)")?;
+        Spi::run("INSERT INTO skus VALUES ('W-1'), ('W-2')")?;
+        Spi::run(
+            "CREATE FUNCTION sku_exists(kw jsonb, val jsonb) RETURNS boolean
+             LANGUAGE sql AS $$
+                 SELECT jsonb_typeof(val) <> 'string' OR EXISTS (SELECT FROM skus WHERE sku = val #>> '{}')
+             $$",
+        )?;
+        Spi::run(
+            "CREATE FUNCTION date_order(kw jsonb, obj jsonb) RETURNS text
+             LANGUAGE sql AS $$
+                 SELECT format('%s must precede %s', kw->>0, kw->>1)
+                  WHERE (obj->>(kw->>0))::date >= (obj->>(kw->>1))::date
+             $$",
+        )?;
+        Spi::run("SELECT jsonschema_register_keyword('x-sku', 'sku_exists(jsonb, jsonb)')")?;
+        Spi::run("SELECT jsonschema_register_keyword('x-date-order', 'date_order(jsonb, jsonb)')")?;
+
+        let schema = json!({
+            "type": "object",
+            "x-date-order": ["start", "end"],
+            "properties": {"sku": {"type": "string", "x-sku": true}},
+        });
+        for (data, valid) in [
+            (
+                json!({"sku": "W-1", "start": "2024-01-01", "end": "2024-02-01"}),
+                true,
+            ),
+            (
+                json!({"sku": "W-3", "start": "2024-01-01", "end": "2024-02-01"}),
+                false,
+            ),
+            (
+                json!({"sku": "W-2", "start": "2024-03-01", "end": "2024-02-01"}),
+                false,
+            ),
+            (json!({"start": "2024-01-01", "end": "2024-01-02"}), true),
+        ] {
+            let query = format!("SELECT jsonschema_validates('{data}'::jsonb, '{schema}'::jsonb)");
+            assert_eq!(Some(valid), Spi::get_one(&query)?, "{query}");
+        }
+
+        // Errors report the keyword and instance locations.
+        Spi::run("CREATE TABLE orders (doc jsonb)")?;
+        Spi::run(&format!(
+            r#"SELECT jsonschema_import(
+                '{{"sku": "W-3", "start": "2024-03-01", "end": "2024-02-01"}}',
+                'orders', 'doc', '{schema}'::jsonb
+            )"#
+        ))?;
+        assert_eq!(
+            Some(json!([
+                {"keywordLocation": "/x-date-order", "instanceLocation": "", "error": "start must precede end"},
+                {"keywordLocation": "/properties/sku/x-sku", "instanceLocation": "/sku", "error": r#"does not satisfy "x-sku""#},
+            ])),
+            Spi::get_one::<JsonB>(
+                "SELECT jsonb_agg(e - 'absoluteKeywordLocation' ORDER BY e->>'keywordLocation' DESC)
+                   FROM jsonschema_import_rejects, jsonb_array_elements(errors->'errors') e
+                  WHERE e ? 'keywordLocation' AND e->>'keywordLocation' LIKE '%x-%'",
+            )?
+            .map(|v| v.0),
+        );
+
+        // Invalid functions.
+        for (function, message) in [
+            (
+                "length(text)",
+                r#"invalid function for keyword "x-bad": length(text) must take (jsonb, jsonb) and return boolean or text"#,
+            ),
+            (
+                "jsonb_concat(jsonb, jsonb)",
+                r#"invalid function for keyword "x-bad": jsonb_concat(jsonb, jsonb) must take (jsonb, jsonb) and return boolean or text"#,
+            ),
+        ] {
+            assert_error(
+                &format!("SELECT jsonschema_register_keyword('x-bad', '{function}')"),
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                message,
+            );
+        }
+        assert_error(
+            "SELECT jsonschema_register_keyword('', 'sku_exists(jsonb, jsonb)')",
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            "cannot register an empty keyword",
+        );
+
+        // Unregistered keywords are annotations again.
+        assert_eq!(
+            Some(true),
+            Spi::get_one("SELECT jsonschema_unregister_keyword('x-sku')")?,
+        );
+        assert_eq!(
+            Some(false),
+            Spi::get_one("SELECT jsonschema_unregister_keyword('x-sku')")?,
+        );
+        assert_eq!(
+            Some(true),
+            Spi::get_one(&format!(
+                r#"SELECT jsonschema_validates('{{"sku": "W-3"}}'::jsonb, '{schema}'::jsonb)"#
+            ))?,
+        );
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_x_pg_references() -> spi::Result<()> {
+        Spi::run("CREATE TABLE customers (id text PRIMARY KEY)")?;
+        Spi::run("CREATE TABLE products (id int PRIMARY KEY)")?;
+        Spi::run("INSERT INTO customers VALUES ('c-1'), ('c-2')")?;
+        Spi::run("INSERT INTO products SELECT generate_series(1, 5)")?;
+
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "customer_id": {
+                    "type": "string",
+                    "x-pg-references": {"table": "public.customers", "column": "id"},
+                },
+                "product_ids": {
+                    "type": "array",
+                    "items": {"x-pg-references": {"table": "products", "column": "id"}},
+                },
+            },
+        });
+        for (data, valid) in [
+            (
+                json!({"customer_id": "c-1", "product_ids": [1, 2, 5]}),
+                true,
+            ),
+            (json!({"customer_id": "c-2", "product_ids": []}), true),
+            (json!({"product_ids": [3, null, {}]}), true),
+            (json!({"customer_id": "c-3", "product_ids": [1]}), false),
+            (json!({"customer_id": "c-1", "product_ids": [1, 6]}), false),
+            (json!({"product_ids": ["2", 3]}), true),
+            (json!({"product_ids": ["abc"]}), false),
+            (json!({"product_ids": [1.5]}), false),
+            (json!({"product_ids": [true]}), false),
+        ] {
+            for data_type in ["json", "jsonb"] {
+                let query = format!(
+                    "SELECT jsonschema_validates('{data}'::{data_type}, '{schema}'::{data_type})"
+                );
+                assert_eq!(Some(valid), Spi::get_one(&query)?, "{query}");
+            }
+        }
+
+        // A document makes one lookup per table column, however many values
+        // it references.
+        for data in [
+            json!({"customer_id": "c-1", "product_ids": [1, 2, 3, 4]}),
+            json!({"customer_id": "c-3", "product_ids": [1, 6, 2, 7]}),
+        ] {
+            reference::LOOKUPS.set(0);
+            Spi::get_one::<bool>(&format!(
+                "SELECT jsonschema_validates('{data}'::jsonb, '{schema}'::jsonb)"
+            ))?;
+            assert_eq!(2, reference::LOOKUPS.get(), "{data}");
+        }
+
+        // Errors report the JSON pointer to each missing value.
+        Spi::run("CREATE TABLE orders (doc jsonb)")?;
+        Spi::run(&format!(
+            r#"SELECT jsonschema_import(
+                '{{"customer_id": "c-9", "product_ids": [1, 7, 2, 8]}}',
+                'orders', 'doc', '{schema}'::jsonb
+            )"#
+        ))?;
+        assert_eq!(
+            Some(json!([
+                {"instanceLocation": "/customer_id", "error": r#"key (id)=(c-9) is not present in table "public.customers""#},
+                {"instanceLocation": "/product_ids/1", "error": r#"key (id)=(7) is not present in table "products""#},
+                {"instanceLocation": "/product_ids/3", "error": r#"key (id)=(8) is not present in table "products""#},
+            ])),
+            Spi::get_one::<JsonB>(
+                "SELECT jsonb_agg(
+                            jsonb_build_object('instanceLocation', e->'instanceLocation', 'error', e->'error')
+                            ORDER BY e->>'instanceLocation'
+                        )
+                   FROM jsonschema_import_rejects, jsonb_array_elements(errors->'errors') e
+                  WHERE e->>'keywordLocation' LIKE '%/x-pg-references'",
+            )?
+            .map(|v| v.0),
+        );
+
+        // Invalid keyword values fail to compile.
+        let schema = json!({"x-pg-references": {"table": "customers"}});
+        assert_error(
+            &format!(r#"SELECT jsonschema_validates('"c-1"'::jsonb, '{schema}'::jsonb)"#),
+            PgSqlErrorCode::ERRCODE_INTERNAL_ERROR,
+            &format!(
+                "{:#}",
+                compile(DEFAULT_URL, &[schema.clone()]).err().unwrap()
+            ),
+        );
+        assert_eq!(
+            Some(false),
+            Spi::get_one::<bool>(&format!("SELECT jsonschema_is_valid('{schema}'::jsonb)"))?,
+        );
+
+        // Invalid targets.
+        for (target, code, message) in [
+            (
+                json!({"table": "customers", "column": "nope"}),
+                PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+                r#"column "nope" of relation "customers" does not exist"#,
+            ),
+            (
+                json!({"table": "nope", "column": "id"}),
+                PgSqlErrorCode::ERRCODE_UNDEFINED_TABLE,
+                r#"relation "nope" does not exist"#,
+            ),
+        ] {
+            assert_error(
+                &format!(
+                    r#"SELECT jsonschema_validates('"c-1"'::jsonb, '{}'::jsonb)"#,
+                    json!({"x-pg-references": target}),
+                ),
+                code,
+                message,
+            );
+        }
+        assert_error(
+            "SELECT jsonschema_register_keyword('x-pg-references', 'jsonb_exists(jsonb, text)')",
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"cannot register keyword "x-pg-references": it is built in"#,
+        );
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_data_references() -> spi::Result<()> {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "maxPrice": {"type": "number", "minimum": {"$data": "1/minPrice"}},
+                "confirmation": {"const": {"$data": "/password"}},
+                "items": {"maxItems": {"$data": "1/limit"}},
+            },
+        });
+        let cases = [
+            (json!({"minPrice": 5, "maxPrice": 10}), true),
+            (json!({"minPrice": 10, "maxPrice": 5}), false),
+            (json!({"maxPrice": 5}), true),
+            (json!({"password": "pw", "confirmation": "pw"}), true),
+            (json!({"password": "pw", "confirmation": "px"}), false),
+            (json!({"limit": 2, "items": [1, 2]}), true),
+            (json!({"limit": 1, "items": [1, 2]}), false),
+        ];
+
+        // Disabled by default, so the schema is invalid.
+        assert_eq!(
+            Some(false),
+            Spi::get_one(&format!("SELECT jsonschema_is_valid('{schema}'::jsonb)"))?,
+        );
+
+        // Enable per call.
+        for (data, valid) in &cases {
+            for data_type in ["json", "jsonb"] {
+                let query = format!(
+                    "SELECT jsonschema_validates('{data}'::{data_type}, '{schema}'::{data_type}, data_refs => true)"
+                );
+                assert_eq!(Some(*valid), Spi::get_one(&query)?, "{query}");
+            }
+        }
+
+        // Enable by configuration.
+        Spi::run("SET LOCAL jsonschema.data_references TO on")?;
+        assert_eq!(
+            Some(true),
+            Spi::get_one(&format!("SELECT jsonschema_is_valid('{schema}'::jsonb)"))?,
+        );
+        for (data, valid) in &cases {
+            let query = format!("SELECT jsonschema_validates('{data}'::jsonb, '{schema}'::jsonb)");
+            assert_eq!(Some(*valid), Spi::get_one(&query)?, "{query}");
+        }
+
+        // The per-call argument overrides the configuration.
+        assert_error(
+            &format!(
+                r#"SELECT jsonschema_validates('{{}}'::jsonb, '{schema}'::jsonb, data_refs => false)"#
+            ),
+            PgSqlErrorCode::ERRCODE_INTERNAL_ERROR,
+            &format!(
+                "{:#}",
+                data_ref::with_enabled(false, || compile(DEFAULT_URL, &[schema.clone()]))
+                    .err()
+                    .expect("schema compiled without $data references")
+            ),
+        );
+
+        // The configuration applies again after the error.
+        assert_eq!(
+            Some(true),
+            Spi::get_one(&format!("SELECT jsonschema_is_valid('{schema}'::jsonb)"))?,
+        );
+
+        // Errors report the locations of the original keywords.
+        Spi::run("CREATE TABLE prices (doc jsonb)")?;
+        Spi::run(&format!(
+            r#"SELECT jsonschema_import(
+                '{{"minPrice": 10, "maxPrice": 5, "password": "pw", "confirmation": "px"}}',
+                'prices', 'doc', '{schema}'::jsonb
+            )"#
+        ))?;
+        assert_eq!(
+            Some(json!([
+                {"keywordLocation": "/properties/maxPrice/minimum", "instanceLocation": "/maxPrice", "error": "must be >= 10, but got 5"},
+                {"keywordLocation": "/properties/confirmation/const", "instanceLocation": "/confirmation", "error": r#"value must be "pw""#},
+            ])),
+            Spi::get_one::<JsonB>(
+                "SELECT jsonb_agg(e - 'absoluteKeywordLocation' ORDER BY e->>'instanceLocation' DESC)
+                   FROM jsonschema_import_rejects, jsonb_array_elements(errors->'errors') e
+                  WHERE e->>'keywordLocation' ~ '/(minimum|const)$'",
+            )?
+            .map(|v| v.0),
+        );
+
+        Ok(())
+    }
+
+    #[pg_test]
+    fn test_error_message() -> spi::Result<()> {
+        let schema = json!({
+            "$id": "https://example.com/person",
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": {"type": "string", "errorMessage": "name must be text"},
+                "age": {
+                    "type": "integer",
+                    "minimum": 18,
+                    "errorMessage": {"minimum": "${1/name} must be at least 18, not ${0}"},
+                },
+            },
+            "errorMessage": {"required": "a person needs a name"},
+        });
+        for (data, valid) in [
+            (json!({"name": "Kamala", "age": 59}), true),
+            (json!({"name": "Tim", "age": 16}), false),
+            (json!({"age": 20}), false),
+            (json!({"name": 42}), false),
+        ] {
+            let query = format!("SELECT jsonschema_validates('{data}'::jsonb, '{schema}'::jsonb)");
+            assert_eq!(Some(valid), Spi::get_one(&query)?, "{query}");
+        }
+
+        // Structured errors report the custom messages at the original
+        // keyword locations.
+        let source = [
+            r#"{"name": "Tim", "age": 16}"#,
+            r#"{"age": 20}"#,
+            r#"{"name": 42, "age": "old"}"#,
+        ]
+        .join("\n");
+        Spi::run("CREATE TABLE people (doc jsonb)")?;
+        Spi::run_with_args(
+            &format!("SELECT jsonschema_import($1, 'people', 'doc', '{schema}'::jsonb)"),
+            &[source.as_str().into()],
+        )?;
+        assert_eq!(
+            Some(json!([
+                {"keywordLocation": "/properties/age/minimum", "instanceLocation": "/age", "error": "Tim must be at least 18, not 16"},
+                {"keywordLocation": "/required", "instanceLocation": "", "error": "a person needs a name"},
+                {"keywordLocation": "/properties/name/type", "instanceLocation": "/name", "error": "name must be text"},
+            ])),
+            Spi::get_one::<JsonB>(
+                "SELECT jsonb_agg(e - 'absoluteKeywordLocation' ORDER BY r.line)
+                   FROM jsonschema_import_rejects r, jsonb_array_elements(r.errors->'errors') e
+                  WHERE e->>'keywordLocation' ~ '/(minimum|required|name/type)$'",
+            )?
+            .map(|v| v.0),
+        );
+
+        // Keywords without custom messages keep their default messages.
+        assert_eq!(
+            Some(true),
+            Spi::get_one::<bool>(
+                r#"SELECT errors @? '$.errors[*] ? (@.keywordLocation == "/properties/age/type" && @.error starts with "want integer")'
+                     FROM jsonschema_import_rejects WHERE line = 3"#,
+            )?,
+        );
+
+        // As do error descriptions.
+        Spi::run("CREATE TABLE members (id int PRIMARY KEY, doc jsonb)")?;
+        Spi::run(&format!(
+            "CREATE TRIGGER validate_members BEFORE INSERT ON members
+             FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger('column=doc', 'schema={schema}')"
+        ))?;
+        assert_check_violation(
+            r#"INSERT INTO members VALUES (1, '{"name": false}')"#,
+            r#"new row for relation "members" violates jsonschema for column "doc""#,
+            "Failing row has (id)=(1).\n\
+            jsonschema validation failed with https://example.com/person#\n\
+            - at '/name': name must be text",
+        );
+
+        // Invalid messages fail to compile.
+        let schema = json!({"errorMessage": {"type": 1}});
+        assert_error(
+            &format!("SELECT jsonschema_validates('1'::jsonb, '{schema}'::jsonb)"),
+            PgSqlErrorCode::ERRCODE_INTERNAL_ERROR,
+            &format!(
+                "{:#}",
+                compile(DEFAULT_URL, &[schema.clone()]).err().unwrap()
+            ),
+        );
+        assert_eq!(
+            Some(false),
+            Spi::get_one::<bool>(&format!("SELECT jsonschema_is_valid('{schema}'::jsonb)"))?,
+        );
+        assert_error(
+            "SELECT jsonschema_register_keyword('errorMessage', 'jsonb_exists(jsonb, text)')",
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"cannot register keyword "errorMessage": it is built in"#,
+        );
 
         Ok(())
     }
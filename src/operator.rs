@@ -0,0 +1,406 @@
+//! Validation operators.
+//!
+//! The `@~` operator returns true if the JSON document on its left validates
+//! against the JSON Schema on its right, and `!@~` returns true if it does not.
+//! Their commutators, `~@` and `!~@`, take the schema on the left. Each
+//! operator supports the `json` and `jsonb` combinations of document and
+//! schema supported by `jsonschema_validates()`.
+//!
+//! Unlike `jsonschema_validates()`, the operators do not log validation
+//! errors, which would otherwise be reported for every row a predicate
+//! rejects and for every value the planner evaluates to estimate
+//! selectivity. An invalid schema still raises an error.
+
+use pgrx::prelude::*;
+use pgrx::{Json, JsonB, JsonString};
+use serde_json::Value;
+
+use crate::{parse_data, single, validate_instance, with_compiled};
+
+/// valid returns true if `data` validates against `schema` and false if it
+/// does not or if `data` failed to parse. Raises an error if `schema` is
+/// invalid. `arg` is the position of the schema argument in `fcinfo`, so
+/// that a constant schema compiles only once per call site.
+fn valid(
+    fcinfo: pg_sys::FunctionCallInfo,
+    arg: i32,
+    data: Result<Value, String>,
+    schema: Value,
+) -> bool {
+    let Ok(data) = data else {
+        return false;
+    };
+    match with_compiled(
+        fcinfo,
+        &[arg],
+        || single(schema),
+        |schemas, index| validate_instance(schemas, &data, index).is_ok(),
+    ) {
+        Err(e) => error!("{e:#}"),
+        Ok(ok) => ok,
+    }
+}
+
+// Document validation operator functions.
+
+// jsonschema_valid(data::json,  schema::json)
+// jsonschema_valid(data::jsonb, schema::jsonb)
+// jsonschema_valid(data::json,  schema::jsonb)
+// jsonschema_valid(data::jsonb, schema::json)
+
+/// json_valid_json implements `json @~ json`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn json_valid_json(data: JsonString, schema: Json, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    valid(fcinfo, 1, parse_data(&data.0), schema.0)
+}
+
+/// jsonb_valid_jsonb implements `jsonb @~ jsonb`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn jsonb_valid_jsonb(data: JsonB, schema: JsonB, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    valid(fcinfo, 1, Ok(data.0), schema.0)
+}
+
+/// json_valid_jsonb implements `json @~ jsonb`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn json_valid_jsonb(data: JsonString, schema: JsonB, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    valid(fcinfo, 1, parse_data(&data.0), schema.0)
+}
+
+/// jsonb_valid_json implements `jsonb @~ json`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn jsonb_valid_json(data: JsonB, schema: Json, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    valid(fcinfo, 1, Ok(data.0), schema.0)
+}
+
+// jsonschema_invalid(data::json,  schema::json)
+// jsonschema_invalid(data::jsonb, schema::jsonb)
+// jsonschema_invalid(data::json,  schema::jsonb)
+// jsonschema_invalid(data::jsonb, schema::json)
+
+/// json_invalid_json implements `json !@~ json`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn json_invalid_json(data: JsonString, schema: Json, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    !valid(fcinfo, 1, parse_data(&data.0), schema.0)
+}
+
+/// jsonb_invalid_jsonb implements `jsonb !@~ jsonb`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn jsonb_invalid_jsonb(data: JsonB, schema: JsonB, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    !valid(fcinfo, 1, Ok(data.0), schema.0)
+}
+
+/// json_invalid_jsonb implements `json !@~ jsonb`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn json_invalid_jsonb(data: JsonString, schema: JsonB, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    !valid(fcinfo, 1, parse_data(&data.0), schema.0)
+}
+
+/// jsonb_invalid_json implements `jsonb !@~ json`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn jsonb_invalid_json(data: JsonB, schema: Json, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    !valid(fcinfo, 1, Ok(data.0), schema.0)
+}
+
+// Commutator functions.
+
+// jsonschema_accepts(schema::json,  data::json)
+// jsonschema_accepts(schema::jsonb, data::jsonb)
+// jsonschema_accepts(schema::jsonb, data::json)
+// jsonschema_accepts(schema::json,  data::jsonb)
+
+/// json_accepts_json implements `json ~@ json`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn json_accepts_json(schema: Json, data: JsonString, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    valid(fcinfo, 0, parse_data(&data.0), schema.0)
+}
+
+/// jsonb_accepts_jsonb implements `jsonb ~@ jsonb`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn jsonb_accepts_jsonb(schema: JsonB, data: JsonB, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    valid(fcinfo, 0, Ok(data.0), schema.0)
+}
+
+/// jsonb_accepts_json implements `jsonb ~@ json`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn jsonb_accepts_json(schema: JsonB, data: JsonString, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    valid(fcinfo, 0, parse_data(&data.0), schema.0)
+}
+
+/// json_accepts_jsonb implements `json ~@ jsonb`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn json_accepts_jsonb(schema: Json, data: JsonB, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    valid(fcinfo, 0, Ok(data.0), schema.0)
+}
+
+// jsonschema_rejects(schema::json,  data::json)
+// jsonschema_rejects(schema::jsonb, data::jsonb)
+// jsonschema_rejects(schema::jsonb, data::json)
+// jsonschema_rejects(schema::json,  data::jsonb)
+
+/// json_rejects_json implements `json !~@ json`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn json_rejects_json(schema: Json, data: JsonString, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    !valid(fcinfo, 0, parse_data(&data.0), schema.0)
+}
+
+/// jsonb_rejects_jsonb implements `jsonb !~@ jsonb`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn jsonb_rejects_jsonb(schema: JsonB, data: JsonB, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    !valid(fcinfo, 0, Ok(data.0), schema.0)
+}
+
+/// jsonb_rejects_json implements `jsonb !~@ json`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn jsonb_rejects_json(schema: JsonB, data: JsonString, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    !valid(fcinfo, 0, parse_data(&data.0), schema.0)
+}
+
+/// json_rejects_jsonb implements `json !~@ jsonb`.
+#[pg_extern(stable, strict, parallel_safe, sql = false)]
+fn json_rejects_jsonb(schema: Json, data: JsonB, fcinfo: pg_sys::FunctionCallInfo) -> bool {
+    !valid(fcinfo, 0, Ok(data.0), schema.0)
+}
+
+// The operators use the generic matchingsel and matchingjoinsel estimators,
+// which evaluate the operator against the column's most common values and
+// histogram to estimate the fraction of rows it matches.
+extension_sql!(
+    r#"
+-- jsonschema_valid returns true if data validates against schema. Implements
+-- the @~ operator.
+CREATE FUNCTION jsonschema_valid(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_valid_json_wrapper';
+
+CREATE FUNCTION jsonschema_valid(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_valid_jsonb_wrapper';
+
+CREATE FUNCTION jsonschema_valid(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_valid_jsonb_wrapper';
+
+CREATE FUNCTION jsonschema_valid(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_valid_json_wrapper';
+
+-- jsonschema_invalid returns true if data does not validate against schema.
+-- Implements the !@~ operator.
+CREATE FUNCTION jsonschema_invalid(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_invalid_json_wrapper';
+
+CREATE FUNCTION jsonschema_invalid(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_invalid_jsonb_wrapper';
+
+CREATE FUNCTION jsonschema_invalid(
+	"data" json, /* pgrx::datum::json::JsonString */
+	"schema" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_invalid_jsonb_wrapper';
+
+CREATE FUNCTION jsonschema_invalid(
+	"data" jsonb, /* pgrx::datum::json::JsonB */
+	"schema" json /* pgrx::datum::json::Json */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_invalid_json_wrapper';
+
+-- jsonschema_accepts returns true if data validates against schema.
+-- Implements the ~@ operator.
+CREATE FUNCTION jsonschema_accepts(
+	"schema" json, /* pgrx::datum::json::Json */
+	"data" json /* pgrx::datum::json::JsonString */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_accepts_json_wrapper';
+
+CREATE FUNCTION jsonschema_accepts(
+	"schema" jsonb, /* pgrx::datum::json::JsonB */
+	"data" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_accepts_jsonb_wrapper';
+
+CREATE FUNCTION jsonschema_accepts(
+	"schema" jsonb, /* pgrx::datum::json::JsonB */
+	"data" json /* pgrx::datum::json::JsonString */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_accepts_json_wrapper';
+
+CREATE FUNCTION jsonschema_accepts(
+	"schema" json, /* pgrx::datum::json::Json */
+	"data" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_accepts_jsonb_wrapper';
+
+-- jsonschema_rejects returns true if data does not validate against schema.
+-- Implements the !~@ operator.
+CREATE FUNCTION jsonschema_rejects(
+	"schema" json, /* pgrx::datum::json::Json */
+	"data" json /* pgrx::datum::json::JsonString */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_rejects_json_wrapper';
+
+CREATE FUNCTION jsonschema_rejects(
+	"schema" jsonb, /* pgrx::datum::json::JsonB */
+	"data" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_rejects_jsonb_wrapper';
+
+CREATE FUNCTION jsonschema_rejects(
+	"schema" jsonb, /* pgrx::datum::json::JsonB */
+	"data" json /* pgrx::datum::json::JsonString */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'jsonb_rejects_json_wrapper';
+
+CREATE FUNCTION jsonschema_rejects(
+	"schema" json, /* pgrx::datum::json::Json */
+	"data" jsonb /* pgrx::datum::json::JsonB */
+) RETURNS bool /* bool */
+STABLE STRICT PARALLEL SAFE
+LANGUAGE c /* Rust */
+AS 'MODULE_PATHNAME', 'json_rejects_jsonb_wrapper';
+
+-- data @~ schema: data validates against schema.
+CREATE OPERATOR @~ (
+	LEFTARG = json, RIGHTARG = json, FUNCTION = jsonschema_valid,
+	COMMUTATOR = ~@, NEGATOR = !@~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR @~ (
+	LEFTARG = jsonb, RIGHTARG = jsonb, FUNCTION = jsonschema_valid,
+	COMMUTATOR = ~@, NEGATOR = !@~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR @~ (
+	LEFTARG = json, RIGHTARG = jsonb, FUNCTION = jsonschema_valid,
+	COMMUTATOR = ~@, NEGATOR = !@~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR @~ (
+	LEFTARG = jsonb, RIGHTARG = json, FUNCTION = jsonschema_valid,
+	COMMUTATOR = ~@, NEGATOR = !@~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+-- data !@~ schema: data does not validate against schema.
+CREATE OPERATOR !@~ (
+	LEFTARG = json, RIGHTARG = json, FUNCTION = jsonschema_invalid,
+	COMMUTATOR = !~@, NEGATOR = @~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR !@~ (
+	LEFTARG = jsonb, RIGHTARG = jsonb, FUNCTION = jsonschema_invalid,
+	COMMUTATOR = !~@, NEGATOR = @~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR !@~ (
+	LEFTARG = json, RIGHTARG = jsonb, FUNCTION = jsonschema_invalid,
+	COMMUTATOR = !~@, NEGATOR = @~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR !@~ (
+	LEFTARG = jsonb, RIGHTARG = json, FUNCTION = jsonschema_invalid,
+	COMMUTATOR = !~@, NEGATOR = @~,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+-- schema ~@ data: schema accepts data.
+CREATE OPERATOR ~@ (
+	LEFTARG = json, RIGHTARG = json, FUNCTION = jsonschema_accepts,
+	COMMUTATOR = @~, NEGATOR = !~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR ~@ (
+	LEFTARG = jsonb, RIGHTARG = jsonb, FUNCTION = jsonschema_accepts,
+	COMMUTATOR = @~, NEGATOR = !~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR ~@ (
+	LEFTARG = jsonb, RIGHTARG = json, FUNCTION = jsonschema_accepts,
+	COMMUTATOR = @~, NEGATOR = !~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR ~@ (
+	LEFTARG = json, RIGHTARG = jsonb, FUNCTION = jsonschema_accepts,
+	COMMUTATOR = @~, NEGATOR = !~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+-- schema !~@ data: schema rejects data.
+CREATE OPERATOR !~@ (
+	LEFTARG = json, RIGHTARG = json, FUNCTION = jsonschema_rejects,
+	COMMUTATOR = !@~, NEGATOR = ~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR !~@ (
+	LEFTARG = jsonb, RIGHTARG = jsonb, FUNCTION = jsonschema_rejects,
+	COMMUTATOR = !@~, NEGATOR = ~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR !~@ (
+	LEFTARG = jsonb, RIGHTARG = json, FUNCTION = jsonschema_rejects,
+	COMMUTATOR = !@~, NEGATOR = ~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+
+CREATE OPERATOR !~@ (
+	LEFTARG = json, RIGHTARG = jsonb, FUNCTION = jsonschema_rejects,
+	COMMUTATOR = !@~, NEGATOR = ~@,
+	RESTRICT = matchingsel, JOIN = matchingjoinsel
+);
+"#,
+    name = "jsonschema_operators",
+);
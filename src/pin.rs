@@ -0,0 +1,247 @@
+//! Pinned schema references: an `$id` or subject and a content digest.
+
+use std::rc::Rc;
+
+use boon::{CompileError, Compiler, SchemaIndex, Schemas};
+use pgrx::datum::DatumWithOid;
+use pgrx::prelude::*;
+use pgrx::{JsonB, JsonString};
+use serde_json::Value;
+
+use crate::{extension_table, StatementCache, DEFAULT_URL};
+
+// The separator between the ID and the digest of a pinned reference.
+const PIN: &str = "@sha256:";
+
+/// jsonschema_pin returns a pinned reference to the schema registered as `id`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_pin")]
+fn jsonschema_pin_id(id: &str) -> String {
+    let digest = Spi::get_one_with_args::<String>(
+        &format!(
+            "SELECT encode(sha256(convert_to(schema::text, 'UTF8')), 'hex') FROM {} WHERE id = $1",
+            extension_table("jsonschema_registry"),
+        ),
+        &[id.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    let Some(digest) = digest else {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            format!(r#"no schema registered for "{id}""#),
+        );
+    };
+    check_pinnable(
+        id,
+        &format!(
+            "SELECT schema FROM {} WHERE id = $1",
+            extension_table("jsonschema_registry"),
+        ),
+        &[id.into()],
+    );
+    format!("{id}{PIN}{digest}")
+}
+
+/// jsonschema_pin_version returns a pinned reference to `version` of `subject`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_pin")]
+fn jsonschema_pin_version(subject: &str, version: i32) -> String {
+    let digest = Spi::get_one_with_args::<String>(
+        &format!(
+            "SELECT encode(sha256(convert_to(schema::text, 'UTF8')), 'hex') FROM {}
+              WHERE subject = $1 AND version = $2",
+            extension_table("jsonschema_subject_versions"),
+        ),
+        &[subject.into(), version.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    let Some(digest) = digest else {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            format!(r#"subject "{subject}" has no version {version}"#),
+        );
+    };
+    check_pinnable(
+        subject,
+        &format!(
+            "SELECT schema FROM {} WHERE subject = $1 AND version = $2",
+            extension_table("jsonschema_subject_versions"),
+        ),
+        &[subject.into(), version.into()],
+    );
+    format!("{subject}{PIN}{digest}")
+}
+
+/// check_pinnable raises an error if the schema to pin does not compile alone.
+fn check_pinnable(name: &str, query: &str, args: &[DatumWithOid]) {
+    let schema = Spi::get_one_with_args::<JsonB>(query, args)
+        .unwrap_or_else(|e| error!("{e}"))
+        .map_or(Value::Null, |s| s.0);
+    if let Err(e) = compile(&schema) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(r#"cannot pin "{name}": {e:#}"#),
+            "Pinned schemas compile on their own, so they cannot reference other schemas.",
+        );
+    }
+}
+
+// jsonschema_validates_pinned(data::json,  pinned::text)
+// jsonschema_validates_pinned(data::jsonb, pinned::text)
+
+/// json_schema_validates_pinned validates `data` against `pinned`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_pinned")]
+fn json_schema_validates_pinned(data: JsonString, pinned: &str) -> bool {
+    validates_pinned(crate::parse(&data.0), pinned)
+}
+
+/// jsonb_schema_validates_pinned validates `data` against `pinned`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_pinned")]
+fn jsonb_schema_validates_pinned(data: JsonB, pinned: &str) -> bool {
+    validates_pinned(Ok(data.0), pinned)
+}
+
+/// validates_pinned validates `data` against `pinned`, logging any errors.
+fn validates_pinned(data: Result<Value, String>, pinned: &str) -> bool {
+    let data = match data {
+        Err(e) => {
+            info!("{e}");
+            return false;
+        }
+        Ok(v) => v,
+    };
+    let c = compiled(pinned);
+    // Validate with boon alone rather than validate_instance(), so that the
+    // result depends only on the pinned content.
+    if let Err(e) = c.schemas.validate(&data, c.index) {
+        info!("{e}");
+        return false;
+    }
+    true
+}
+
+/// parse splits `pinned` into its ID and lowercase hex SHA-256 digest.
+fn parse(pinned: &str) -> Result<(&str, String), String> {
+    match pinned.rsplit_once(PIN) {
+        Some((id, digest))
+            if !id.is_empty()
+                && digest.len() == 64
+                && digest.chars().all(|c| c.is_ascii_hexdigit()) =>
+        {
+            Ok((id, digest.to_ascii_lowercase()))
+        }
+        _ => Err(format!(
+            r#"invalid pinned schema reference "{pinned}": must be "id{PIN}" followed by a hex SHA-256 digest"#
+        )),
+    }
+}
+
+/// Compiled holds a compiled pinned schema.
+struct Compiled {
+    schemas: Schemas,
+    index: SchemaIndex,
+}
+
+thread_local! {
+    // Compiled schemas by pinned reference, reused for the rest of the
+    // statement.
+    static CACHE: StatementCache<String, Compiled> = StatementCache::new();
+}
+
+/// compiled returns the schema pinned by `pinned`, compiled once per statement.
+fn compiled(pinned: &str) -> Rc<Compiled> {
+    CACHE.with(|cache| cache.get(pinned.to_string(), || load(pinned)))
+}
+
+/// load looks up and compiles the schema pinned by `pinned`.
+fn load(pinned: &str) -> Compiled {
+    let (id, digest) = parse(pinned)
+        .unwrap_or_else(|e| ereport!(ERROR, PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE, e));
+    let schema = Spi::get_one_with_args::<JsonB>(
+        &format!(
+            "SELECT schema FROM (
+                 SELECT schema FROM {} WHERE id = $1
+                 UNION ALL
+                 SELECT schema FROM {} WHERE subject = $1 OR schema->>'$id' = $1
+             ) s
+             WHERE encode(sha256(convert_to(schema::text, 'UTF8')), 'hex') = $2
+             LIMIT 1",
+            extension_table("jsonschema_registry"),
+            extension_table("jsonschema_subject_versions"),
+        ),
+        &[id.into(), digest.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    let Some(schema) = schema else {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            format!(r#"no schema found for pinned reference "{pinned}""#),
+        );
+    };
+    let (schemas, index) = compile(&schema.0).unwrap_or_else(|e| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(r#"invalid schema for pinned reference "{pinned}": {e:#}"#),
+        )
+    });
+
+    Compiled { schemas, index }
+}
+
+/// compile compiles `schema` alone with the default draft of `boon`.
+fn compile(schema: &Value) -> Result<(Schemas, SchemaIndex), CompileError> {
+    let id = schema["$id"].as_str().unwrap_or(DEFAULT_URL);
+    let mut compiler = Compiler::new();
+    // Use an empty loader to prevent boon from accessing the file system.
+    compiler.use_loader(Box::new(boon::SchemeUrlLoader::new()));
+    compiler.add_resource(id, schema.to_owned())?;
+    let mut schemas = Schemas::new();
+    let index = compiler.compile(id, &mut schemas)?;
+    Ok((schemas, index))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let digest = "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08";
+        let lower = digest.to_ascii_lowercase();
+        for (name, pinned, res) in [
+            (
+                "id",
+                format!("https://example.com/user@sha256:{lower}"),
+                Ok(("https://example.com/user", lower.clone())),
+            ),
+            (
+                "uppercase",
+                format!("orders@sha256:{digest}"),
+                Ok(("orders", lower.clone())),
+            ),
+            (
+                "at sign",
+                format!("https://me@example.com/user@sha256:{lower}"),
+                Ok(("https://me@example.com/user", lower.clone())),
+            ),
+            ("no id", format!("@sha256:{lower}"), Err(())),
+            ("no digest", "orders".to_string(), Err(())),
+            ("short", "orders@sha256:9f86d0".to_string(), Err(())),
+            (
+                "not hex",
+                format!("orders@sha256:{}", "z".repeat(64)),
+                Err(()),
+            ),
+            ("other hash", format!("orders@sha512:{lower}"), Err(())),
+        ] {
+            assert_eq!(res, parse(&pinned).map_err(|_| ()), "{name}");
+        }
+        assert_eq!(
+            Err(r#"invalid pinned schema reference "orders": must be "id@sha256:" followed by a hex SHA-256 digest"#.to_string()),
+            parse("orders"),
+        );
+    }
+}
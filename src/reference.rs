@@ -0,0 +1,252 @@
+//! The `x-pg-references` keyword.
+//!
+//! `x-pg-references` is a built-in custom keyword that checks that a value
+//! exists in a table column, like a foreign key, as in
+//! `"x-pg-references": {"table": "public.customers", "column": "id"}`. It
+//! applies to strings, numbers, and booleans, whose text representations it
+//! compares to the column cast to text, so that values the column's type
+//! cannot represent are not present rather than errors. To look up every
+//! value in a document at once, the keyword pass walks the document twice:
+//! first collecting the values for each table column in `References` and
+//! treating them as present, then, after one lookup per table column,
+//! checking them.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use pgrx::prelude::*;
+use serde_json::Value;
+
+/// The name of the keyword.
+pub(crate) const KEYWORD: &str = "x-pg-references";
+
+/// Target is the table column referenced by an `x-pg-references` keyword.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Target {
+    table: String,
+    column: String,
+}
+
+impl Target {
+    /// parse parses the value of an `x-pg-references` keyword.
+    pub(crate) fn parse(value: &Value) -> Result<Self, String> {
+        if let Value::Object(obj) = value {
+            if let (Some(Value::String(table)), Some(Value::String(column)), 2) =
+                (obj.get("table"), obj.get("column"), obj.len())
+            {
+                return Ok(Target {
+                    table: table.to_string(),
+                    column: column.to_string(),
+                });
+            }
+        }
+        Err(format!(
+            r#"invalid "{KEYWORD}" {value}: must be an object with only "table" and "column" strings"#
+        ))
+    }
+
+    /// check returns an error message if `instance` is a string, number, or
+    /// boolean not present in the target table column.
+    pub(crate) fn check(&self, instance: &Value, refs: &mut References) -> Result<(), String> {
+        let Some(key) = key(instance) else {
+            return Ok(());
+        };
+        let known = match refs {
+            References::Collect(keys) => {
+                keys.entry(self.clone()).or_default().insert(key.clone());
+                Some(true)
+            }
+            References::Check(found) => found.get(self).and_then(|f| f.get(&key).copied()),
+        };
+        // Look up keys not collected in the first pass on their own.
+        if known.unwrap_or_else(|| !self.lookup(vec![key.clone()]).is_empty()) {
+            return Ok(());
+        }
+        Err(format!(
+            r#"key ({})=({key}) is not present in table "{}""#,
+            self.column, self.table
+        ))
+    }
+
+    /// lookup returns the members of `keys` present in the target table
+    /// column, compared as text. Raises an error if the table or column does
+    /// not exist.
+    fn lookup(&self, keys: Vec<String>) -> HashSet<String> {
+        #[cfg(any(test, feature = "pg_test"))]
+        LOOKUPS.set(LOOKUPS.get() + 1);
+        let query = Spi::get_one_with_args::<String>(
+            "SELECT pg_catalog.format(
+                        'SELECT k FROM pg_catalog.unnest($1::pg_catalog.text[]) k
+                          WHERE EXISTS (SELECT FROM %s WHERE %I::pg_catalog.text = k)',
+                        a.attrelid::pg_catalog.regclass, a.attname
+                    )
+               FROM pg_catalog.pg_attribute a
+              WHERE a.attrelid = $1::pg_catalog.regclass
+                AND a.attname = $2
+                AND a.attnum > 0
+                AND NOT a.attisdropped",
+            &[self.table.as_str().into(), self.column.as_str().into()],
+        )
+        .unwrap_or_else(|e| error!("{e}"));
+        let Some(query) = query else {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+                format!(
+                    r#"column "{}" of relation "{}" does not exist"#,
+                    self.column, self.table
+                ),
+            );
+        };
+
+        Spi::connect(|client| {
+            client
+                .select(&query, None, &[keys.into()])?
+                .map(|row| row.get::<String>(1))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .unwrap_or_else(|e| error!("{e}"))
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+/// key returns the text representation of `instance` to look up in a table
+/// column, or `None` if it's not a string, number, or boolean.
+fn key(instance: &Value) -> Option<String> {
+    match instance {
+        Value::String(s) => Some(s.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// References is the state of the `x-pg-references` keywords in a walk of a
+/// document.
+pub(crate) enum References {
+    /// Collect the keys for each target and treat them as present.
+    Collect(HashMap<Target, BTreeSet<String>>),
+    /// Check the keys for each target against the results of the lookups.
+    Check(HashMap<Target, HashMap<String, bool>>),
+}
+
+impl References {
+    /// new returns `References` to collect keys.
+    pub(crate) fn new() -> Self {
+        References::Collect(HashMap::new())
+    }
+
+    /// lookup looks up the collected keys for each target table column at
+    /// once and switches to checking them. Returns false if there are no keys
+    /// to look up.
+    pub(crate) fn lookup(&mut self) -> bool {
+        let References::Collect(keys) = self else {
+            return false;
+        };
+        if keys.is_empty() {
+            return false;
+        }
+        let found = std::mem::take(keys)
+            .into_iter()
+            .map(|(target, keys)| {
+                let keys: Vec<_> = keys.into_iter().collect();
+                let present = target.lookup(keys.clone());
+                let found = keys
+                    .into_iter()
+                    .map(|k| {
+                        let exists = present.contains(&k);
+                        (k, exists)
+                    })
+                    .collect();
+                (target, found)
+            })
+            .collect();
+        *self = References::Check(found);
+        true
+    }
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+thread_local! {
+    // The number of table lookups, for tests.
+    pub(crate) static LOOKUPS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Ok(Target {
+                table: "public.customers".to_string(),
+                column: "id".to_string(),
+            }),
+            Target::parse(&json!({"table": "public.customers", "column": "id"})),
+        );
+        for value in [
+            json!("customers"),
+            json!({"table": "customers"}),
+            json!({"table": "customers", "column": 1}),
+            json!({"table": "customers", "column": "id", "on": "x"}),
+        ] {
+            assert_eq!(
+                Err(format!(
+                    r#"invalid "x-pg-references" {value}: must be an object with only "table" and "column" strings"#
+                )),
+                Target::parse(&value),
+            );
+        }
+    }
+
+    #[test]
+    fn test_key() {
+        assert_eq!(Some("c-42".to_string()), key(&json!("c-42")));
+        assert_eq!(Some("42".to_string()), key(&json!(42)));
+        assert_eq!(Some("4.2".to_string()), key(&json!(4.2)));
+        assert_eq!(Some("true".to_string()), key(&json!(true)));
+        assert_eq!(None, key(&json!(null)));
+        assert_eq!(None, key(&json!([1])));
+        assert_eq!(None, key(&json!({"id": 1})));
+    }
+
+    #[test]
+    fn test_references() {
+        let target = Target {
+            table: "customers".to_string(),
+            column: "id".to_string(),
+        };
+        // The first pass treats keys as present and collects them.
+        let mut refs = References::new();
+        assert_eq!(Ok(()), target.check(&json!("c-1"), &mut refs));
+        assert_eq!(Ok(()), target.check(&json!(2), &mut refs));
+        assert_eq!(Ok(()), target.check(&json!(null), &mut refs));
+        let References::Collect(keys) = &refs else {
+            panic!("not collecting");
+        };
+        assert_eq!(
+            &HashMap::from([(
+                target.clone(),
+                BTreeSet::from(["2".to_string(), "c-1".to_string()])
+            )]),
+            keys,
+        );
+
+        // The second pass checks them against the results of the lookups.
+        let mut refs = References::Check(HashMap::from([(
+            target.clone(),
+            HashMap::from([("c-1".to_string(), true), ("2".to_string(), false)]),
+        )]));
+        assert_eq!(Ok(()), target.check(&json!("c-1"), &mut refs));
+        assert_eq!(
+            Err(r#"key (id)=(2) is not present in table "customers""#.to_string()),
+            target.check(&json!(2), &mut refs),
+        );
+
+        // No keys, no lookups.
+        assert!(!References::new().lookup());
+    }
+}
@@ -0,0 +1,274 @@
+//! Registry of JSON Schemas.
+//!
+//! The `jsonschema_registry` table stores schemas by their `$id`s. Each
+//! registered schema also has an integer key, a hash of its `$id` used as the
+//! type modifier of `jsonschema_doc` columns, so that type modifiers resolve
+//! without reading the table, as when `pg_restore` creates the columns before
+//! restoring its contents. Registered schemas may reference one another.
+//! `jsonschema_validates_self()` validates self-describing documents against
+//! the registered schemas they name.
+
+use pgrx::prelude::*;
+use pgrx::{JsonB, JsonString};
+use serde_json::Value;
+
+use crate::{compile, doc, extension_table, parse_data, validate_instance};
+
+extension_sql!(
+    r#"
+-- jsonschema_registry stores JSON Schemas by their "$id"s. The typmod column,
+-- a hash of the "$id", identifies the schema in jsonschema_doc type modifiers.
+CREATE TABLE jsonschema_registry (
+	id     TEXT    PRIMARY KEY,
+	typmod INTEGER NOT NULL UNIQUE,
+	schema JSONB   NOT NULL
+);
+
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_registry', '');
+"#,
+    name = "jsonschema_registry",
+);
+
+/// jsonschema_register registers `schema` under its `$id`, replacing any
+/// schema previously registered with the same `$id`, and returns the `$id`.
+/// The schema must compile, so any schemas it references must already be
+/// registered. Raises an error if it would replace a different schema that a
+/// `jsonschema_doc` column or domain uses, or if its `$id` hashes to the type
+/// modifier of another registered schema.
+#[pg_extern(volatile, strict)]
+fn jsonschema_register(schema: JsonB) -> String {
+    let Value::String(id) = &schema.0["$id"] else {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            r#"cannot register a schema without an "$id""#,
+        );
+    };
+    let id = id.to_string();
+
+    let mut schemas = vec![schema.0.clone()];
+    schemas.extend(
+        registered()
+            .into_iter()
+            .filter(|s| s["$id"].as_str() != Some(&id)),
+    );
+    if let Err(e) = compile(&id, &schemas) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(r#"invalid schema "{id}": {e:#}"#),
+        );
+    }
+
+    let typmod = typmod(&id);
+    if let Some(other) = id_for_typmod(typmod).filter(|other| *other != id) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNIQUE_VIOLATION,
+            format!(
+                r#"cannot register schema "{id}" because its type modifier conflicts with schema "{other}""#
+            ),
+        );
+    }
+    if registered_schema(&id).is_some_and(|s| s != schema.0) {
+        if let Some(user) = user_of(typmod) {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_DEPENDENT_OBJECTS_STILL_EXIST,
+                format!(r#"cannot replace schema "{id}" because {user} uses it"#),
+            );
+        }
+    }
+
+    Spi::run_with_args(
+        &format!(
+            "INSERT INTO {} (id, typmod, schema) VALUES ($1, $2, $3)
+             ON CONFLICT (id) DO UPDATE SET schema = EXCLUDED.schema",
+            extension_table("jsonschema_registry"),
+        ),
+        &[id.as_str().into(), typmod.into(), schema.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    id
+}
+
+/// jsonschema_unregister removes the schema registered under `id`, returning
+/// true if it was registered and false if not. Raises an error if a column
+/// or domain uses the schema as a `jsonschema_doc` type modifier.
+#[pg_extern(volatile, strict)]
+fn jsonschema_unregister(id: &str) -> bool {
+    let Some(typmod) = typmod_for(id) else {
+        return false;
+    };
+
+    if let Some(user) = user_of(typmod) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_DEPENDENT_OBJECTS_STILL_EXIST,
+            format!(r#"cannot unregister schema "{id}" because {user} uses it"#),
+        );
+    }
+
+    Spi::run_with_args(
+        &format!(
+            "DELETE FROM {} WHERE id = $1",
+            extension_table("jsonschema_registry")
+        ),
+        &[id.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    true
+}
+
+/// json_schema_validates_self validates `data` against the registered schema
+/// named by its `property` property.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_self")]
+fn json_schema_validates_self(data: JsonString, property: default!(&str, "'$schema'")) -> bool {
+    match parse_data(&data.0) {
+        Err(e) => {
+            info!("{e}");
+            false
+        }
+        Ok(data) => validates_self(&data, property),
+    }
+}
+
+/// jsonb_schema_validates_self validates `data` against the registered
+/// schema named by its `property` property.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_self")]
+fn jsonb_schema_validates_self(data: JsonB, property: default!(&str, "'$schema'")) -> bool {
+    validates_self(&data.0, property)
+}
+
+/// validates_self validates `data` against the registered schema whose `$id`
+/// is the string value of its `property` property. Logs the error at the
+/// `INFO` level and returns false if `data` has no such property. Raises an
+/// error if no schema is registered under the URI.
+fn validates_self(data: &Value, property: &str) -> bool {
+    let Some(uri) = data.get(property).and_then(Value::as_str) else {
+        info!(r#"jsonschema validation failed: missing "{property}" string property"#);
+        return false;
+    };
+    let Some(typmod) = typmod_for(uri).or_else(|| typmod_for(uri.strip_suffix('#')?)) else {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            format!(r#"no schema registered for "{uri}""#),
+        );
+    };
+
+    let c = doc::compiled(typmod);
+    if let Err(e) = validate_instance(&c.schemas, data, c.index) {
+        info!("{e}");
+        return false;
+    }
+    true
+}
+
+/// registered returns all of the registered schemas.
+pub(crate) fn registered() -> Vec<Value> {
+    Spi::connect(|client| {
+        client
+            .select(
+                &format!(
+                    "SELECT schema FROM {} ORDER BY id",
+                    extension_table("jsonschema_registry")
+                ),
+                None,
+                &[],
+            )?
+            .map(|row| row.get::<JsonB>(1))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .unwrap_or_else(|e| error!("{e}"))
+    .into_iter()
+    .flatten()
+    .map(|s| s.0)
+    .collect()
+}
+
+/// registered_schema returns the schema registered under `id`, or `None` if
+/// no such schema is registered.
+fn registered_schema(id: &str) -> Option<Value> {
+    Spi::get_one_with_args::<JsonB>(
+        &format!(
+            "SELECT schema FROM {} WHERE id = $1",
+            extension_table("jsonschema_registry")
+        ),
+        &[id.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"))
+    .map(|s| s.0)
+}
+
+/// user_of describes a column or domain that uses the schema with type
+/// modifier `typmod`, or returns `None` if none does.
+fn user_of(typmod: i32) -> Option<String> {
+    Spi::get_one_with_args::<String>(
+        "SELECT format('column %I of relation %s', a.attname, a.attrelid::regclass)
+           FROM pg_catalog.pg_attribute a
+           JOIN pg_catalog.pg_type t ON t.oid = a.atttypid
+          WHERE t.typname IN ('jsonschema_doc', '_jsonschema_doc')
+            AND a.atttypmod = $1
+            AND NOT a.attisdropped
+          UNION ALL
+         SELECT format('domain %s', d.oid::regtype)
+           FROM pg_catalog.pg_type d
+           JOIN pg_catalog.pg_type t ON t.oid = d.typbasetype
+          WHERE d.typtype = 'd'
+            AND t.typname IN ('jsonschema_doc', '_jsonschema_doc')
+            AND d.typtypmod = $1
+          LIMIT 1",
+        &[typmod.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"))
+}
+
+/// typmod returns the `jsonschema_doc` type modifier for the schema `$id`
+/// `id`: the 32-bit FNV-1a hash of `id` without its sign bit, since type
+/// modifiers must not be negative. It must never change, because Postgres
+/// stores type modifiers in its catalogs.
+pub(crate) fn typmod(id: &str) -> i32 {
+    let hash = id.bytes().fold(0x811c_9dc5_u32, |hash, b| {
+        (hash ^ u32::from(b)).wrapping_mul(0x0100_0193)
+    });
+    (hash & 0x7fff_ffff) as i32
+}
+
+/// typmod_for returns the type modifier for the schema registered under
+/// `id`, or `None` if no such schema is registered.
+pub(crate) fn typmod_for(id: &str) -> Option<i32> {
+    Spi::get_one_with_args(
+        &format!(
+            "SELECT typmod FROM {} WHERE id = $1",
+            extension_table("jsonschema_registry")
+        ),
+        &[id.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"))
+}
+
+/// id_for_typmod returns the `$id` of the schema with type modifier
+/// `typmod`, or `None` if no such schema is registered.
+pub(crate) fn id_for_typmod(typmod: i32) -> Option<String> {
+    Spi::get_one_with_args(
+        &format!(
+            "SELECT id FROM {} WHERE typmod = $1",
+            extension_table("jsonschema_registry")
+        ),
+        &[typmod.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_typmod() {
+        assert_eq!(18652613, typmod(""));
+        assert_eq!(1063606743, typmod("https://example.com/user"));
+        assert_eq!(784139796, typmod("https://example.com/address"));
+    }
+}
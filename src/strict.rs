@@ -0,0 +1,172 @@
+//! Strict parsing of JSON documents into `serde_json::Value`s.
+//!
+//! The `json` type preserves duplicate object keys, but `serde_json` silently
+//! keeps only the last value for each key. Parsing with this module produces
+//! the same `Value`, but also reports the [JSON Pointer] to the first
+//! duplicate key it finds, at any depth.
+//!
+//! [JSON Pointer]: https://www.rfc-editor.org/rfc/rfc6901
+
+use std::fmt;
+
+use serde::de::{DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Number, Value};
+
+/// parse parses `json` into a `serde_json::Value`, returning it along with
+/// the JSON Pointer to the first duplicate object key in `json`, if any.
+pub(crate) fn parse(json: &str) -> Result<(Value, Option<String>), serde_json::Error> {
+    let mut de = serde_json::Deserializer::from_str(json);
+    let mut state = State::default();
+    let value = Seed(&mut state).deserialize(&mut de)?;
+    de.end()?;
+    Ok((value, state.duplicate))
+}
+
+/// State tracks the path to the value being parsed and the pointer to the
+/// first duplicate key.
+#[derive(Default)]
+struct State {
+    path: Vec<String>,
+    duplicate: Option<String>,
+}
+
+impl State {
+    /// pointer returns the JSON Pointer for the current path.
+    fn pointer(&self) -> String {
+        self.path
+            .iter()
+            .map(|t| format!("/{}", t.replace('~', "~0").replace('/', "~1")))
+            .collect()
+    }
+}
+
+/// Seed deserializes a `serde_json::Value`, recording duplicate keys in its
+/// State.
+struct Seed<'a>(&'a mut State);
+
+impl<'de> DeserializeSeed<'de> for Seed<'_> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Seed<'_> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any valid JSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Number::from_f64(v).map_or(Value::Null, Value::Number))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut vec = Vec::new();
+        loop {
+            self.0.path.push(vec.len().to_string());
+            let next = seq.next_element_seed(Seed(self.0))?;
+            self.0.path.pop();
+            match next {
+                Some(v) => vec.push(v),
+                None => return Ok(Value::Array(vec)),
+            }
+        }
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut obj = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            self.0.path.push(key);
+            if self.0.duplicate.is_none() && obj.contains_key(self.0.path.last().unwrap()) {
+                self.0.duplicate = Some(self.0.pointer());
+            }
+            let value = map.next_value_seed(Seed(self.0))?;
+            obj.insert(self.0.path.pop().unwrap(), value);
+        }
+        Ok(Value::Object(obj))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        for (name, input, dup) in [
+            ("scalar", "42", None),
+            ("array", "[1, 2.5, \"x\", null, true]", None),
+            ("object", r#"{"a": 1, "b": {"a": 2}}"#, None),
+            (
+                "root",
+                r#"{"role": "user", "role": "admin"}"#,
+                Some("/role"),
+            ),
+            ("nested", r#"{"a": {"b": 1, "b": 2}}"#, Some("/a/b")),
+            (
+                "in array",
+                r#"[1, {"x": [{"y": 1, "y": 1}]}]"#,
+                Some("/1/x/0/y"),
+            ),
+            (
+                "escaped",
+                r#"{"a/b": {"c~d": 1, "c~d": 2}}"#,
+                Some("/a~1b/c~0d"),
+            ),
+            ("empty key", r#"{"": 1, "": 2}"#, Some("/")),
+            ("first", r#"{"a": {"x": 1, "x": 2}, "a": 3}"#, Some("/a/x")),
+            ("after", r#"{"a": 1, "a": {"x": 1, "x": 2}}"#, Some("/a")),
+        ] {
+            let (value, found) = parse(input).unwrap();
+            assert_eq!(dup.map(String::from), found, "{name}");
+            assert_eq!(
+                serde_json::from_str::<Value>(input).unwrap(),
+                value,
+                "{name}"
+            );
+        }
+
+        assert_eq!(
+            (json!({"role": "admin"}), Some("/role".to_string())),
+            parse(r#"{"role": "user", "role": "admin"}"#).unwrap(),
+        );
+
+        for input in ["{\"x\": ", "[1, 2", "{} {}", ""] {
+            assert_eq!(
+                serde_json::from_str::<Value>(input)
+                    .unwrap_err()
+                    .to_string(),
+                parse(input).unwrap_err().to_string(),
+                "{input}"
+            );
+        }
+    }
+}
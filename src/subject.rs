@@ -0,0 +1,305 @@
+//! Versioned schema subjects.
+//!
+//! A subject is a named sequence of schema versions, numbered from 1, as in
+//! the Confluent Schema Registry. The `jsonschema_subject_versions` table
+//! stores the versions and rejects updates and deletes, so that the version
+//! history is immutable. Each subject has a compatibility level, stored in
+//! `jsonschema_subjects`, that `jsonschema_register_version()` enforces when
+//! adding a version, using the checks in the `compat` module. Versions may
+//! reference schemas in the registry by `$id`.
+
+use std::rc::Rc;
+
+use boon::SchemaIndex;
+use pgrx::prelude::*;
+use pgrx::{JsonB, JsonString};
+use serde_json::Value;
+
+use crate::compat::{self, Compatibility};
+use crate::{
+    compile, extension_table, parse_data, registry, validate_instance, CompileError, Draft,
+    Schemas, StatementCache, DEFAULT_URL, GUC,
+};
+
+extension_sql!(
+    r#"
+-- jsonschema_subjects stores the compatibility level of each subject.
+CREATE TABLE jsonschema_subjects (
+	subject       TEXT PRIMARY KEY,
+	compatibility TEXT NOT NULL DEFAULT 'BACKWARD' CHECK (compatibility IN (
+		'NONE', 'BACKWARD', 'BACKWARD_TRANSITIVE', 'FORWARD',
+		'FORWARD_TRANSITIVE', 'FULL', 'FULL_TRANSITIVE'
+	))
+);
+
+-- jsonschema_subject_versions stores the schema versions of each subject.
+-- Versions are immutable.
+CREATE TABLE jsonschema_subject_versions (
+	subject       TEXT        NOT NULL REFERENCES jsonschema_subjects,
+	version       INTEGER     NOT NULL CHECK (version > 0),
+	schema        JSONB       NOT NULL,
+	registered_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp(),
+	PRIMARY KEY (subject, version)
+);
+
+CREATE FUNCTION jsonschema_subject_versions_immutable() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+	RAISE EXCEPTION 'cannot % version % of subject "%": schema versions are immutable',
+		lower(TG_OP), OLD.version, OLD.subject
+		USING ERRCODE = 'restrict_violation';
+END;
+$$;
+
+CREATE TRIGGER jsonschema_subject_versions_immutable
+BEFORE UPDATE OR DELETE ON jsonschema_subject_versions
+FOR EACH ROW EXECUTE FUNCTION jsonschema_subject_versions_immutable();
+
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_subjects', '');
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_subject_versions', '');
+"#,
+    name = "jsonschema_subjects",
+);
+
+/// jsonschema_set_compatibility sets the compatibility level of `subject`,
+/// which need not have any versions yet, and returns the level's name.
+#[pg_extern(volatile, strict)]
+fn jsonschema_set_compatibility(subject: &str, compatibility: &str) -> String {
+    let level = Compatibility::from_name(compatibility)
+        .unwrap_or_else(|e| ereport!(ERROR, PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE, e));
+    Spi::run_with_args(
+        &format!(
+            "INSERT INTO {} (subject, compatibility) VALUES ($1, $2)
+             ON CONFLICT (subject) DO UPDATE SET compatibility = EXCLUDED.compatibility",
+            extension_table("jsonschema_subjects"),
+        ),
+        &[subject.into(), level.name().into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    level.name().to_string()
+}
+
+/// jsonschema_register_version adds `schema` as the next version of
+/// `subject` and returns its version number, raising an error if it fails
+/// to compile or violates the subject's compatibility level. If `schema` is
+/// the same as the latest version, returns that version instead.
+#[pg_extern(volatile, strict)]
+fn jsonschema_register_version(subject: &str, schema: JsonB) -> i32 {
+    let level = lock(subject);
+    let versions = versions(subject);
+    if let Some((version, latest)) = versions.last() {
+        if *latest == schema.0 {
+            return *version;
+        }
+    }
+
+    if let Err(e) = compile_version(&schema.0) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(r#"invalid schema for subject "{subject}": {e:#}"#),
+        );
+    }
+
+    let earlier = if level.transitive() {
+        &versions[..]
+    } else {
+        &versions[versions.len().saturating_sub(1)..]
+    };
+    for (version, old) in earlier.iter().rev() {
+        let err = if level.backward() {
+            compat::superset(&schema.0, old).err().map(|at| {
+                format!("the new schema may reject data that version {version} accepts, at '{at}'")
+            })
+        } else {
+            None
+        };
+        let err = err.or_else(|| {
+            if !level.forward() {
+                return None;
+            }
+            compat::superset(old, &schema.0).err().map(|at| {
+                format!("version {version} may reject data that the new schema accepts, at '{at}'")
+            })
+        });
+        if let Some(detail) = err {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                format!(
+                    r#"schema is not {} compatible with version {version} of subject "{subject}""#,
+                    level.name(),
+                ),
+                detail,
+            );
+        }
+    }
+
+    let version = versions.last().map_or(1, |(v, _)| v + 1);
+    Spi::run_with_args(
+        &format!(
+            "INSERT INTO {} (subject, version, schema) VALUES ($1, $2, $3)",
+            extension_table("jsonschema_subject_versions"),
+        ),
+        &[subject.into(), version.into(), schema.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    version
+}
+
+/// lock creates `subject` if it doesn't exist and locks it until the end of
+/// the transaction, so that concurrent registrations of its versions are
+/// serialized. Returns its compatibility level.
+fn lock(subject: &str) -> Compatibility {
+    let level = Spi::get_one_with_args::<String>(
+        &format!(
+            "INSERT INTO {} (subject) VALUES ($1)
+             ON CONFLICT (subject) DO UPDATE SET subject = EXCLUDED.subject
+             RETURNING compatibility",
+            extension_table("jsonschema_subjects"),
+        ),
+        &[subject.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"))
+    .unwrap_or_default();
+    Compatibility::from_name(&level).unwrap_or_else(|e| error!("{e}"))
+}
+
+/// versions returns the version numbers and schemas of `subject`, in order.
+fn versions(subject: &str) -> Vec<(i32, Value)> {
+    Spi::connect(|client| {
+        client
+            .select(
+                &format!(
+                    "SELECT version, schema FROM {} WHERE subject = $1 ORDER BY version",
+                    extension_table("jsonschema_subject_versions"),
+                ),
+                None,
+                &[subject.into()],
+            )?
+            .map(|row| Ok((row.get::<i32>(1)?, row.get::<JsonB>(2)?)))
+            .collect::<Result<Vec<_>, pgrx::spi::SpiError>>()
+    })
+    .unwrap_or_else(|e| error!("{e}"))
+    .into_iter()
+    .filter_map(|(v, s)| Some((v?, s?.0)))
+    .collect()
+}
+
+/// compile_version compiles the subject version `schema` together with the
+/// registered schemas, so that it may reference them.
+fn compile_version(schema: &Value) -> Result<(Schemas, SchemaIndex), CompileError> {
+    let id = schema["$id"].as_str().unwrap_or(DEFAULT_URL);
+    let mut schemas = vec![schema.clone()];
+    schemas.extend(
+        registry::registered()
+            .into_iter()
+            .filter(|s| s["$id"].as_str() != Some(id)),
+    );
+    compile(id, &schemas)
+}
+
+// jsonschema_validates_subject(data::json,  subject::text)
+// jsonschema_validates_subject(data::jsonb, subject::text)
+// jsonschema_validates_subject(data::json,  subject::text, version::integer)
+// jsonschema_validates_subject(data::jsonb, subject::text, version::integer)
+
+/// json_schema_validates_subject validates `data` against the latest version
+/// of `subject`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_subject")]
+fn json_schema_validates_subject(data: JsonString, subject: &str) -> bool {
+    validates_subject(parse_data(&data.0), subject, None)
+}
+
+/// jsonb_schema_validates_subject validates `data` against the latest
+/// version of `subject`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_subject")]
+fn jsonb_schema_validates_subject(data: JsonB, subject: &str) -> bool {
+    validates_subject(Ok(data.0), subject, None)
+}
+
+/// json_schema_validates_subject_version validates `data` against `version`
+/// of `subject`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_subject")]
+fn json_schema_validates_subject_version(data: JsonString, subject: &str, version: i32) -> bool {
+    validates_subject(parse_data(&data.0), subject, Some(version))
+}
+
+/// jsonb_schema_validates_subject_version validates `data` against `version`
+/// of `subject`.
+#[pg_extern(stable, strict, parallel_safe, name = "jsonschema_validates_subject")]
+fn jsonb_schema_validates_subject_version(data: JsonB, subject: &str, version: i32) -> bool {
+    validates_subject(Ok(data.0), subject, Some(version))
+}
+
+/// validates_subject validates `data` against `version` of `subject`, or its
+/// latest version if `version` is `None`. Logs parse and validation errors
+/// at the `INFO` level and returns false.
+fn validates_subject(data: Result<Value, String>, subject: &str, version: Option<i32>) -> bool {
+    let data = match data {
+        Err(e) => {
+            info!("{e}");
+            return false;
+        }
+        Ok(v) => v,
+    };
+    let c = compiled(subject, version);
+    if let Err(e) = validate_instance(&c.schemas, &data, c.index) {
+        info!("{e}");
+        return false;
+    }
+    true
+}
+
+/// Compiled holds a compiled subject version.
+struct Compiled {
+    schemas: Schemas,
+    index: SchemaIndex,
+}
+
+thread_local! {
+    // Compiled versions by subject, version, where None is the latest
+    // version, and default draft, reused for the rest of the statement.
+    static CACHE: StatementCache<(String, Option<i32>, Draft), Compiled> =
+        StatementCache::new();
+}
+
+/// compiled returns the Compiled schema for `version` of `subject`, or its
+/// latest version if `version` is `None`, compiling it once per statement.
+fn compiled(subject: &str, version: Option<i32>) -> Rc<Compiled> {
+    let key = (subject.to_string(), version, GUC.get());
+    CACHE.with(|cache| cache.get(key, || load(subject, version)))
+}
+
+/// load compiles `version` of `subject`, or its latest version if `version`
+/// is `None`. Raises an error if there is no such version.
+fn load(subject: &str, version: Option<i32>) -> Compiled {
+    let schema = Spi::get_one_with_args::<JsonB>(
+        &format!(
+            "SELECT schema FROM {} WHERE subject = $1 AND ($2::integer IS NULL OR version = $2)
+              ORDER BY version DESC LIMIT 1",
+            extension_table("jsonschema_subject_versions"),
+        ),
+        &[subject.into(), version.into()],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    let Some(schema) = schema else {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+            match version {
+                Some(v) => format!(r#"subject "{subject}" has no version {v}"#),
+                None => format!(r#"subject "{subject}" has no versions"#),
+            },
+        );
+    };
+    let (schemas, index) = compile_version(&schema.0).unwrap_or_else(|e| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(r#"invalid schema for subject "{subject}": {e:#}"#),
+        )
+    });
+
+    Compiled { schemas, index }
+}
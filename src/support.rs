@@ -0,0 +1,271 @@
+//! Planner support for `jsonschema_validates()`.
+//!
+//! `jsonschema_validates_support()` is the planner support function for the
+//! `jsonschema_validates()`, `jsonschema_validates_set()`, and
+//! `jsonschema_validates_yaml()` functions. It answers two requests:
+//!
+//! *   Selectivity: Assumes a fixed fraction of rows validate, the default
+//!     of `matchingsel` without statistics, rather than the default for
+//!     boolean functions. Postgres derives the selectivity of `NOT
+//!     jsonschema_validates()` from the same estimate.
+//! *   Cost: Estimates the per-row cost of validating against constant
+//!     schemas from the number of keywords they contain, rather than the
+//!     default cost of a single operator.
+//!
+//! The first argument of every supported function is the data; every other
+//! argument is a schema, a list or set of schemas, or an option such as an
+//! `$id`, format, or draft, which add no keywords.
+
+use pgrx::prelude::*;
+use pgrx::{Internal, Json, JsonB, PgList};
+use serde_json::Value;
+
+use crate::yaml;
+
+// The cost of each schema keyword, in multiples of cpu_operator_cost.
+const KEYWORD_COST: f64 = 10.0;
+
+// The selectivity to assume, like DEFAULT_MATCHING_SEL.
+const DEFAULT_SELECTIVITY: f64 = 0.010;
+
+/// jsonschema_validates_support is the planner support function for the
+/// validating `jsonschema_validates()`, `jsonschema_validates_set()`, and
+/// `jsonschema_validates_yaml()` functions. Returns the request if it has
+/// filled it in, and `NULL` otherwise.
+#[pg_extern(immutable, strict, parallel_safe)]
+fn jsonschema_validates_support(req: Internal) -> Internal {
+    let Some(datum) = req.unwrap() else {
+        return Internal::from(None);
+    };
+    let node = datum.cast_mut_ptr::<pg_sys::Node>();
+    let supported = unsafe {
+        if pgrx::is_a(node, pg_sys::NodeTag::T_SupportRequestSelectivity) {
+            selectivity(&mut *node.cast())
+        } else if pgrx::is_a(node, pg_sys::NodeTag::T_SupportRequestCost) {
+            cost(&mut *node.cast())
+        } else {
+            false
+        }
+    };
+    Internal::from(supported.then_some(datum))
+}
+
+/// selectivity sets the selectivity of a clause calling a validating
+/// function to `DEFAULT_SELECTIVITY`. It does not validate the column's
+/// statistics, so planning never compiles schemas or runs custom keywords.
+fn selectivity(req: &mut pg_sys::SupportRequestSelectivity) -> bool {
+    req.selectivity = DEFAULT_SELECTIVITY;
+    true
+}
+
+/// cost estimates the per-row cost of a call to a validating function with
+/// constant arguments after the data from the number of keywords in its
+/// schemas.
+unsafe fn cost(req: &mut pg_sys::SupportRequestCost) -> bool {
+    let node = req.node;
+    if node.is_null() || !pgrx::is_a(node, pg_sys::NodeTag::T_FuncExpr) {
+        return false;
+    }
+    let args = PgList::<pg_sys::Node>::from_pg((*node.cast::<pg_sys::FuncExpr>()).args);
+    let Some(consts) = args
+        .iter_ptr()
+        .skip(1)
+        .map(|node| constant(req.root, node))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return false;
+    };
+    let count: usize = consts
+        .into_iter()
+        .flat_map(|c| const_schemas(&*c))
+        .map(|schema| keywords(&schema))
+        .sum();
+
+    req.startup = 0.0;
+    req.per_tuple = pg_sys::cpu_operator_cost * KEYWORD_COST * count.max(1) as f64;
+    true
+}
+
+/// constant returns `node` if it is a non-`NULL` constant. Given a planner
+/// `root`, it first estimates the value of `node`, which folds stable
+/// functions and domain coercions, such as to `jsonschema_set`, into
+/// constants.
+unsafe fn constant(
+    root: *mut pg_sys::PlannerInfo,
+    mut node: *mut pg_sys::Node,
+) -> Option<*mut pg_sys::Const> {
+    if !root.is_null() {
+        node = pg_sys::estimate_expression_value(root, node);
+    }
+    if !pgrx::is_a(node, pg_sys::NodeTag::T_Const) {
+        return None;
+    }
+    let c = node.cast::<pg_sys::Const>();
+    (!(*c).constisnull).then_some(c)
+}
+
+/// const_schemas returns the schemas in `c`: its value if it is a `json` or
+/// `jsonb` value or `text` that parses as JSON or YAML, including a domain
+/// over them, and the elements of arrays of them. Returns no schemas for
+/// other types.
+unsafe fn const_schemas(c: &pg_sys::Const) -> Vec<Value> {
+    let (datum, oid) = (c.constvalue, pg_sys::getBaseType(c.consttype));
+    if oid == pg_sys::JSONBOID {
+        JsonB::from_datum(datum, false)
+            .map(|j| j.0)
+            .into_iter()
+            .collect()
+    } else if oid == pg_sys::JSONOID {
+        Json::from_datum(datum, false)
+            .map(|j| j.0)
+            .into_iter()
+            .collect()
+    } else if oid == pg_sys::TEXTOID {
+        String::from_datum(datum, false)
+            .and_then(|s| parse(&s))
+            .into_iter()
+            .collect()
+    } else if oid == pg_sys::JSONBARRAYOID {
+        Vec::<Option<JsonB>>::from_datum(datum, false)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|j| j.0)
+            .collect()
+    } else if oid == pg_sys::JSONARRAYOID {
+        Vec::<Option<Json>>::from_datum(datum, false)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|j| j.0)
+            .collect()
+    } else if oid == pg_sys::TEXTARRAYOID {
+        Vec::<Option<String>>::from_datum(datum, false)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|s| parse(&s))
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// parse parses `text` as JSON or, failing that, YAML.
+fn parse(text: &str) -> Option<Value> {
+    serde_json::from_str(text)
+        .ok()
+        .or_else(|| yaml::parse(text).ok())
+}
+
+/// keywords returns the number of object keys in `schema`, at any depth, as
+/// a rough measure of the cost of validating against it.
+fn keywords(schema: &Value) -> usize {
+    match schema {
+        Value::Object(obj) => obj.len() + obj.values().map(keywords).sum::<usize>(),
+        Value::Array(list) => list.iter().map(keywords).sum(),
+        _ => 0,
+    }
+}
+
+extension_sql!(
+    r#"
+ALTER FUNCTION jsonschema_validates(json, json) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, jsonb) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(json, jsonb) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, json) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(json, text, json[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, text, jsonb[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(json, text, jsonb[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, text, json[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_set(json, text, jsonschema_set) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_set(jsonb, text, jsonschema_set) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(json, json, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, jsonb, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(json, jsonb, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, json, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(json, json, bool) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(jsonb, jsonb, bool) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(text, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(text, text, text[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(bytea, text, json) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(bytea, text, jsonb) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(bytea, text, text, json[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates(bytea, text, text, jsonb[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(text, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(text, json) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(text, jsonb) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(json, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(jsonb, text) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(text, text, text[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(text, text, json[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(text, text, jsonb[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(json, text, text[]) SUPPORT jsonschema_validates_support;
+ALTER FUNCTION jsonschema_validates_yaml(jsonb, text, text[]) SUPPORT jsonschema_validates_support;
+"#,
+    name = "jsonschema_validates_support",
+    requires = [
+        jsonschema_validates_support,
+        crate::json_schema_validates_json,
+        crate::jsonb_schema_validates_jsonb,
+        crate::json_schema_validates_jsonb,
+        crate::jsonb_schema_validates_json,
+        crate::json_schema_id_validates_json,
+        crate::jsonb_schema_id_validates_jsonb,
+        crate::json_schema_id_validates_jsonb,
+        crate::jsonb_schema_id_validates_json,
+        crate::json_schema_validates_json_draft,
+        crate::jsonb_schema_validates_jsonb_draft,
+        crate::json_schema_validates_jsonb_draft,
+        crate::jsonb_schema_validates_json_draft,
+        crate::json_schema_validates_json_data_refs,
+        crate::jsonb_schema_validates_jsonb_data_refs,
+        crate::text_schema_validates_text,
+        crate::text_schema_id_validates_text,
+        crate::bytea_schema_validates_json,
+        crate::bytea_schema_validates_jsonb,
+        crate::bytea_schema_id_validates_json,
+        crate::bytea_schema_id_validates_jsonb,
+        crate::yaml_schema_validates_yaml,
+        crate::yaml_schema_validates_json,
+        crate::yaml_schema_validates_jsonb,
+        crate::json_schema_validates_yaml,
+        crate::jsonb_schema_validates_yaml,
+        crate::yaml_schema_id_validates_yaml,
+        crate::yaml_schema_id_validates_json,
+        crate::yaml_schema_id_validates_jsonb,
+        crate::json_schema_id_validates_yaml,
+        crate::jsonb_schema_id_validates_yaml,
+        "jsonschema_set",
+    ],
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_keywords() {
+        for (name, schema, count) in [
+            ("boolean", json!(true), 0),
+            ("empty", json!({}), 0),
+            ("type", json!({"type": "object"}), 1),
+            (
+                "properties",
+                json!({
+                    "type": "object",
+                    "properties": {"x": {"type": "string"}, "y": {"enum": [1, 2]}},
+                }),
+                6,
+            ),
+            (
+                "array",
+                json!({"anyOf": [{"type": "string"}, {"minimum": 1, "maximum": 2}]}),
+                4,
+            ),
+        ] {
+            assert_eq!(count, keywords(&schema), "{name}");
+        }
+    }
+}
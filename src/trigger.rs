@@ -0,0 +1,848 @@
+//! Trigger validation of JSON columns.
+//!
+//! `jsonschema_validate_trigger()` is a trigger function that validates one
+//! or more columns of each inserted or updated row against a JSON Schema. It
+//! may be fired `FOR EACH ROW`, including as a deferrable constraint trigger,
+//! or `FOR EACH STATEMENT` with a `NEW TABLE` transition table, in which case
+//! it validates every row in the transition table and raises a single error
+//! listing the invalid rows. It's configured by trigger arguments in the form
+//! `key=value`:
+//!
+//! *   `column`: The name of a column to validate. Required; may be repeated.
+//! *   `schema`: A JSON Schema. May be repeated to provide schemas referenced
+//!     by the schema to validate.
+//! *   `id`: The `$id` of the schema to validate against. Defaults to the
+//!     `$id` of the first schema.
+//! *   `schema_id`: The `$id` of a registered schema to validate against,
+//!     compiled with the other registered schemas. Required unless `schema`
+//!     is given, and not allowed with `schema` or `id`.
+//! *   `on_update_only_if_changed`: When `true`, skips validation on `UPDATE`
+//!     of columns whose values have not changed. Row-level triggers only.
+//!     Defaults to `false`.
+//! *   `max_errors`: The maximum number of invalid rows to list in the error
+//!     raised by a statement-level trigger. Defaults to 10.
+//! *   `mode`: How to handle invalid rows: `enforce` raises an error, `warn`
+//!     logs a warning and allows the row, and `quarantine` skips the row and
+//!     records it and its errors in the `jsonschema_quarantine` table.
+//!     Quarantine requires a `BEFORE` row-level trigger. Defaults to
+//!     `enforce`. The `jsonschema.trigger_mode` GUC overrides it.
+
+use std::ffi::{CStr, CString};
+use std::rc::Rc;
+
+use boon::SchemaIndex;
+use pgrx::prelude::*;
+use serde_json::{json, Value};
+
+use crate::{
+    compile, extension_table, parse, parse_data, registry, validate_instance, Draft, Schemas,
+    StatementCache, DEFAULT_URL, GUC, TRIGGER_MODE,
+};
+
+/// Trigger enforcement modes.
+#[derive(PostgresGucEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Mode {
+    /// Use the mode configured by each trigger's arguments.
+    Default,
+    /// Raise an error for invalid rows.
+    Enforce,
+    /// Log a warning for invalid rows and allow them.
+    Warn,
+    /// Skip invalid rows and record them in the quarantine table.
+    Quarantine,
+}
+
+/// Config holds the trigger configuration parsed from its arguments.
+#[derive(Debug, Clone, PartialEq)]
+struct Config {
+    columns: Vec<String>,
+    id: String,
+    schemas: Vec<Value>,
+    registered: bool,
+    only_if_changed: bool,
+    max_errors: usize,
+    mode: Mode,
+}
+
+impl Config {
+    /// parse parses trigger arguments into a Config.
+    fn parse<S: AsRef<str>>(args: &[S]) -> Result<Self, String> {
+        let mut columns = Vec::new();
+        let mut id = None;
+        let mut schema_id = None;
+        let mut schemas = Vec::new();
+        let mut only_if_changed = false;
+        let mut max_errors = 10;
+        let mut mode = Mode::Enforce;
+
+        for arg in args {
+            let arg = arg.as_ref();
+            let Some((key, val)) = arg.split_once('=') else {
+                return Err(format!(r#"invalid argument "{arg}": must be key=value"#));
+            };
+            match key.trim() {
+                "column" => columns.push(val.to_string()),
+                "id" => id = Some(val.to_string()),
+                "schema_id" => schema_id = Some(val.to_string()),
+                "schema" => schemas.push(parse(val)?),
+                "on_update_only_if_changed" => only_if_changed = parse_bool(key, val)?,
+                "max_errors" => max_errors = parse_count(key, val)?,
+                "mode" => mode = parse_mode(key, val)?,
+                _ => return Err(format!(r#"unknown argument "{key}""#)),
+            }
+        }
+
+        if columns.is_empty() {
+            return Err("missing required argument \"column\"".to_string());
+        }
+        if let Some(schema_id) = schema_id {
+            if !schemas.is_empty() || id.is_some() {
+                return Err(
+                    r#"argument "schema_id" cannot be combined with "schema" or "id""#.to_string(),
+                );
+            }
+            return Ok(Config {
+                columns,
+                id: schema_id,
+                schemas,
+                registered: true,
+                only_if_changed,
+                max_errors,
+                mode,
+            });
+        }
+        if schemas.is_empty() {
+            return Err(r#"missing required argument "schema" or "schema_id""#.to_string());
+        }
+
+        let id = id.unwrap_or_else(|| match &schemas[0]["$id"] {
+            Value::String(s) => s.to_string(),
+            _ => DEFAULT_URL.to_string(),
+        });
+
+        Ok(Config {
+            columns,
+            id,
+            schemas,
+            registered: false,
+            only_if_changed,
+            max_errors,
+            mode,
+        })
+    }
+}
+
+/// parse_bool parses a boolean trigger argument value.
+fn parse_bool(key: &str, val: &str) -> Result<bool, String> {
+    match val.trim().to_ascii_lowercase().as_str() {
+        "true" | "on" | "yes" | "1" => Ok(true),
+        "false" | "off" | "no" | "0" => Ok(false),
+        _ => Err(format!(r#"invalid value for "{key}": "{val}""#)),
+    }
+}
+
+/// parse_count parses a positive integer trigger argument value.
+fn parse_count(key: &str, val: &str) -> Result<usize, String> {
+    match val.trim().parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!(r#"invalid value for "{key}": "{val}""#)),
+    }
+}
+
+/// parse_mode parses an enforcement mode trigger argument value.
+fn parse_mode(key: &str, val: &str) -> Result<Mode, String> {
+    match val.trim().to_ascii_lowercase().as_str() {
+        "enforce" => Ok(Mode::Enforce),
+        "warn" => Ok(Mode::Warn),
+        "quarantine" => Ok(Mode::Quarantine),
+        _ => Err(format!(r#"invalid value for "{key}": "{val}""#)),
+    }
+}
+
+/// Compiled holds a trigger's configuration and compiled schema.
+struct Compiled {
+    config: Config,
+    schemas: Schemas,
+    index: SchemaIndex,
+}
+
+impl Compiled {
+    /// validate parses `value` and validates it against the compiled schema,
+    /// returning a description of the failure if it's invalid.
+    fn validate(&self, value: &str) -> Result<(), String> {
+        let instance = parse_data(value)?;
+        validate_instance(&self.schemas, &instance, self.index).map_err(|e| e.to_string())
+    }
+}
+
+thread_local! {
+    // Compiled schemas by trigger OID and default draft, reused for the rest
+    // of the statement.
+    static CACHE: StatementCache<(pg_sys::Oid, Draft), Compiled> = StatementCache::new();
+}
+
+/// compiled returns the Compiled configuration and schema for `trigger`,
+/// parsing its arguments and compiling its schema once per statement.
+fn compiled(trigger: &PgTrigger) -> Rc<Compiled> {
+    let tgoid = unsafe { trigger.trigger() }.tgoid;
+    CACHE.with(|cache| cache.get((tgoid, GUC.get()), || load(trigger)))
+}
+
+/// load parses the arguments of `trigger` and compiles its schema, with all
+/// of the registered schemas if it names a registered schema.
+fn load(trigger: &PgTrigger) -> Compiled {
+    let name = trigger.name().unwrap_or_default();
+    let args = trigger.extra_args().unwrap_or_default();
+    let config = Config::parse(&args).unwrap_or_else(|e| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(r#"invalid arguments for trigger "{name}": {e}"#),
+        )
+    });
+    let compiled = if config.registered {
+        if registry::typmod_for(&config.id).is_none() {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_UNDEFINED_OBJECT,
+                format!(
+                    r#"invalid arguments for trigger "{name}": no schema registered for "{}""#,
+                    config.id
+                ),
+            );
+        }
+        compile(&config.id, &registry::registered())
+    } else {
+        compile(&config.id, &config.schemas)
+    };
+    let (schemas, index) = compiled.unwrap_or_else(|e| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(r#"invalid schema for trigger "{name}": {e:#}"#),
+        )
+    });
+
+    Compiled {
+        config,
+        schemas,
+        index,
+    }
+}
+
+// jsonschema_quarantine records rows skipped by triggers in quarantine mode.
+// Mark it as a configuration table so that pg_dump dumps its contents.
+extension_sql!(
+    r#"
+-- jsonschema_quarantine records rows that failed validation by
+-- jsonschema_validate_trigger() in quarantine mode, along with their errors.
+CREATE TABLE jsonschema_quarantine (
+	id             BIGINT      GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+	quarantined_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp(),
+	relation       REGCLASS    NOT NULL,
+	trigger_name   NAME        NOT NULL,
+	operation      TEXT        NOT NULL,
+	row_data       JSONB       NOT NULL,
+	errors         JSONB       NOT NULL
+);
+
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_quarantine', '');
+SELECT pg_catalog.pg_extension_config_dump('jsonschema_quarantine_id_seq', '');
+"#,
+    name = "jsonschema_quarantine",
+);
+
+/// jsonschema_validate_trigger validates the columns named by its trigger
+/// arguments in each inserted or updated row. Row-level triggers handle each
+/// invalid row according to the enforcement mode; statement-level triggers
+/// validate the rows in the `NEW TABLE` transition table and report the
+/// invalid rows together.
+#[pg_trigger]
+fn jsonschema_validate_trigger<'a>(
+    trigger: &'a PgTrigger<'a>,
+) -> Result<Option<PgHeapTuple<'a, impl WhoAllocated>>, PgTriggerError> {
+    let name = trigger.name()?;
+    let op = trigger.op()?;
+    if !matches!(op, PgTriggerOperation::Insert | PgTriggerOperation::Update) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_E_R_I_E_TRIGGER_PROTOCOL_VIOLATED,
+            format!(
+                r#"trigger "{name}": jsonschema_validate_trigger() must be fired on INSERT or UPDATE"#
+            ),
+        );
+    }
+
+    let c = compiled(trigger);
+    let level = trigger.level();
+    let before = trigger.when()? == PgTriggerWhen::Before;
+    let can_quarantine = before && level == PgTriggerLevel::Row;
+    if c.config.mode == Mode::Quarantine && !can_quarantine {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(
+                r#"invalid arguments for trigger "{name}": "mode=quarantine" requires BEFORE and FOR EACH ROW"#
+            ),
+        );
+    }
+
+    // The GUC overrides the trigger's mode. Triggers that cannot skip rows
+    // warn instead of quarantining.
+    let mode = match TRIGGER_MODE.get() {
+        Mode::Default => c.config.mode,
+        Mode::Quarantine if !can_quarantine => Mode::Warn,
+        mode => mode,
+    };
+
+    match level {
+        PgTriggerLevel::Row => {
+            if !validate_row(trigger, &c, op, mode)? {
+                return Ok(None);
+            }
+        }
+        PgTriggerLevel::Statement => validate_rows(trigger, &c, mode)?,
+    }
+
+    Ok(trigger.new())
+}
+
+/// validate_row validates the configured columns of the row passed to a
+/// row-level trigger. In `enforce` mode it raises an error for the first
+/// invalid value, in `warn` mode it logs a warning for each invalid value,
+/// and in `quarantine` mode it records the row and its errors in the
+/// quarantine table. Returns false if the row should be skipped.
+fn validate_row(
+    trigger: &PgTrigger,
+    c: &Compiled,
+    op: PgTriggerOperation,
+    mode: Mode,
+) -> Result<bool, PgTriggerError> {
+    let data = unsafe { trigger.trigger_data() };
+    let tupdesc = unsafe { (*data.tg_relation).rd_att };
+    let (new, old) = match op {
+        PgTriggerOperation::Update => (data.tg_newtuple, data.tg_trigtuple),
+        _ => (data.tg_trigtuple, std::ptr::null_mut()),
+    };
+
+    // A deferred constraint trigger fires with the row as it was when the
+    // event was queued. Skip it if the row has since been updated, which
+    // queues an event for the new version, or deleted.
+    if unsafe { (*data.tg_trigger).tgdeferrable } {
+        let live = unsafe { is_live(new, trigger) }?;
+        if !live {
+            return Ok(true);
+        }
+    }
+
+    let mut failures = Vec::new();
+    for column in &c.config.columns {
+        let value = unsafe { column_value(tupdesc, new, column, trigger) };
+        let Some(value) = value else { continue };
+        if c.config.only_if_changed && !old.is_null() {
+            let prev = unsafe { column_value(tupdesc, old, column, trigger) };
+            if prev.as_deref() == Some(value.as_str()) {
+                continue;
+            }
+        }
+
+        let Err(err) = c.validate(&value) else {
+            continue;
+        };
+
+        if mode == Mode::Quarantine {
+            failures.push((column.as_str(), err));
+            continue;
+        }
+
+        let table = trigger.table_name()?;
+        let message =
+            format!(r#"new row for relation "{table}" violates jsonschema for column "{column}""#);
+        let detail = match unsafe { primary_key(tupdesc, new, trigger.relid()?, trigger) } {
+            Some(pk) => format!("Failing row has {pk}.\n{err}"),
+            None => err,
+        };
+        if mode == Mode::Warn {
+            ereport!(
+                PgLogLevel::WARNING,
+                PgSqlErrorCode::ERRCODE_CHECK_VIOLATION,
+                message,
+                detail,
+            );
+        } else {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_CHECK_VIOLATION,
+                message,
+                detail,
+            );
+        }
+    }
+
+    if failures.is_empty() {
+        return Ok(true);
+    }
+    quarantine(trigger, op, &failures)?;
+    Ok(false)
+}
+
+/// quarantine records the new row passed to `trigger` and the column
+/// validation `failures` in the `jsonschema_quarantine` table.
+fn quarantine(
+    trigger: &PgTrigger,
+    op: PgTriggerOperation,
+    failures: &[(&str, String)],
+) -> Result<(), PgTriggerError> {
+    let errors: Vec<Value> = failures
+        .iter()
+        .map(|(column, error)| json!({"column": column, "error": error}))
+        .collect();
+    let op = match op {
+        PgTriggerOperation::Update => "UPDATE",
+        _ => "INSERT",
+    };
+
+    Spi::run_with_args(
+        &format!(
+            "INSERT INTO {}
+                    (relation, trigger_name, operation, row_data, errors)
+             VALUES ($1, $2, $3, to_jsonb($4), $5)",
+            extension_table("jsonschema_quarantine"),
+        ),
+        &[
+            trigger.relid()?.into(),
+            trigger.name()?.into(),
+            op.into(),
+            trigger.new().into(),
+            pgrx::JsonB(Value::Array(errors)).into(),
+        ],
+    )
+    .unwrap_or_else(|e| error!("{e}"));
+    Ok(())
+}
+
+/// validate_rows validates the configured columns of every row in the `NEW
+/// TABLE` transition table passed to a statement-level trigger, raising a
+/// single error listing up to `max_errors` invalid rows, or logging it as a
+/// warning in `warn` mode.
+fn validate_rows(trigger: &PgTrigger, c: &Compiled, mode: Mode) -> Result<(), PgTriggerError> {
+    let name = trigger.name()?;
+    let data = unsafe { trigger.trigger_data() };
+    if data.tg_newtable.is_null() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_E_R_I_E_TRIGGER_PROTOCOL_VIOLATED,
+            format!(
+                r#"trigger "{name}": jsonschema_validate_trigger() must be fired FOR EACH ROW or with REFERENCING NEW TABLE"#
+            ),
+        );
+    }
+    if c.config.only_if_changed {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(
+                r#"invalid arguments for trigger "{name}": "on_update_only_if_changed" requires FOR EACH ROW"#
+            ),
+        );
+    }
+
+    // Make sure the columns exist before querying the transition table.
+    let tupdesc = unsafe { (*data.tg_relation).rd_att };
+    for column in &c.config.columns {
+        unsafe { attnum(tupdesc, column, trigger) };
+    }
+
+    let pk = primary_key_columns(trigger.relid()?);
+    let newtable = unsafe { CStr::from_ptr((*data.tg_trigger).tgnewtable) };
+    let query = format!(
+        "SELECT {} FROM {}",
+        c.config
+            .columns
+            .iter()
+            .chain(&pk)
+            .map(|col| format!("{}::text", spi::quote_identifier(col)))
+            .collect::<Vec<_>>()
+            .join(", "),
+        spi::quote_identifier(newtable.to_string_lossy()),
+    );
+
+    let ncols = c.config.columns.len();
+    let (count, failures) = Spi::connect(|client| {
+        // Expose the transition table to the query.
+        unsafe { pg_sys::SPI_register_trigger_data(data as *const _ as *mut _) };
+        let mut count = 0;
+        let mut failures = Vec::new();
+        for (i, row) in client.select(&query, None, &[])?.enumerate() {
+            let mut errors = Vec::new();
+            for (j, column) in c.config.columns.iter().enumerate() {
+                let Some(value) = row.get::<String>(j + 1)? else {
+                    continue;
+                };
+                if let Err(err) = c.validate(&value) {
+                    errors.push(format!("Column \"{column}\": {err}"));
+                }
+            }
+            if errors.is_empty() {
+                continue;
+            }
+
+            count += 1;
+            if failures.len() < c.config.max_errors {
+                let row_id = if pk.is_empty() {
+                    format!("Failing row {}", i + 1)
+                } else {
+                    let values = (0..pk.len())
+                        .map(|k| row.get::<String>(ncols + k + 1))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let values: Vec<String> = values
+                        .into_iter()
+                        .map(|v| v.unwrap_or_else(|| "null".to_string()))
+                        .collect();
+                    format!(
+                        "Failing row has ({})=({})",
+                        pk.join(", "),
+                        values.join(", ")
+                    )
+                };
+                failures.push(format!("{row_id}.\n{}", errors.join("\n")));
+            }
+        }
+        Ok::<_, spi::Error>((count, failures))
+    })
+    .unwrap_or_else(|e| error!("{e}"));
+
+    if count == 0 {
+        return Ok(());
+    }
+
+    let table = trigger.table_name()?;
+    let message = if count == 1 {
+        format!(r#"1 new row for relation "{table}" violates jsonschema"#)
+    } else {
+        format!(r#"{count} new rows for relation "{table}" violate jsonschema"#)
+    };
+    let mut detail = failures.join("\n");
+    if count > failures.len() {
+        let more = count - failures.len();
+        detail.push_str(&format!(
+            "\nAnd {more} more invalid row{}.",
+            if more == 1 { "" } else { "s" }
+        ));
+    }
+    if mode == Mode::Warn {
+        ereport!(
+            PgLogLevel::WARNING,
+            PgSqlErrorCode::ERRCODE_CHECK_VIOLATION,
+            message,
+            detail,
+        );
+        return Ok(());
+    }
+    ereport!(
+        ERROR,
+        PgSqlErrorCode::ERRCODE_CHECK_VIOLATION,
+        message,
+        detail,
+    );
+}
+
+/// is_live returns true if `tuple` is the current version of its row, and
+/// false if the row has since been updated or deleted.
+///
+/// # Safety
+///
+/// `tuple` must be a tuple from the trigger's relation.
+unsafe fn is_live(tuple: pg_sys::HeapTuple, trigger: &PgTrigger) -> Result<bool, PgTriggerError> {
+    let (block, offset) = pgrx::itemptr::item_pointer_get_both((*tuple).t_self);
+    let query = format!(
+        "SELECT EXISTS(SELECT 1 FROM ONLY {}.{} WHERE ctid = '({block},{offset})')",
+        spi::quote_identifier(trigger.table_schema()?),
+        spi::quote_identifier(trigger.table_name()?),
+    );
+    Ok(Spi::get_one::<bool>(&query)
+        .unwrap_or_else(|e| error!("{e}"))
+        .unwrap_or(false))
+}
+
+/// attnum returns the attribute number of `column` in `tupdesc`, raising an
+/// error if it does not exist.
+///
+/// # Safety
+///
+/// `tupdesc` must describe the trigger's relation.
+unsafe fn attnum(tupdesc: pg_sys::TupleDesc, column: &str, trigger: &PgTrigger) -> i32 {
+    let cname = CString::new(column).unwrap_or_default();
+    let attnum = pg_sys::SPI_fnumber(tupdesc, cname.as_ptr());
+    if attnum <= 0 {
+        let table = trigger.table_name().unwrap_or_default();
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            format!(r#"column "{column}" of relation "{table}" does not exist"#),
+        );
+    }
+    attnum
+}
+
+/// column_value returns the text representation of `column` in `tuple`, or
+/// `None` if it's NULL.
+///
+/// # Safety
+///
+/// `tupdesc` must describe `tuple`.
+unsafe fn column_value(
+    tupdesc: pg_sys::TupleDesc,
+    tuple: pg_sys::HeapTuple,
+    column: &str,
+    trigger: &PgTrigger,
+) -> Option<String> {
+    let attnum = attnum(tupdesc, column, trigger);
+    let value = pg_sys::SPI_getvalue(tuple, tupdesc, attnum);
+    if value.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(value).to_string_lossy().into_owned())
+}
+
+/// primary_key returns a string describing the primary key of `tuple` in the
+/// form `(col1, col2)=(val1, val2)`, or `None` if the table with OID `relid`
+/// has no primary key.
+///
+/// # Safety
+///
+/// `tupdesc` must describe `tuple`.
+unsafe fn primary_key(
+    tupdesc: pg_sys::TupleDesc,
+    tuple: pg_sys::HeapTuple,
+    relid: pg_sys::Oid,
+    trigger: &PgTrigger,
+) -> Option<String> {
+    let columns = primary_key_columns(relid);
+    if columns.is_empty() {
+        return None;
+    }
+
+    let values: Vec<String> = columns
+        .iter()
+        .map(|c| column_value(tupdesc, tuple, c, trigger).unwrap_or_else(|| "null".to_string()))
+        .collect();
+    Some(format!("({})=({})", columns.join(", "), values.join(", ")))
+}
+
+/// primary_key_columns returns the names of the primary key columns of the
+/// table with OID `relid`, in key order, or an empty vector if it has no
+/// primary key.
+fn primary_key_columns(relid: pg_sys::Oid) -> Vec<String> {
+    let columns = Spi::connect(|client| {
+        client
+            .select(
+                "SELECT a.attname::text
+                   FROM pg_catalog.pg_index i
+                   JOIN pg_catalog.pg_attribute a
+                     ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)
+                  WHERE i.indrelid = $1 AND i.indisprimary
+                  ORDER BY array_position(i.indkey::int2[], a.attnum)",
+                None,
+                &[relid.into()],
+            )?
+            .map(|row| row.get::<String>(1))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .unwrap_or_default();
+
+    columns.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_config() {
+        let schema = json!({"$id": "https://example.com/a", "type": "object"});
+        let schema2 = json!({"$id": "https://example.com/b", "type": "string"});
+
+        for (name, args, config) in [
+            (
+                "basic",
+                vec!["column=body".to_string(), format!("schema={schema}")],
+                Config {
+                    columns: vec!["body".to_string()],
+                    id: "https://example.com/a".to_string(),
+                    schemas: vec![schema.clone()],
+                    registered: false,
+                    only_if_changed: false,
+                    max_errors: 10,
+                    mode: Mode::Enforce,
+                },
+            ),
+            (
+                "no id",
+                vec!["column=body".to_string(), "schema={}".to_string()],
+                Config {
+                    columns: vec!["body".to_string()],
+                    id: DEFAULT_URL.to_string(),
+                    schemas: vec![json!({})],
+                    registered: false,
+                    only_if_changed: false,
+                    max_errors: 10,
+                    mode: Mode::Enforce,
+                },
+            ),
+            (
+                "everything",
+                vec![
+                    "column=a".to_string(),
+                    "column=b".to_string(),
+                    format!("schema={schema}"),
+                    format!("schema={schema2}"),
+                    "id=https://example.com/b".to_string(),
+                    "on_update_only_if_changed=true".to_string(),
+                    "max_errors=3".to_string(),
+                    "mode=Quarantine".to_string(),
+                ],
+                Config {
+                    columns: vec!["a".to_string(), "b".to_string()],
+                    id: "https://example.com/b".to_string(),
+                    schemas: vec![schema.clone(), schema2.clone()],
+                    registered: false,
+                    only_if_changed: true,
+                    max_errors: 3,
+                    mode: Mode::Quarantine,
+                },
+            ),
+            (
+                "schema_id",
+                vec![
+                    "column=body".to_string(),
+                    "schema_id=https://example.com/a".to_string(),
+                ],
+                Config {
+                    columns: vec!["body".to_string()],
+                    id: "https://example.com/a".to_string(),
+                    schemas: vec![],
+                    registered: true,
+                    only_if_changed: false,
+                    max_errors: 10,
+                    mode: Mode::Enforce,
+                },
+            ),
+            (
+                "equals in value",
+                vec!["column=a=b".to_string(), "schema=true".to_string()],
+                Config {
+                    columns: vec!["a=b".to_string()],
+                    id: DEFAULT_URL.to_string(),
+                    schemas: vec![json!(true)],
+                    registered: false,
+                    only_if_changed: false,
+                    max_errors: 10,
+                    mode: Mode::Enforce,
+                },
+            ),
+        ] {
+            assert_eq!(Ok(config), Config::parse(&args), "{name}");
+        }
+
+        for (name, args, err) in [
+            ("empty", vec![], r#"missing required argument "column""#),
+            (
+                "no schema",
+                vec!["column=a"],
+                r#"missing required argument "schema" or "schema_id""#,
+            ),
+            (
+                "schema and schema_id",
+                vec!["column=a", "schema={}", "schema_id=https://example.com/a"],
+                r#"argument "schema_id" cannot be combined with "schema" or "id""#,
+            ),
+            (
+                "id and schema_id",
+                vec!["column=a", "id=x", "schema_id=https://example.com/a"],
+                r#"argument "schema_id" cannot be combined with "schema" or "id""#,
+            ),
+            (
+                "no column",
+                vec!["schema={}"],
+                r#"missing required argument "column""#,
+            ),
+            (
+                "not key=value",
+                vec!["column"],
+                r#"invalid argument "column": must be key=value"#,
+            ),
+            ("unknown", vec!["nope=1"], r#"unknown argument "nope""#),
+            (
+                "bad schema",
+                vec!["column=a", "schema={"],
+                "invalid JSON: EOF while parsing an object at line 1 column 1",
+            ),
+            (
+                "bad bool",
+                vec!["on_update_only_if_changed=maybe"],
+                r#"invalid value for "on_update_only_if_changed": "maybe""#,
+            ),
+            (
+                "bad count",
+                vec!["max_errors=0"],
+                r#"invalid value for "max_errors": "0""#,
+            ),
+            (
+                "bad mode",
+                vec!["mode=default"],
+                r#"invalid value for "mode": "default""#,
+            ),
+        ] {
+            assert_eq!(Err(err.to_string()), Config::parse(&args), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_parse_bool() {
+        for val in ["true", "TRUE", "on", "yes", "1", " true "] {
+            assert_eq!(Ok(true), parse_bool("x", val), "{val}");
+        }
+        for val in ["false", "False", "off", "no", "0"] {
+            assert_eq!(Ok(false), parse_bool("x", val), "{val}");
+        }
+        assert_eq!(
+            Err(r#"invalid value for "x": "nope""#.to_string()),
+            parse_bool("x", "nope"),
+        );
+    }
+
+    #[test]
+    fn test_parse_count() {
+        for (val, n) in [("1", 1), ("10", 10), (" 42 ", 42)] {
+            assert_eq!(Ok(n), parse_count("x", val), "{val}");
+        }
+        for val in ["0", "-1", "1.5", "lots", ""] {
+            assert_eq!(
+                Err(format!(r#"invalid value for "x": "{val}""#)),
+                parse_count("x", val),
+                "{val}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        for (val, mode) in [
+            ("enforce", Mode::Enforce),
+            ("warn", Mode::Warn),
+            (" WARN ", Mode::Warn),
+            ("quarantine", Mode::Quarantine),
+        ] {
+            assert_eq!(Ok(mode), parse_mode("x", val), "{val}");
+        }
+        for val in ["default", "", "reject"] {
+            assert_eq!(
+                Err(format!(r#"invalid value for "x": "{val}""#)),
+                parse_mode("x", val),
+                "{val}"
+            );
+        }
+    }
+}
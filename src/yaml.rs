@@ -0,0 +1,173 @@
+//! Parsing of YAML documents into `serde_json::Value`s.
+//!
+//! YAML is parsed according to the YAML 1.2 core schema, so only `true` and
+//! `false` are booleans and only `null`, `~`, and empty values are nulls.
+//! Anchors, aliases, and `<<` merge keys are resolved before conversion. YAML
+//! values that have no JSON equivalent map to JSON like so:
+//!
+//! *   Tags are ignored in favor of the tagged value.
+//! *   Non-finite floats (`.nan` and `.inf`) become `null`.
+//! *   Map keys that are nulls, booleans, or numbers become their JSON text
+//!     representations. Sequence and mapping keys are an error.
+//!
+//! Multi-document streams are not supported.
+
+use serde_json::{Map, Number, Value};
+use serde_yaml::Value as Yaml;
+
+/// parse parses `yaml` into a `serde_json::Value`, returning a message
+/// describing the failure if `yaml` is malformed, including its line and
+/// column when serde_yaml attributes it to one.
+pub(crate) fn parse(yaml: &str) -> Result<Value, String> {
+    let mut doc: Yaml = serde_yaml::from_str(yaml).map_err(|e| format!("invalid YAML: {e}"))?;
+    doc.apply_merge()
+        .map_err(|e| format!("invalid YAML: {e}"))?;
+    to_json(doc).map_err(|e| format!("invalid YAML: {e}"))
+}
+
+/// parse_all parses each item in `yamls` into a `serde_json::Value`,
+/// returning the message for the first one that's malformed.
+pub(crate) fn parse_all<'a, I: IntoIterator<Item = &'a str>>(
+    yamls: I,
+) -> Result<Vec<Value>, String> {
+    yamls.into_iter().map(parse).collect()
+}
+
+/// to_json converts a YAML value into a JSON value.
+fn to_json(yaml: Yaml) -> Result<Value, String> {
+    Ok(match yaml {
+        Yaml::Null => Value::Null,
+        Yaml::Bool(b) => Value::Bool(b),
+        Yaml::Number(n) => number_to_json(&n),
+        Yaml::String(s) => Value::String(s),
+        Yaml::Sequence(seq) => {
+            Value::Array(seq.into_iter().map(to_json).collect::<Result<_, _>>()?)
+        }
+        Yaml::Mapping(map) => {
+            let mut obj = Map::with_capacity(map.len());
+            for (k, v) in map {
+                obj.insert(key_to_string(k)?, to_json(v)?);
+            }
+            Value::Object(obj)
+        }
+        Yaml::Tagged(tagged) => to_json(tagged.value)?,
+    })
+}
+
+/// number_to_json converts a YAML number into a JSON number, or `null` if
+/// it's not finite.
+fn number_to_json(n: &serde_yaml::Number) -> Value {
+    if let Some(i) = n.as_i64() {
+        Value::Number(i.into())
+    } else if let Some(u) = n.as_u64() {
+        Value::Number(u.into())
+    } else {
+        n.as_f64()
+            .and_then(Number::from_f64)
+            .map_or(Value::Null, Value::Number)
+    }
+}
+
+/// key_to_string converts a YAML mapping key into a JSON object key.
+fn key_to_string(key: Yaml) -> Result<String, String> {
+    match key {
+        Yaml::String(s) => Ok(s),
+        Yaml::Null => Ok("null".to_string()),
+        Yaml::Bool(b) => Ok(b.to_string()),
+        Yaml::Number(n) => Ok(number_to_json(&n).to_string()),
+        Yaml::Tagged(tagged) => key_to_string(tagged.value),
+        Yaml::Sequence(_) => Err("sequence mapping keys are not supported".to_string()),
+        Yaml::Mapping(_) => Err("mapping mapping keys are not supported".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        for (name, yaml, json) in [
+            ("scalar", "42", json!(42)),
+            ("string", "hello", json!("hello")),
+            ("null", "~", json!(null)),
+            (
+                "yaml 1.2 bool",
+                "[true, yes, no, on]",
+                json!([true, "yes", "no", "on"]),
+            ),
+            ("float", "1.5", json!(1.5)),
+            ("nan", ".nan", json!(null)),
+            ("inf", "-.inf", json!(null)),
+            (
+                "big int",
+                "18446744073709551615",
+                json!(18446744073709551615u64),
+            ),
+            (
+                "object",
+                "name: Theory\ntags:\n  - a\n  - b\n",
+                json!({"name": "Theory", "tags": ["a", "b"]}),
+            ),
+            ("flow", "{x: 1, y: [2, 3]}", json!({"x": 1, "y": [2, 3]})),
+            (
+                "keys",
+                "200: ok\n1.5: f\ntrue: t\n~: n\n",
+                json!({"200": "ok", "1.5": "f", "true": "t", "null": "n"}),
+            ),
+            ("tag", "!custom {a: 1}", json!({"a": 1})),
+            (
+                "anchors",
+                "base: &b {x: 1, y: 2}\nderived:\n  <<: *b\n  y: 3\n",
+                json!({"base": {"x": 1, "y": 2}, "derived": {"x": 1, "y": 3}}),
+            ),
+            ("document marker", "---\na: 1\n", json!({"a": 1})),
+        ] {
+            assert_eq!(json, parse(yaml).unwrap(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_parse_err() {
+        for (name, yaml, err) in [
+            (
+                "unclosed flow",
+                "{a: 1",
+                "invalid YAML: did not find expected ',' or '}' at line 2 column 1, while parsing a flow mapping",
+            ),
+            (
+                "bad indent",
+                "a:\n\tb: 1",
+                "invalid YAML: found character that cannot start any token at line 2 column 1, while scanning for the next token",
+            ),
+            (
+                "unknown anchor",
+                "a: &x 1\nb: *y",
+                "invalid YAML: unknown anchor at line 2 column 4",
+            ),
+            (
+                "duplicate key",
+                "a: 1\na: 2",
+                "invalid YAML: duplicate entry with key \"a\"",
+            ),
+            (
+                "multiple documents",
+                "---\na: 1\n---\nb: 2",
+                "invalid YAML: deserializing from YAML containing more than one document is not supported",
+            ),
+            (
+                "sequence key",
+                "? [a, b]\n: c\n",
+                "invalid YAML: sequence mapping keys are not supported",
+            ),
+        ] {
+            assert_eq!(err, parse(yaml).unwrap_err(), "{name}");
+        }
+
+        assert_eq!(
+            "invalid YAML: unknown anchor at line 2 column 4",
+            parse_all(["a: 1", "a: &x 1\nb: *y"]).unwrap_err(),
+        );
+    }
+}
@@ -0,0 +1,30 @@
+-- CBOR
+SELECT jsonschema_validates('\xa1616101'::bytea, 'cbor', '{"properties": {"a": {"type": "integer"}}}'::jsonb);
+SELECT jsonschema_validates('\xa161616178'::bytea, 'cbor', '{"properties": {"a": {"type": "integer"}}}'::json);
+
+-- MessagePack
+SELECT jsonschema_validates('\x81a16101'::bytea, 'msgpack', '{"properties": {"a": {"type": "integer"}}}'::jsonb);
+SELECT jsonschema_validates('\x81a161a178'::bytea, 'MessagePack', '{"properties": {"a": {"type": "integer"}}}'::json);
+
+-- Values with no JSON equivalent
+SELECT jsonschema_validates('\xa16162420102'::bytea, 'cbor', '{"properties": {"b": {"const": "AQI"}}}'::jsonb);
+SELECT jsonschema_validates('\xa16162d7420102'::bytea, 'cbor', '{"properties": {"b": {"const": "0102"}}}'::jsonb);
+SELECT jsonschema_validates('\xc2420100'::bytea, 'cbor', '{"const": 256}'::jsonb);
+SELECT jsonschema_validates('\xf97e00'::bytea, 'cbor', '{"type": "null"}'::jsonb);
+
+-- Multiple schemas
+SELECT jsonschema_validates(
+    '\xa1616101'::bytea, 'cbor', 'https://example.com/binary/a',
+    '{"$id": "https://example.com/binary/a", "$ref": "b"}'::jsonb,
+    '{"$id": "https://example.com/binary/b", "type": "object"}'::jsonb
+);
+
+-- NULL data
+SELECT jsonschema_validates(NULL::bytea, 'cbor', '{"properties": {"a": {"type": "integer"}}}'::jsonb);
+
+-- Malformed data
+SELECT jsonschema_validates('\xa161610100'::bytea, 'cbor', '{}'::jsonb);
+SELECT jsonschema_validates('\x81a1610100'::bytea, 'msgpack', '{}'::jsonb);
+
+-- Unknown format
+SELECT jsonschema_validates('\x00'::bytea, 'bson', '{}'::jsonb);
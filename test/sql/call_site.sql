@@ -0,0 +1,25 @@
+-- Recompile schemas that change between calls from the same expression
+CREATE TABLE call_site_docs (id int, doc jsonb, schema jsonb);
+INSERT INTO call_site_docs VALUES
+    (1, '{"x": 1}', '{"type": "object"}'),
+    (2, '{"x": 1}', '{"type": "array"}'),
+    (3, '[1]', '{"type": "array"}'),
+    (4, '[1]', '{"type": "object"}');
+CREATE FUNCTION call_site_valid() RETURNS TABLE (id int, valid bool)
+LANGUAGE plpgsql AS $$
+DECLARE
+    r record;
+BEGIN
+    FOR r IN SELECT * FROM call_site_docs ORDER BY 1 LOOP
+        id := r.id;
+        valid := r.doc @~ r.schema;
+        RETURN NEXT;
+    END LOOP;
+END;
+$$;
+SELECT * FROM call_site_valid();
+SELECT * FROM call_site_valid();
+
+-- Clean up
+DROP FUNCTION call_site_valid();
+DROP TABLE call_site_docs;
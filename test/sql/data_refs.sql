@@ -0,0 +1,39 @@
+-- $data references are disabled by default
+SHOW jsonschema.data_references;
+\set schema '{"type": "object", "properties": {"minPrice": {"type": "number"}, "maxPrice": {"type": "number", "minimum": {"$data": "1/minPrice"}}}}'
+SELECT jsonschema_is_valid(:'schema'::jsonb);
+
+-- Enable $data references for a call
+SELECT jsonschema_validates('{"minPrice": 10, "maxPrice": 15}'::jsonb, :'schema'::jsonb, data_refs => true);
+SELECT jsonschema_validates('{"minPrice": 10, "maxPrice": 5}'::jsonb, :'schema'::jsonb, data_refs => true);
+SELECT jsonschema_validates('{"minPrice": 10.5, "maxPrice": 10}'::json, :'schema'::json, data_refs => true);
+
+-- Keywords whose pointers resolve to nothing are ignored
+SELECT jsonschema_validates('{"maxPrice": 5}'::jsonb, :'schema'::jsonb, data_refs => true);
+
+-- Enable $data references for the session
+SET jsonschema.data_references TO on;
+SELECT jsonschema_is_valid(:'schema'::jsonb);
+SELECT jsonschema_validates('{"minPrice": 10, "maxPrice": 5}'::jsonb, :'schema'::jsonb);
+SELECT '{"minPrice": 10, "maxPrice": 15}'::jsonb @~ :'schema'::jsonb AS valid;
+SELECT jsonschema_validates(
+    '{"password": "s3cr3t!", "confirmation": "s3cr3t?"}'::jsonb,
+    '{"properties": {"confirmation": {"const": {"$data": "1/password"}}}}'::jsonb
+);
+SELECT jsonschema_validates(
+    '{"allowed": ["a", "b"], "tags": ["a", "c"]}'::jsonb,
+    '{"properties": {"tags": {"items": {"enum": {"$data": "2/allowed"}}}}}'::jsonb
+);
+SELECT jsonschema_validates(
+    '{"fields": ["a", "b"], "a": 1}'::jsonb,
+    '{"required": {"$data": "/fields"}}'::jsonb
+);
+
+-- Disable $data references for a call
+SELECT jsonschema_validates('{"minPrice": 10, "maxPrice": 5}'::jsonb, :'schema'::jsonb, data_refs => false);
+
+-- The configuration still applies after the error
+SELECT jsonschema_validates('{"minPrice": 10, "maxPrice": 5}'::jsonb, :'schema'::jsonb);
+
+-- Clean up
+RESET jsonschema.data_references;
@@ -0,0 +1,51 @@
+-- Register a vocabulary meta-schema and a dialect that references it
+SELECT jsonschema_register_dialect('{
+    "$id": "https://example.com/regress/meta/sku",
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "$vocabulary": {"https://example.com/regress/vocab/sku": true},
+    "$dynamicAnchor": "meta",
+    "properties": {"x-sku": {"type": "string"}}
+}');
+SELECT jsonschema_register_dialect('{
+    "$id": "https://example.com/regress/dialect/sku",
+    "$schema": "https://json-schema.org/draft/2020-12/schema",
+    "$vocabulary": {
+        "https://json-schema.org/draft/2020-12/vocab/core": true,
+        "https://json-schema.org/draft/2020-12/vocab/applicator": true,
+        "https://json-schema.org/draft/2020-12/vocab/unevaluated": true,
+        "https://json-schema.org/draft/2020-12/vocab/validation": true,
+        "https://json-schema.org/draft/2020-12/vocab/meta-data": true,
+        "https://json-schema.org/draft/2020-12/vocab/format-annotation": true,
+        "https://json-schema.org/draft/2020-12/vocab/content": true,
+        "https://example.com/regress/vocab/sku": true
+    },
+    "$dynamicAnchor": "meta",
+    "allOf": [
+        {"$ref": "https://json-schema.org/draft/2020-12/schema"},
+        {"$ref": "https://example.com/regress/meta/sku"}
+    ]
+}');
+SELECT id, draft FROM jsonschema_dialects ORDER BY id;
+
+-- Schemas may name the dialect in $schema
+SELECT jsonschema_draft_of('{"$schema": "https://example.com/regress/dialect/sku", "type": "object", "x-sku": "widget", "properties": {"qty": {"type": "integer", "x-sku": "qty"}}}'::jsonb);
+SELECT jsonschema_is_valid('{"$schema": "https://example.com/regress/dialect/sku", "type": "object", "x-sku": "widget", "properties": {"qty": {"type": "integer", "x-sku": "qty"}}}'::jsonb);
+SELECT jsonschema_validates('{"qty": 3}'::jsonb, '{"$schema": "https://example.com/regress/dialect/sku", "type": "object", "x-sku": "widget", "properties": {"qty": {"type": "integer", "x-sku": "qty"}}}'::jsonb);
+SELECT jsonschema_validates('{"qty": "3"}'::jsonb, '{"$schema": "https://example.com/regress/dialect/sku", "type": "object", "x-sku": "widget", "properties": {"qty": {"type": "integer", "x-sku": "qty"}}}'::jsonb);
+
+-- The dialect meta-schema applies to schemas that name it
+SELECT jsonschema_is_valid('{"$schema": "https://example.com/regress/dialect/sku", "x-sku": 42}'::jsonb);
+
+-- Schemas may reference dialect meta-schemas
+SELECT jsonschema_validates('{"x-sku": 42}'::jsonb, '{"$ref": "https://example.com/regress/meta/sku"}'::jsonb);
+
+-- Invalid dialects
+SELECT jsonschema_register_dialect('{"$schema": "https://json-schema.org/draft/2020-12/schema"}');
+SELECT jsonschema_register_dialect('{"$id": "http://json-schema.org/draft-07/schema#"}');
+SELECT jsonschema_register_dialect('{"$id": "https://example.com/regress/nope", "$schema": "https://example.com/regress/lol"}');
+
+-- Unregister the dialect
+SELECT jsonschema_unregister_dialect('https://example.com/regress/dialect/sku');
+SELECT jsonschema_unregister_dialect('https://example.com/regress/dialect/sku');
+SELECT jsonschema_draft_of('{"$schema": "https://example.com/regress/dialect/sku", "type": "object", "x-sku": "widget", "properties": {"qty": {"type": "integer", "x-sku": "qty"}}}'::jsonb);
+SELECT jsonschema_unregister_dialect('https://example.com/regress/meta/sku');
@@ -0,0 +1,64 @@
+-- Register schemas
+SELECT jsonschema_register('{
+    "$id": "https://example.com/address",
+    "type": "object",
+    "properties": {"city": {"type": "string"}}
+}');
+SELECT jsonschema_register('{
+    "$id": "https://example.com/user",
+    "type": "object",
+    "required": ["name"],
+    "properties": {
+        "name": {"type": "string"},
+        "address": {"$ref": "https://example.com/address"}
+    }
+}');
+SELECT jsonschema_register('{"type": "object"}');
+
+-- Declare a column validated by a registered schema
+CREATE TABLE users (id int, profile jsonschema_doc('https://example.com/user'));
+SELECT format_type(atttypid, atttypmod) FROM pg_attribute
+ WHERE attrelid = 'users'::regclass AND attname = 'profile';
+
+-- Valid values
+INSERT INTO users VALUES
+    (1, '{"name": "Naomi", "address": {"city": "Ceres"}}'),
+    (2, jsonb_build_object('name', 'Amos'));
+SELECT id, profile, profile->>'name' AS name FROM users ORDER BY id;
+
+-- Invalid values
+INSERT INTO users VALUES (3, jsonb_build_object('name', 3));
+UPDATE users SET profile = profile::jsonb - 'name' WHERE id = 2;
+UPDATE users SET profile = profile::jsonb || '{"address": []}' WHERE id = 1;
+
+-- Replace schemas only if no column or domain uses them
+SELECT jsonschema_register('{
+    "$id": "https://example.com/user",
+    "type": "object",
+    "required": ["name"],
+    "properties": {
+        "name": {"type": "string"},
+        "address": {"$ref": "https://example.com/address"}
+    }
+}');
+SELECT jsonschema_register('{"$id": "https://example.com/user"}');
+CREATE DOMAIN user_profile AS jsonschema_doc('https://example.com/user');
+
+-- Unregister schemas
+SELECT jsonschema_unregister('https://example.com/user');
+DROP TABLE users;
+SELECT jsonschema_unregister('https://example.com/user');
+DROP DOMAIN user_profile;
+SELECT jsonschema_unregister('https://example.com/user');
+SELECT jsonschema_unregister('https://example.com/user');
+SELECT jsonschema_unregister('https://example.com/address');
+
+-- Declare columns before registering their schemas, as pg_restore does
+CREATE TABLE orders (id int, body jsonschema_doc('https://example.com/order'));
+INSERT INTO orders SELECT 1, '{}'::jsonb;
+SELECT jsonschema_register('{"$id": "https://example.com/order", "type": "object"}');
+INSERT INTO orders SELECT 1, '{}'::jsonb;
+SELECT format_type(atttypid, atttypmod) FROM pg_attribute
+ WHERE attrelid = 'orders'::regclass AND attname = 'body';
+DROP TABLE orders;
+SELECT jsonschema_unregister('https://example.com/order');
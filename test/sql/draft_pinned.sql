@@ -0,0 +1,37 @@
+-- The draft-pinned functions are immutable
+SELECT p.oid::regprocedure AS function, p.provolatile
+  FROM pg_catalog.pg_proc p
+ WHERE p.proname IN ('jsonschema_is_valid', 'jsonschema_validates')
+   AND 'draft' = ANY (p.proargnames)
+ ORDER BY p.oid::regprocedure::text;
+
+-- Validate schemas against the draft
+SELECT jsonschema_is_valid('{"minimum": 1, "exclusiveMinimum": true}'::jsonb, 'V4');
+SELECT jsonschema_is_valid('{"minimum": 1, "exclusiveMinimum": true}'::json, 'v2020');
+
+-- Validate data with the draft
+SELECT jsonschema_validates('2'::jsonb, '{"minimum": 1, "exclusiveMinimum": true}'::jsonb, 'V4');
+SELECT jsonschema_validates('1'::json, '{"minimum": 1, "exclusiveMinimum": true}'::json, 'V4');
+SELECT jsonschema_validates('1'::jsonb, '{"minimum": 1, "exclusiveMinimum": true}'::json, 'V2020');
+
+-- $schema overrides the draft
+SELECT jsonschema_validates('1'::json, '{"$schema": "http://json-schema.org/draft-04/schema#", "minimum": 1, "exclusiveMinimum": true}'::jsonb, 'V2020');
+
+-- jsonschema.default_draft does not apply
+SET jsonschema.default_draft TO 'V4';
+SELECT jsonschema_validates('2'::jsonb, '{"exclusiveMinimum": 1}'::jsonb, 'V2020');
+SELECT jsonschema_is_valid('{"minimum": 1, "exclusiveMinimum": true}'::jsonb, 'V2020');
+RESET jsonschema.default_draft;
+
+-- Use in generated columns
+CREATE TABLE draft_docs (
+    doc   jsonb,
+    valid boolean GENERATED ALWAYS AS (jsonschema_validates(doc, '{"minimum": 1, "exclusiveMinimum": true}'::jsonb, 'V4')) STORED
+);
+INSERT INTO draft_docs (doc) VALUES ('2'), ('1'), ('"x"');
+SELECT doc, valid FROM draft_docs ORDER BY doc;
+DROP TABLE draft_docs;
+
+-- Unknown draft
+SELECT jsonschema_is_valid('{}'::jsonb, 'V5');
+SELECT jsonschema_validates('{}'::jsonb, '{}'::jsonb, 'V5');
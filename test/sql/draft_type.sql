@@ -0,0 +1,28 @@
+-- The draft type
+SELECT enum_range(NULL::jsonschema_draft);
+
+-- The draft named by $schema
+SELECT url, jsonschema_draft_of(jsonb_build_object('$schema', url))
+  FROM (VALUES
+    ('http://json-schema.org/draft-04/schema#'),
+    ('http://json-schema.org/draft-06/schema#'),
+    ('https://json-schema.org/draft-07/schema'),
+    ('https://json-schema.org/draft/2019-09/schema'),
+    ('https://json-schema.org/draft/2020-12/schema#')
+  ) AS v(url);
+
+-- The default draft when there is no $schema
+SELECT jsonschema_draft_of('{"type": "object"}'::json);
+SET jsonschema.default_draft TO 'V7';
+SELECT jsonschema_draft_of('{"type": "object"}'::jsonb);
+RESET jsonschema.default_draft;
+
+-- Unsupported drafts
+SELECT jsonschema_draft_of('{"$schema": "https://example.com/nonesuch"}'::jsonb);
+SELECT jsonschema_draft_of('{"$schema": 7}'::json);
+
+-- Meta-schemas
+SELECT d, coalesce(m->>'$id', m->>'id') AS id
+  FROM unnest(enum_range(NULL::jsonschema_draft)) AS d,
+       jsonschema_meta_schema(d) AS m;
+SELECT jsonschema_validates('{"type": "object"}'::jsonb, jsonschema_meta_schema('V7'));
@@ -0,0 +1,25 @@
+-- Make sure the DSO is loaded.
+SELECT jsonschema_is_valid('{"type": "object"}'::json);
+
+-- Duplicate keys allowed by default
+SHOW jsonschema.reject_duplicate_keys;
+SELECT jsonschema_validates('{"role": "user", "role": "admin"}'::json, '{"type": "object"}'::json);
+SELECT jsonschema_validates('{"role": "user", "role": "admin"}'::text, '{"type": "object"}'::text);
+
+-- Reject duplicate keys
+SET jsonschema.reject_duplicate_keys TO on;
+SELECT jsonschema_validates('{"role": "user", "role": "admin"}'::json, '{"type": "object"}'::json);
+SELECT jsonschema_validates('{"role": "user", "role": "admin"}'::text, '{"type": "object"}'::text);
+
+-- Nested duplicate keys
+SELECT jsonschema_validates('{"a": [{"b/c": 1, "b/c": 2}]}'::json, '{"type": "object"}'::jsonb);
+
+-- JSONB has no duplicate keys
+SELECT jsonschema_validates('{"role": "user", "role": "admin"}'::jsonb, '{"type": "object"}'::jsonb);
+
+-- No duplicate keys
+SELECT jsonschema_validates('{"role": "user", "a": {"role": "admin"}}'::json, '{"type": "object"}'::json);
+
+-- Reset
+RESET jsonschema.reject_duplicate_keys;
+SELECT jsonschema_validates('{"role": "user", "role": "admin"}'::json, '{"type": "object"}'::json);
@@ -0,0 +1,33 @@
+-- Replace the messages of all keywords
+\set schema '{"type": "object", "required": ["name"], "properties": {"name": {"type": "string", "errorMessage": "name must be text"}, "age": {"type": "integer", "minimum": 18, "errorMessage": {"minimum": "${1/name} must be at least 18, not ${0}"}}, "nick": {"maxLength": 8, "errorMessage": "${1/nope}${0} is too long"}}, "errorMessage": {"required": "a person needs a name"}}'
+SELECT jsonschema_validates('{"name": 42}'::jsonb, :'schema'::jsonb);
+
+-- Replace the messages of some keywords
+SELECT jsonschema_validates('{"name": "Tim", "age": 16}'::jsonb, :'schema'::jsonb);
+SELECT jsonschema_validates('{"name": "Tim", "age": "old"}'::jsonb, :'schema'::jsonb);
+SELECT jsonschema_validates('{"age": 20}'::jsonb, :'schema'::jsonb);
+
+-- Pointers to nothing interpolate nothing
+SELECT jsonschema_validates('{"name": "Tim", "nick": "Timothy Jones"}'::jsonb, :'schema'::jsonb);
+
+-- Messages do not apply to the errors of subschemas
+SELECT jsonschema_validates(
+    '{"age": 16}'::jsonb,
+    '{"properties": {"age": {"minimum": 18}}, "errorMessage": "invalid person"}'::jsonb
+);
+
+-- Rejected imports record the messages and keep the locations
+CREATE TABLE error_message_people (doc jsonb);
+SELECT * FROM jsonschema_import(
+    E'{"name": "Ann", "age": 30}\n{"name": "Tim", "age": 16}\n{"name": 42}',
+    'error_message_people', 'doc', :'schema'::jsonb
+);
+SELECT line, e->>'keywordLocation' AS keyword, e->>'instanceLocation' AS instance, e->>'error' AS error
+  FROM jsonschema_import_rejects, jsonb_array_elements(errors->'errors') e
+ WHERE relation = 'error_message_people'::regclass
+   AND e->>'keywordLocation' IN ('/properties/age/minimum', '/properties/name/type')
+ ORDER BY line;
+
+-- Clean up
+DELETE FROM jsonschema_import_rejects WHERE relation = 'error_message_people'::regclass;
+DROP TABLE error_message_people;
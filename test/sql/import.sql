@@ -0,0 +1,38 @@
+CREATE TABLE import_events (id int GENERATED ALWAYS AS IDENTITY, payload jsonb);
+
+-- Import valid lines and reject the rest
+SELECT * FROM jsonschema_import(
+    E'{"type": "a"}\n\n[]\n{"type": "b"}\n{"type"',
+    'import_events', 'payload',
+    '{"type": "object", "required": ["type"]}'::jsonb
+);
+SELECT id, payload FROM import_events ORDER BY id;
+SELECT relation, column_name, line, data, errors FROM jsonschema_import_rejects ORDER BY line;
+DELETE FROM jsonschema_import_rejects;
+
+-- Validate against one of several schemas
+SELECT * FROM jsonschema_import(
+    E'{"type": "c"}\n{"type": 1}',
+    'import_events', 'payload', 'https://example.com/import/event',
+    '{"$id": "https://example.com/import/event", "properties": {"type": {"$ref": "type"}}}'::jsonb,
+    '{"$id": "https://example.com/import/type", "type": "string"}'::jsonb
+);
+SELECT id, payload FROM import_events ORDER BY id;
+SELECT line, data FROM jsonschema_import_rejects ORDER BY line;
+DELETE FROM jsonschema_import_rejects;
+
+-- Record lines that fail to insert
+ALTER TABLE import_events ADD CONSTRAINT import_events_type CHECK (payload->>'type' <> 'x');
+SELECT * FROM jsonschema_import(
+    E'{"type": "x"}\n{"type": "d"}',
+    'import_events', 'payload', '{"type": "object"}'::jsonb
+);
+SELECT id, payload FROM import_events ORDER BY id;
+SELECT line, data, errors->'errors'->0->>'error' AS error FROM jsonschema_import_rejects ORDER BY line;
+DELETE FROM jsonschema_import_rejects;
+
+-- Missing column
+SELECT * FROM jsonschema_import('{}', 'import_events', 'nope', '{}'::jsonb);
+
+-- Clean up
+DROP TABLE import_events;
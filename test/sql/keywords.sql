@@ -0,0 +1,45 @@
+-- Register custom keywords implemented by SQL functions
+CREATE FUNCTION kw_date_order(kw jsonb, obj jsonb) RETURNS text
+LANGUAGE sql AS $$
+    SELECT format('%s must precede %s', kw->>0, kw->>1)
+     WHERE (obj->>(kw->>0))::date >= (obj->>(kw->>1))::date
+$$;
+CREATE FUNCTION kw_sku(kw jsonb, val jsonb) RETURNS boolean
+LANGUAGE sql AS $$ SELECT jsonb_typeof(val) <> 'string' OR starts_with(val #>> '{}', kw #>> '{}') $$;
+SELECT jsonschema_register_keyword('x-date-order', 'kw_date_order(jsonb, jsonb)');
+SELECT jsonschema_register_keyword('x-sku', 'kw_sku(jsonb, jsonb)');
+SELECT keyword, function FROM jsonschema_keywords ORDER BY keyword;
+
+-- Functions returning text report their messages
+\set schema '{"type": "object", "x-date-order": ["start", "end"], "properties": {"sku": {"type": "string", "x-sku": "SKU-"}}}'
+SELECT jsonschema_validates('{"start": "2024-01-01", "end": "2024-02-01", "sku": "SKU-1"}'::jsonb, :'schema'::jsonb);
+SELECT jsonschema_validates('{"start": "2024-03-01", "end": "2024-02-01"}'::jsonb, :'schema'::jsonb);
+
+-- Functions returning boolean report the keyword
+SELECT jsonschema_validates('{"sku": "ABC-1"}'::jsonb, :'schema'::jsonb);
+SELECT '{"sku": "SKU-2"}'::jsonb @~ :'schema'::jsonb AS valid;
+SELECT '{"sku": "XYZ-2"}'::jsonb @~ :'schema'::jsonb AS valid;
+
+-- Replace the function for a keyword
+CREATE FUNCTION kw_sku_text(kw jsonb, val jsonb) RETURNS text
+LANGUAGE sql AS $$
+    SELECT format('%s is not a SKU', val)
+     WHERE NOT starts_with(val #>> '{}', kw #>> '{}')
+$$;
+SELECT jsonschema_register_keyword('x-sku', 'kw_sku_text(jsonb, jsonb)');
+SELECT jsonschema_validates('{"sku": "ABC-1"}'::jsonb, :'schema'::jsonb);
+
+-- Invalid registrations
+SELECT jsonschema_register_keyword('', 'kw_sku(jsonb, jsonb)');
+SELECT jsonschema_register_keyword('errorMessage', 'kw_sku(jsonb, jsonb)');
+SELECT jsonschema_register_keyword('x-pg-references', 'kw_sku(jsonb, jsonb)');
+SELECT jsonschema_register_keyword('x-lower', 'lower(text)');
+
+-- Unregistered keywords are annotations
+SELECT jsonschema_unregister_keyword('x-sku');
+SELECT jsonschema_unregister_keyword('x-sku');
+SELECT jsonschema_validates('{"sku": "ABC-1"}'::jsonb, :'schema'::jsonb);
+
+-- Clean up
+SELECT jsonschema_unregister_keyword('x-date-order');
+DROP FUNCTION kw_date_order(jsonb, jsonb), kw_sku(jsonb, jsonb), kw_sku_text(jsonb, jsonb);
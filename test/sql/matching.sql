@@ -0,0 +1,28 @@
+-- Candidate schemas
+\set click '{"$id": "https://example.com/click", "type": "object", "required": ["x", "y"]}'
+\set key '{"$id": "https://example.com/key", "type": "object", "required": ["key"]}'
+\set any '{"$id": "https://example.com/any", "type": "object"}'
+
+-- All matches, in order
+SELECT jsonschema_matching('{"x": 1, "y": 2}'::jsonb, :'click', :'key', :'any');
+SELECT jsonschema_matching('{"key": "a"}'::json, :'click'::jsonb, :'key'::jsonb, :'any'::jsonb);
+
+-- No matches
+SELECT count(*) FROM jsonschema_matching('[]'::jsonb, :'click', :'key', :'any');
+
+-- First match
+SELECT jsonschema_first_match('{"x": 1, "y": 2}'::jsonb, :'any', :'click', :'key');
+SELECT jsonschema_first_match('{"key": "a"}'::json, :'click'::json, :'key'::json);
+SELECT jsonschema_first_match('[]'::jsonb, :'click'::json, :'key'::json) IS NULL AS no_match;
+
+-- Schemas without $id
+SELECT jsonschema_matching('"x"'::jsonb, '{"type": "string"}', '{"type": "number"}', '{"minLength": 1}');
+
+-- Classify rows
+CREATE TEMPORARY TABLE events (id INT, payload JSONB);
+INSERT INTO events VALUES (1, '{"x": 1, "y": 2}'), (2, '{"key": "q"}'), (3, '{"key": "q", "x": 1, "y": 2}'), (4, '[]');
+SELECT id, jsonschema_first_match(payload, :'click', :'key') AS type
+  FROM events ORDER BY id;
+SELECT id, count(m) AS matches
+  FROM events LEFT JOIN LATERAL jsonschema_matching(payload, :'click', :'key') m ON true
+ GROUP BY id ORDER BY id;
@@ -0,0 +1,25 @@
+-- Valid
+SELECT '{"x": "y"}'::json @~ '{"type": "object"}'::jsonb AS valid;
+SELECT '{"x": "y"}'::jsonb !@~ '{"type": "object"}'::json AS invalid;
+SELECT '{"type": "object"}'::jsonb ~@ '{"x": "y"}'::jsonb AS accepts;
+SELECT '{"type": "object"}'::json !~@ '{"x": "y"}'::json AS rejects;
+
+-- Invalid json (no INFO)
+SELECT '["x", "y"]'::json @~ '{"type": "object"}'::jsonb AS valid;
+SELECT '["x", "y"]'::jsonb !@~ '{"type": "object"}'::json AS invalid;
+SELECT '{"type": "object"}'::jsonb ~@ '["x", "y"]'::jsonb AS accepts;
+SELECT '{"type": "object"}'::json !~@ '["x", "y"]'::json AS rejects;
+
+-- Invalid schema
+SELECT '{"x": "y"}'::jsonb @~ '{"type": "nonesuch"}'::jsonb;
+
+-- NULLs
+SELECT NULL::jsonb @~ '{"type": "object"}'::jsonb AS valid;
+SELECT '{"x": "y"}'::json !@~ NULL::json AS invalid;
+
+-- Filter rows
+CREATE TEMPORARY TABLE docs (id INT, doc JSONB);
+INSERT INTO docs VALUES (1, '{"x": 1}'), (2, '[1]'), (3, '{}'), (4, NULL);
+SELECT id FROM docs WHERE doc @~ '{"type": "object"}' ORDER BY id;
+SELECT id FROM docs WHERE doc !@~ '{"type": "object"}' ORDER BY id;
+SELECT id FROM docs WHERE NOT doc @~ '{"type": "object"}' ORDER BY id;
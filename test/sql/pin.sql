@@ -0,0 +1,28 @@
+-- Pin registered schemas
+SELECT jsonschema_register('{"$id": "https://example.com/pin/user", "type": "object", "required": ["name"]}');
+SELECT jsonschema_pin('https://example.com/pin/user') AS pinned \gset
+SELECT :'pinned' = id || '@sha256:' || encode(sha256(convert_to(schema::text, 'UTF8')), 'hex') AS pinned
+  FROM jsonschema_registry WHERE id = 'https://example.com/pin/user';
+
+-- Validate against pinned schemas
+SELECT jsonschema_validates_pinned('{"name": "Tim"}'::jsonb, :'pinned');
+SELECT jsonschema_validates_pinned('{}'::json, :'pinned');
+CREATE TABLE pin_users (doc jsonb CHECK (jsonschema_validates_pinned(doc, :'pinned')));
+INSERT INTO pin_users VALUES ('{"name": "Tim"}');
+INSERT INTO pin_users VALUES ('{}');
+
+-- Replacing the schema breaks the pin
+DROP TABLE pin_users;
+SELECT jsonschema_register('{"$id": "https://example.com/pin/user", "type": "object"}');
+SELECT jsonschema_validates_pinned('{}'::jsonb, :'pinned');
+
+-- Invalid pins
+SELECT jsonschema_validates_pinned('{}'::jsonb, 'https://example.com/pin/user');
+SELECT jsonschema_pin('https://example.com/pin/nope');
+SELECT jsonschema_register('{"$id": "https://example.com/pin/address", "type": "object"}');
+SELECT jsonschema_register('{"$id": "https://example.com/pin/person", "$ref": "https://example.com/pin/address"}');
+SELECT jsonschema_pin('https://example.com/pin/person');
+
+-- Clean up
+SELECT jsonschema_unregister(id) FROM jsonschema_registry
+ WHERE id LIKE 'https://example.com/pin/%' ORDER BY id DESC;
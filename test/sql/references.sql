@@ -0,0 +1,33 @@
+-- Reference values in table columns
+CREATE TABLE ref_customers (id text PRIMARY KEY);
+CREATE TABLE ref_products (id int PRIMARY KEY);
+INSERT INTO ref_customers VALUES ('c-1'), ('c-2');
+INSERT INTO ref_products SELECT generate_series(1, 5);
+\set schema '{"type": "object", "properties": {"customer_id": {"x-pg-references": {"table": "ref_customers", "column": "id"}}, "product_ids": {"type": "array", "items": {"x-pg-references": {"table": "ref_products", "column": "id"}}}}}'
+CREATE TABLE ref_orders (id int, doc jsonb);
+INSERT INTO ref_orders VALUES
+    (1, '{"customer_id": "c-1", "product_ids": [1, 2, 5]}'),
+    (2, '{"customer_id": "c-2", "product_ids": [3, null, {}]}'),
+    (3, '{"customer_id": "c-3", "product_ids": [1]}'),
+    (4, '{"customer_id": "c-1", "product_ids": [1, 6]}'),
+    (5, '{"product_ids": ["2"]}');
+SELECT id, doc @~ :'schema'::jsonb AS valid FROM ref_orders ORDER BY id;
+
+-- Values the column type cannot represent are not present
+SELECT jsonschema_validates('{"product_ids": [1, "abc"]}'::jsonb, :'schema'::jsonb);
+SELECT jsonschema_validates('{"product_ids": [1.5]}'::jsonb, :'schema'::jsonb);
+
+-- Import documents with references
+SELECT * FROM jsonschema_import(
+    E'{"customer_id": "c-1", "product_ids": [1]}\n{"customer_id": "c-1", "product_ids": ["abc", 1.5]}\n{"customer_id": "c-9"}',
+    'ref_orders', 'doc', :'schema'::jsonb
+);
+SELECT line, e->>'instanceLocation' AS location, e->>'error' AS error
+  FROM jsonschema_import_rejects, jsonb_array_elements(errors->'errors') e
+ WHERE relation = 'ref_orders'::regclass
+   AND e->>'keywordLocation' LIKE '%/x-pg-references'
+ ORDER BY line, location;
+
+-- Clean up
+DELETE FROM jsonschema_import_rejects WHERE relation = 'ref_orders'::regclass;
+DROP TABLE ref_orders, ref_products, ref_customers;
@@ -0,0 +1,44 @@
+-- Create a table of schemas
+CREATE TEMPORARY TABLE schemas (schema jsonb);
+INSERT INTO schemas VALUES
+    ('{"$id": "https://example.com/a", "$ref": "b"}'),
+    ('{"$id": "https://example.com/b", "type": "string"}'),
+    (NULL);
+
+-- Aggregate schemas
+SELECT jsonschema_agg(schema) FROM schemas;
+SELECT jsonschema_agg(schema) FROM schemas WHERE schema IS NULL;
+SELECT pg_typeof(jsonschema_agg(schema)) FROM schemas;
+
+-- Valid schema set
+SELECT jsonschema_is_valid_set('https://example.com/a', (SELECT jsonschema_agg(schema) FROM schemas));
+
+-- Validate against aggregated schemas
+SELECT jsonschema_validates_set('"x"'::json, 'https://example.com/a', (SELECT jsonschema_agg(schema) FROM schemas));
+SELECT jsonschema_validates_set('"x"'::jsonb, 'https://example.com/a', (SELECT jsonschema_agg(schema) FROM schemas));
+SELECT jsonschema_validates_set('42'::jsonb, 'https://example.com/b', (SELECT jsonschema_agg(schema) FROM schemas));
+
+-- Array schema set with NULLs
+SELECT jsonschema_validates_set('"x"'::jsonb, 'https://example.com/a', '[
+    null,
+    {"$id": "https://example.com/a", "$ref": "b"},
+    {"$id": "https://example.com/b", "type": "string"}
+]'::jsonb);
+
+-- Object schema set
+SELECT jsonschema_validates_set('"x"'::json, 'https://example.com/a', '{
+    "https://example.com/a": {"$ref": "b"},
+    "https://example.com/b": {"type": "string"},
+    "https://example.com/c": null
+}'::jsonschema_set);
+
+-- Invalid schema set
+SELECT '"nope"'::jsonschema_set;
+SELECT jsonschema_validates_set('"x"'::jsonb, 'https://example.com/a', '"nope"'::jsonb);
+
+-- Single jsonb schemas still use the VARIADIC function
+SELECT jsonschema_validates('"x"'::jsonb, 'https://example.com/b', '{"$id": "https://example.com/b", "type": "string"}'::jsonb);
+
+-- Sets passed as a single schema
+SELECT jsonschema_is_valid('https://example.com/a', (SELECT jsonschema_agg(schema) FROM schemas));
+SELECT jsonschema_validates('"x"'::jsonb, 'https://example.com/a', (SELECT jsonschema_agg(schema) FROM schemas));
@@ -0,0 +1,24 @@
+-- Soft errors require Postgres 16 or later
+SELECT current_setting('server_version_num')::int >= 160000 AS soft_errors \gset
+\if :soft_errors
+SELECT jsonschema_register('{"$id": "https://example.com/soft/user", "type": "object", "required": ["name"], "properties": {"name": {"type": "string"}}}');
+CREATE DOMAIN soft_user AS jsonb CHECK (jsonschema_validates(VALUE, '{"$id": "https://example.com/soft/user", "type": "object", "required": ["name"], "properties": {"name": {"type": "string"}}}'::jsonb));
+
+-- Valid values
+SELECT pg_input_is_valid('{"name": "Kamala"}', 'jsonschema_doc(''https://example.com/soft/user'')');
+SELECT pg_input_is_valid('{"name": "Kamala"}', 'soft_user');
+
+-- Invalid values
+SELECT pg_input_is_valid('{"name": 42}', 'jsonschema_doc(''https://example.com/soft/user'')');
+SELECT pg_input_is_valid('{"name": 42}', 'soft_user');
+SELECT message, sql_error_code FROM pg_input_error_info('{"name": 42}', 'jsonschema_doc(''https://example.com/soft/user'')');
+SELECT detail FROM pg_input_error_info('{"name": 42}', 'jsonschema_doc(''https://example.com/soft/user'')');
+
+-- Malformed values
+SELECT pg_input_is_valid('{"name"', 'jsonschema_doc(''https://example.com/soft/user'')');
+SELECT message, sql_error_code FROM pg_input_error_info('{"name"', 'jsonschema_doc(''https://example.com/soft/user'')');
+
+-- Clean up
+DROP DOMAIN soft_user;
+SELECT jsonschema_unregister('https://example.com/soft/user');
+\endif
@@ -0,0 +1,22 @@
+-- Register versions of a subject
+SELECT jsonschema_register_version('orders', '{"type": "object", "properties": {"id": {"type": "integer"}}, "required": ["id"]}');
+SELECT jsonschema_register_version('orders', '{"type": "object", "properties": {"id": {"type": "number"}}, "required": ["id"]}');
+SELECT jsonschema_register_version('orders', '{"type": "object", "properties": {"id": {"type": "number"}}, "required": ["id"]}');
+
+-- Subjects default to BACKWARD compatibility
+SELECT jsonschema_register_version('orders', '{"type": "object", "properties": {"id": {"type": "number"}}, "required": ["id", "total"]}');
+
+-- Change the compatibility level
+SELECT jsonschema_set_compatibility('orders', 'full');
+SELECT jsonschema_register_version('orders', '{"title": "Order", "type": "object", "properties": {"id": {"type": "number"}}, "required": ["id"]}');
+SELECT jsonschema_register_version('orders', '{"type": "object", "properties": {"id": {"type": "string"}}, "required": ["id"]}');
+
+-- Validate against the latest or a specific version
+SELECT jsonschema_validates_subject('{"id": 1.5}'::jsonb, 'orders') AS latest;
+SELECT jsonschema_validates_subject('{"id": 1.5}'::json, 'orders', 1) AS v1;
+SELECT jsonschema_validates_subject('{"id": 1.5}'::jsonb, 'orders', 4);
+
+-- Versions are immutable
+SELECT version, schema->'properties'->'id'->>'type' AS id_type
+  FROM jsonschema_subject_versions WHERE subject = 'orders' ORDER BY version;
+UPDATE jsonschema_subject_versions SET schema = '{}' WHERE subject = 'orders' AND version = 1;
@@ -0,0 +1,33 @@
+-- Estimate the rows that validate from column statistics
+CREATE TABLE support_docs (doc jsonb);
+INSERT INTO support_docs
+SELECT CASE WHEN i % 10 = 0 THEN '[1]' ELSE '{"x": 1}' END::jsonb
+  FROM generate_series(1, 1000) i;
+ANALYZE support_docs;
+CREATE FUNCTION support_plan(filter text) RETURNS json
+LANGUAGE plpgsql AS $$
+DECLARE
+    plan json;
+BEGIN
+    EXECUTE 'EXPLAIN (FORMAT JSON) SELECT * FROM support_docs WHERE ' || filter INTO plan;
+    RETURN plan->0->'Plan';
+END;
+$$;
+SELECT filter, support_plan(filter)->>'Plan Rows' AS rows FROM (VALUES
+    ($$jsonschema_validates(doc, '{"type": "object"}'::jsonb)$$),
+    ($$NOT jsonschema_validates(doc, '{"type": "object"}'::json)$$),
+    ($$jsonschema_validates(doc, 'https://example.com/x', '{"type": "object"}'::jsonb)$$),
+    ($$jsonschema_validates_set(doc, 'https://example.com/x', '{"https://example.com/x": {"type": "object"}}'::jsonb)$$),
+    ($$jsonschema_validates(doc, '{"type": "object"}'::jsonb, 'V4')$$),
+    ($$jsonschema_validates(doc, '{"type": "object"}'::jsonb, true)$$),
+    ($$NOT jsonschema_validates_yaml(doc, 'type: object')$$)
+) AS t(filter);
+
+-- Estimate the cost from the number of keywords in the schemas
+SELECT (support_plan($$jsonschema_validates(doc, '{"type": "object", "required": ["x"]}'::jsonb)$$)->>'Total Cost')::float8
+     > (support_plan($$jsonschema_validates(doc, '{"type": "object"}'::jsonb)$$)->>'Total Cost')::float8 AS costlier;
+SELECT (support_plan($$jsonschema_validates_yaml(doc, '{type: object, required: [x]}')$$)->>'Total Cost')::float8
+     > (support_plan($$jsonschema_validates_yaml(doc, 'type: object')$$)->>'Total Cost')::float8 AS costlier;
+
+DROP FUNCTION support_plan(text);
+DROP TABLE support_docs;
@@ -0,0 +1,22 @@
+-- Valid schema
+SELECT jsonschema_is_valid('{"type": "object"}'::text);
+
+-- Malformed schema
+SELECT jsonschema_is_valid('{"type": "object"'::text);
+
+-- Valid JSON
+SELECT jsonschema_validates('{"x": "y"}'::text, '{"type": "object"}'::text);
+
+-- Invalid JSON
+SELECT jsonschema_validates('["x", "y"]'::text, '{"type": "object"}'::text);
+
+-- Malformed JSON
+SELECT jsonschema_validates('{"x": "y"'::text, '{"type": "object"}'::text);
+
+-- Malformed schema
+SELECT jsonschema_validates('{"x": "y"}'::text, '{"type": "object"'::text);
+
+-- NULLs
+SELECT jsonschema_is_valid(NULL::text);
+SELECT jsonschema_validates(NULL::text, '{"type": "object"}'::text);
+SELECT jsonschema_validates('{"x": "y"}'::text, NULL::text);
@@ -0,0 +1,126 @@
+-- Create a table with a validation trigger
+CREATE TABLE docs (id int PRIMARY KEY, body jsonb);
+CREATE TRIGGER validate_body BEFORE INSERT OR UPDATE ON docs
+    FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+        'column=body',
+        'schema={"$id": "https://example.com/doc", "type": "object", "properties": {"title": {"type": "string"}}}'
+    );
+
+-- Valid rows
+INSERT INTO docs VALUES (1, '{"title": "Hello"}'), (2, NULL);
+
+-- Invalid insert
+INSERT INTO docs VALUES (3, '{"title": 42}');
+
+-- Invalid update
+UPDATE docs SET body = '[]' WHERE id = 2;
+
+-- Only validate changed columns
+ALTER TABLE docs ADD COLUMN note text;
+ALTER TABLE docs DISABLE TRIGGER validate_body;
+UPDATE docs SET body = '{"title": true}' WHERE id = 2;
+ALTER TABLE docs ENABLE TRIGGER validate_body;
+UPDATE docs SET note = 'hi' WHERE id = 2;
+DROP TRIGGER validate_body ON docs;
+CREATE TRIGGER validate_body BEFORE INSERT OR UPDATE ON docs
+    FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+        'column=body',
+        'schema={"$id": "https://example.com/doc", "type": "object", "properties": {"title": {"type": "string"}}}',
+        'on_update_only_if_changed=true'
+    );
+UPDATE docs SET note = 'hi' WHERE id = 2;
+SELECT * FROM docs ORDER BY id;
+
+-- Invalid configuration
+CREATE TRIGGER bad BEFORE INSERT ON docs
+    FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger('column=body');
+INSERT INTO docs VALUES (4, '{}');
+DROP TRIGGER bad ON docs;
+
+-- Validate bulk loads once per statement
+CREATE TABLE items (id int PRIMARY KEY, body jsonb);
+CREATE TRIGGER validate_items AFTER INSERT ON items
+    REFERENCING NEW TABLE AS new_items
+    FOR EACH STATEMENT EXECUTE FUNCTION jsonschema_validate_trigger(
+        'column=body',
+        'schema={"$id": "https://example.com/doc", "type": "object", "properties": {"title": {"type": "string"}}}',
+        'max_errors=1'
+    );
+INSERT INTO items VALUES (1, '{"title": "a"}'), (2, '{"title": 2}'), (3, '[]');
+SELECT count(*) FROM items;
+
+-- Defer validation to the end of the transaction
+CREATE CONSTRAINT TRIGGER validate_items_deferred AFTER INSERT OR UPDATE ON items
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+        'column=body',
+        'schema={"$id": "https://example.com/doc", "type": "object", "properties": {"title": {"type": "string"}}}'
+    );
+DROP TRIGGER validate_items ON items;
+BEGIN;
+INSERT INTO items VALUES (4, '{"title": 4}');
+UPDATE items SET body = '{"title": "four"}' WHERE id = 4;
+COMMIT;
+BEGIN;
+INSERT INTO items VALUES (5, '{"title": 5}');
+COMMIT;
+SELECT * FROM items ORDER BY id;
+DROP TABLE items;
+
+-- Warn about invalid rows
+CREATE TABLE posts (id int PRIMARY KEY, body jsonb);
+CREATE TRIGGER validate_posts BEFORE INSERT OR UPDATE ON posts
+    FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+        'column=body',
+        'schema={"$id": "https://example.com/doc", "type": "object", "properties": {"title": {"type": "string"}}}',
+        'mode=warn'
+    );
+INSERT INTO posts VALUES (1, '{"title": 1}');
+
+-- Override the trigger mode
+SET jsonschema.trigger_mode TO 'Enforce';
+INSERT INTO posts VALUES (2, '{"title": 2}');
+RESET jsonschema.trigger_mode;
+
+-- Quarantine invalid rows
+DROP TRIGGER validate_posts ON posts;
+CREATE TRIGGER validate_posts BEFORE INSERT OR UPDATE ON posts
+    FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+        'column=body',
+        'schema={"$id": "https://example.com/doc", "type": "object", "properties": {"title": {"type": "string"}}}',
+        'mode=quarantine'
+    );
+INSERT INTO posts VALUES (3, '[]');
+SELECT * FROM posts ORDER BY id;
+SELECT relation, trigger_name, operation, row_data FROM jsonschema_quarantine;
+SELECT jsonb_pretty(errors) FROM jsonschema_quarantine;
+DELETE FROM jsonschema_quarantine;
+DROP TABLE posts;
+
+-- Validate against a registered schema and its references
+SELECT jsonschema_register('{"$id": "https://example.com/trigger/title", "type": "string"}');
+SELECT jsonschema_register('{"$id": "https://example.com/trigger/doc", "type": "object", "properties": {"title": {"$ref": "title"}}}');
+CREATE TABLE notes (id int PRIMARY KEY, body jsonb);
+CREATE TRIGGER validate_notes BEFORE INSERT ON notes
+    FOR EACH ROW EXECUTE FUNCTION jsonschema_validate_trigger(
+        'column=body',
+        'schema_id=https://example.com/trigger/doc'
+    );
+INSERT INTO notes VALUES (1, '{"title": "Hello"}');
+INSERT INTO notes VALUES (2, '{"title": 2}');
+DROP TABLE notes;
+SELECT jsonschema_unregister('https://example.com/trigger/doc');
+SELECT jsonschema_unregister('https://example.com/trigger/title');
+
+-- Only superusers may override the trigger mode
+CREATE ROLE jsonschema_trigger_writer;
+GRANT INSERT ON docs TO jsonschema_trigger_writer;
+SET ROLE jsonschema_trigger_writer;
+SET jsonschema.trigger_mode TO 'Warn';
+INSERT INTO docs VALUES (6, '{"title": 6}');
+RESET ROLE;
+REVOKE INSERT ON docs FROM jsonschema_trigger_writer;
+DROP ROLE jsonschema_trigger_writer;
+
+-- Clean up
+DROP TABLE docs;
@@ -0,0 +1,96 @@
+-- Test the upgrade path in a database of its own
+SELECT current_database() AS regress_db \gset
+CREATE DATABASE jsonschema_upgrade;
+\c jsonschema_upgrade
+
+-- Record the objects of a new installation
+CREATE EXTENSION jsonschema;
+CREATE FUNCTION upgrade_objects(OUT object text, OUT detail text) RETURNS SETOF record
+LANGUAGE sql AS $$
+    SELECT pg_describe_object(d.classid, d.objid, 0), concat_ws(' -- ', CASE d.classid
+        WHEN 'pg_proc'::regclass THEN (
+            SELECT CASE WHEN p.prokind = 'a' THEN concat_ws(' ', a.aggtransfn, a.aggfinalfn, a.aggtranstype::regtype, a.agginitval)
+                        ELSE pg_get_functiondef(p.oid) END || coalesce(' ' || p.proacl::text, '')
+              FROM pg_proc p LEFT JOIN pg_aggregate a ON a.aggfnoid = p.oid
+             WHERE p.oid = d.objid
+        )
+        WHEN 'pg_class'::regclass THEN (
+            SELECT concat_ws('; ',
+                string_agg(format('%I %s%s%s', a.attname, format_type(a.atttypid, a.atttypmod),
+                    CASE WHEN a.attnotnull THEN ' NOT NULL' END,
+                    ' DEFAULT ' || pg_get_expr(ad.adbin, ad.adrelid)), ', ' ORDER BY a.attnum),
+                (SELECT string_agg(pg_get_constraintdef(c.oid), '; ' ORDER BY c.conname)
+                   FROM pg_constraint c WHERE c.conrelid = d.objid),
+                (SELECT string_agg(pg_get_indexdef(i.indexrelid), '; ' ORDER BY i.indexrelid::regclass::text)
+                   FROM pg_index i WHERE i.indrelid = d.objid),
+                (SELECT string_agg(pg_get_triggerdef(t.oid), '; ' ORDER BY t.tgname)
+                   FROM pg_trigger t WHERE t.tgrelid = d.objid AND NOT t.tgisinternal),
+                (SELECT relacl::text FROM pg_class WHERE oid = d.objid)
+            )
+              FROM pg_attribute a
+              LEFT JOIN pg_attrdef ad ON ad.adrelid = a.attrelid AND ad.adnum = a.attnum
+             WHERE a.attrelid = d.objid AND a.attnum > 0 AND NOT a.attisdropped
+        )
+        WHEN 'pg_type'::regclass THEN (
+            SELECT concat_ws(' ', t.typtype, format_type(nullif(t.typbasetype, 0), t.typtypmod),
+                   t.typinput, t.typoutput, t.typreceive, t.typsend, t.typmodin, t.typmodout,
+                   t.typlen, t.typalign, t.typstorage, t.typcategory, t.typispreferred,
+                   (SELECT string_agg(e.enumlabel::text, ', ' ORDER BY e.enumsortorder)
+                      FROM pg_enum e WHERE e.enumtypid = t.oid),
+                   (SELECT string_agg(pg_get_constraintdef(c.oid), '; ' ORDER BY c.conname)
+                      FROM pg_constraint c WHERE c.contypid = t.oid))
+              FROM pg_type t WHERE t.oid = d.objid
+        )
+        WHEN 'pg_operator'::regclass THEN (
+            SELECT concat_ws(' ', o.oprcode, o.oprcom::regoperator, o.oprnegate::regoperator,
+                   o.oprrest, o.oprjoin, o.oprcanhash, o.oprcanmerge)
+              FROM pg_operator o WHERE o.oid = d.objid
+        )
+        WHEN 'pg_cast'::regclass THEN (
+            SELECT concat_ws(' ', c.castfunc::regprocedure, c.castcontext, c.castmethod)
+              FROM pg_cast c WHERE c.oid = d.objid
+        )
+    END, obj_description(d.objid, d.classid::regclass::text))
+      FROM pg_depend d
+     WHERE d.refclassid = 'pg_extension'::regclass
+       AND d.refobjid = (SELECT oid FROM pg_extension WHERE extname = 'jsonschema')
+       AND d.deptype = 'e'
+    UNION ALL
+    SELECT 'configuration tables', string_agg(
+               c.tbl::regclass::text || coalesce(' ' || nullif(c.cond, ''), ''), ', '
+               ORDER BY c.tbl::regclass::text
+           )
+      FROM pg_extension e, unnest(e.extconfig, e.extcondition) AS c(tbl, cond)
+     WHERE e.extname = 'jsonschema'
+$$;
+CREATE TABLE upgrade_installed AS SELECT * FROM upgrade_objects();
+SELECT count(*) > 0 AS recorded FROM upgrade_installed;
+DROP EXTENSION jsonschema;
+
+-- Install v0.1.6 and update it
+CREATE EXTENSION jsonschema VERSION '0.1.6';
+SELECT extversion FROM pg_extension WHERE extname = 'jsonschema';
+
+-- The update refuses to run while an index uses a function that becomes STABLE
+CREATE TABLE upgrade_docs (doc jsonb);
+CREATE INDEX upgrade_docs_valid ON upgrade_docs (jsonschema_validates(doc, '{"type": "object"}'::jsonb));
+\set SHOW_CONTEXT never
+ALTER EXTENSION jsonschema UPDATE;
+\set SHOW_CONTEXT errors
+SELECT extversion FROM pg_extension WHERE extname = 'jsonschema';
+DROP TABLE upgrade_docs;
+ALTER EXTENSION jsonschema UPDATE;
+SELECT e.extversion = a.default_version AS updated
+  FROM pg_extension e
+  JOIN pg_available_extensions a ON a.name = e.extname
+ WHERE e.extname = 'jsonschema';
+
+-- The update creates the same objects as a new installation
+SELECT 'missing' AS diff, * FROM (TABLE upgrade_installed EXCEPT SELECT * FROM upgrade_objects()) m
+UNION ALL
+SELECT 'extra', * FROM (SELECT * FROM upgrade_objects() EXCEPT TABLE upgrade_installed) x
+ORDER BY object, diff;
+
+-- Clean up
+\c :regress_db
+DROP DATABASE jsonschema_upgrade WITH (FORCE);
@@ -0,0 +1,30 @@
+-- Register versioned schemas
+SELECT jsonschema_register('{"$id": "https://example.com/event/v1", "type": "object", "properties": {"x": {"type": "integer"}}}');
+SELECT jsonschema_register('{"$id": "https://example.com/event/v2", "type": "object", "properties": {"x": {"type": "string"}}}');
+
+-- Validate against the schema named by $schema
+SELECT jsonschema_validates_self('{"$schema": "https://example.com/event/v1", "x": 1}'::jsonb);
+SELECT jsonschema_validates_self('{"$schema": "https://example.com/event/v2", "x": 1}'::json);
+SELECT jsonschema_validates_self('{"$schema": "https://example.com/event/v2#", "x": "a"}'::jsonb);
+
+-- Name the schema with another property
+SELECT jsonschema_validates_self('{"schemaVersion": "https://example.com/event/v1", "x": 1}'::jsonb, 'schemaVersion');
+
+-- Documents without the property fail validation
+SELECT jsonschema_validates_self('{"x": 1}'::jsonb);
+
+-- Unknown schemas raise an error
+SELECT jsonschema_validates_self('{"$schema": "https://example.com/event/v3"}'::jsonb);
+
+-- Check a column of many document types and versions
+CREATE TABLE messages (id int, payload jsonb CHECK (jsonschema_validates_self(payload)));
+INSERT INTO messages VALUES
+    (1, '{"$schema": "https://example.com/event/v1", "x": 1}'),
+    (2, '{"$schema": "https://example.com/event/v2", "x": "a"}');
+INSERT INTO messages VALUES (3, '{"$schema": "https://example.com/event/v1", "x": "a"}');
+SELECT id FROM messages ORDER BY id;
+
+-- Clean up
+DROP TABLE messages;
+SELECT jsonschema_unregister('https://example.com/event/v1');
+SELECT jsonschema_unregister('https://example.com/event/v2');
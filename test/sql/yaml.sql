@@ -0,0 +1,41 @@
+-- Valid schema
+SELECT jsonschema_is_valid_yaml(E'type: object\nproperties:\n  x: {type: string}\n');
+
+-- Malformed schema
+SELECT jsonschema_is_valid_yaml('{type: object');
+
+-- Invalid schema
+SELECT jsonschema_is_valid_yaml('[type, object]');
+
+-- Valid YAML
+SELECT jsonschema_validates_yaml('x: y', 'type: object');
+
+-- Invalid YAML
+SELECT jsonschema_validates_yaml('[x, y]', 'type: object');
+
+-- Malformed YAML
+SELECT jsonschema_validates_yaml(E'type: object\n  nope: [', 'type: object');
+
+-- Malformed schema
+SELECT jsonschema_validates_yaml('x: y', '{type: object');
+
+-- YAML data, JSON schema
+SELECT jsonschema_validates_yaml('x: y', '{"type": "object"}'::json);
+SELECT jsonschema_validates_yaml('[x, y]', '{"type": "object"}'::jsonb);
+
+-- JSON data, YAML schema
+SELECT jsonschema_validates_yaml('{"x": "y"}'::json, 'type: object');
+SELECT jsonschema_validates_yaml('["x", "y"]'::jsonb, 'type: object');
+
+-- Multiple schemas
+SELECT jsonschema_validates_yaml(
+    E'name: Theory\naddress: {locality: Portland}\n',
+    'https://example.com/person.schema.yaml',
+    E'$id: https://example.com/person.schema.yaml\ntype: object\nproperties:\n  name: {type: string}\n  address: {$ref: address.schema.yaml}\n',
+    E'$id: https://example.com/address.schema.yaml\ntype: object\nrequired: [locality]\nproperties:\n  locality: {type: string}\n'
+);
+
+-- NULLs
+SELECT jsonschema_is_valid_yaml(NULL);
+SELECT jsonschema_validates_yaml(NULL, 'type: object');
+SELECT jsonschema_validates_yaml('x: y', NULL);